@@ -0,0 +1,257 @@
+use std::ops::Range;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextDirection {
+    Ltr,
+    Rtl,
+}
+
+fn is_rtl_char(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x0590..=0x08FF | 0xFB1D..=0xFDFF | 0xFE70..=0xFEFF | 0x10800..=0x10FFF | 0x1E800..=0x1EFFF
+    ) || c == '\u{200F}'
+}
+
+fn strong_direction(c: char) -> Option<TextDirection> {
+    if c == '\u{200E}' {
+        return Some(TextDirection::Ltr);
+    }
+    if !c.is_alphabetic() && c != '\u{200F}' {
+        return None;
+    }
+    Some(if is_rtl_char(c) {
+        TextDirection::Rtl
+    } else {
+        TextDirection::Ltr
+    })
+}
+
+/// Resolves a line's base direction from its first strong character,
+/// defaulting to left-to-right for lines without one.
+pub fn line_direction(text: &str) -> TextDirection {
+    text.chars()
+        .find_map(strong_direction)
+        .unwrap_or(TextDirection::Ltr)
+}
+
+#[derive(Clone, Debug)]
+struct CharSpan {
+    index: usize,
+    left: f32,
+    right: f32,
+    rtl: bool,
+}
+
+impl CharSpan {
+    /// The caret position before this character, which is its right edge
+    /// when it runs right-to-left.
+    fn caret_x(&self) -> f32 {
+        if self.rtl { self.right } else { self.left }
+    }
+}
+
+/// Caret and selection geometry for one shaped line, in x positions relative
+/// to the left edge of the text area. Glyph positions come from the shaper
+/// in visual order, so carets are placed per character instead of assuming
+/// x grows with the byte offset.
+#[derive(Clone, Debug)]
+pub struct LineGeometry {
+    pub direction: TextDirection,
+    /// Shift applied to the whole line; right-to-left lines are right-aligned.
+    pub origin_x: f32,
+    len: usize,
+    spans: Vec<CharSpan>,
+}
+
+impl LineGeometry {
+    /// `glyphs` are `(byte index, x)` pairs as reported by the shaper and
+    /// `width` is the shaped line's advance.
+    pub fn new(
+        text: &str,
+        glyphs: impl IntoIterator<Item = (usize, f32)>,
+        width: f32,
+        available_width: f32,
+    ) -> Self {
+        let direction = line_direction(text);
+        let origin_x = match direction {
+            TextDirection::Ltr => 0.,
+            TextDirection::Rtl => (available_width - width).max(0.),
+        };
+
+        let mut glyphs: Vec<(usize, f32)> = glyphs.into_iter().collect();
+        glyphs.sort_by(|a, b| a.1.total_cmp(&b.1));
+        // Each glyph ends where the next one starts visually; after that
+        // they're looked up by byte index. The sort is stable, so a cluster
+        // shaped into several glyphs still finds its leftmost one first.
+        let mut glyph_edges: Vec<(usize, f32, f32)> = glyphs
+            .iter()
+            .enumerate()
+            .map(|(position, &(index, left))| {
+                let right = glyphs.get(position + 1).map_or(width, |next| next.1);
+                (index, left, right)
+            })
+            .collect();
+        glyph_edges.sort_by_key(|&(index, _, _)| index);
+
+        let mut spans: Vec<CharSpan> = Vec::new();
+        for (index, c) in text.char_indices() {
+            let position = glyph_edges.partition_point(|&(glyph_index, _, _)| glyph_index < index);
+            let edges = glyph_edges
+                .get(position)
+                .filter(|&&(glyph_index, _, _)| glyph_index == index)
+                .map(|&(_, left, right)| (left, right))
+                .or_else(|| {
+                    // Characters folded into a previous glyph's cluster
+                    // share its edges.
+                    spans.last().map(|span| (span.left, span.right))
+                })
+                .unwrap_or((0., 0.));
+            let rtl = match strong_direction(c) {
+                Some(direction) => direction == TextDirection::Rtl,
+                None if c.is_ascii_digit() => false,
+                None => direction == TextDirection::Rtl,
+            };
+            spans.push(CharSpan {
+                index,
+                left: origin_x + edges.0,
+                right: origin_x + edges.1,
+                rtl,
+            });
+        }
+
+        Self {
+            direction,
+            origin_x,
+            len: text.len(),
+            spans,
+        }
+    }
+
+    /// Byte length of the line's source text.
+    pub fn text_len(&self) -> usize {
+        self.len
+    }
+
+    /// The caret position before the character at `index`, or after the last
+    /// character when `index` is the line length.
+    pub fn x_for_index(&self, index: usize) -> f32 {
+        let position = self.spans.partition_point(|span| span.index < index);
+        match self.spans.get(position) {
+            Some(span) => span.caret_x(),
+            None => self.end_x(),
+        }
+    }
+
+    /// The caret offset closest to `x`. Always lands on a char boundary.
+    pub fn index_for_x(&self, x: f32) -> usize {
+        self.spans
+            .iter()
+            .map(|span| (span.index, span.caret_x()))
+            .chain(std::iter::once((self.len, self.end_x())))
+            .min_by(|a, b| (a.1 - x).abs().total_cmp(&(b.1 - x).abs()))
+            .map_or(0, |(index, _)| index)
+    }
+
+    /// The caret position after the last character.
+    fn end_x(&self) -> f32 {
+        match self.spans.last() {
+            Some(span) if span.rtl => span.left,
+            Some(span) => span.right,
+            None => self.origin_x,
+        }
+    }
+
+    /// Horizontal extents covering the characters in `range`. A logical
+    /// range over mixed-direction text can be visually split in several parts.
+    pub fn selection_spans(&self, range: Range<usize>) -> Vec<(f32, f32)> {
+        let mut extents: Vec<(f32, f32)> = self
+            .spans
+            .iter()
+            .filter(|span| range.start <= span.index && span.index < range.end)
+            .map(|span| (span.left, span.right))
+            .collect();
+        extents.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut merged: Vec<(f32, f32)> = Vec::new();
+        for (left, right) in extents {
+            match merged.last_mut() {
+                Some(last) if left <= last.1 => last.1 = last.1.max(right),
+                _ => merged.push((left, right)),
+            }
+        }
+        merged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn direction_follows_first_strong_character() {
+        assert_eq!(line_direction("hello שלום"), TextDirection::Ltr);
+        assert_eq!(line_direction("שלום hello"), TextDirection::Rtl);
+        assert_eq!(line_direction("12 - مرحبا"), TextDirection::Rtl);
+        assert_eq!(line_direction("  123 "), TextDirection::Ltr);
+        assert_eq!(line_direction(""), TextDirection::Ltr);
+    }
+
+    #[test]
+    fn mixed_line_places_carets_by_character_direction() {
+        // "ab אב": the Hebrew run is shaped right-to-left, so bet sits to the
+        // left of alef on screen.
+        let text = "ab אב";
+        let glyphs = [(0, 0.), (1, 10.), (2, 20.), (5, 30.), (3, 40.)];
+        let geometry = LineGeometry::new(text, glyphs, 50., 200.);
+
+        assert_eq!(geometry.direction, TextDirection::Ltr);
+        assert_eq!(geometry.x_for_index(0), 0.);
+        assert_eq!(geometry.x_for_index(2), 20.);
+        assert_eq!(geometry.x_for_index(3), 50.);
+        assert_eq!(geometry.x_for_index(5), 40.);
+        assert_eq!(geometry.x_for_index(text.len()), 30.);
+
+        assert_eq!(geometry.index_for_x(48.), 3);
+        assert_eq!(geometry.index_for_x(41.), 5);
+        assert_eq!(geometry.selection_spans(3..text.len()), vec![(30., 50.)]);
+        assert_eq!(geometry.selection_spans(1..5), vec![(10., 30.), (40., 50.)]);
+
+        for x in 0..60 {
+            assert!(text.is_char_boundary(geometry.index_for_x(x as f32)));
+        }
+    }
+
+    #[test]
+    fn proportional_glyphs_put_carets_on_their_own_edges() {
+        // A proportional font gives "Wim" three different advances.
+        let text = "Wim";
+        let glyphs = [(0, 0.), (1, 14.), (2, 18.)];
+        let geometry = LineGeometry::new(text, glyphs, 30., 200.);
+
+        assert_eq!(geometry.x_for_index(0), 0.);
+        assert_eq!(geometry.x_for_index(1), 14.);
+        assert_eq!(geometry.x_for_index(2), 18.);
+        assert_eq!(geometry.x_for_index(3), 30.);
+
+        assert_eq!(geometry.index_for_x(15.), 1);
+        assert_eq!(geometry.index_for_x(17.), 2);
+        assert_eq!(geometry.index_for_x(25.), 3);
+        assert_eq!(geometry.selection_spans(1..2), vec![(14., 18.)]);
+    }
+
+    #[test]
+    fn rtl_line_is_right_aligned() {
+        // "אב 1": the digit keeps its left-to-right run at the visual start.
+        let text = "אב 1";
+        let glyphs = [(5, 0.), (4, 10.), (2, 20.), (0, 30.)];
+        let geometry = LineGeometry::new(text, glyphs, 40., 100.);
+
+        assert_eq!(geometry.direction, TextDirection::Rtl);
+        assert_eq!(geometry.origin_x, 60.);
+        assert_eq!(geometry.x_for_index(0), 100.);
+        assert_eq!(geometry.x_for_index(2), 90.);
+        assert_eq!(geometry.index_for_x(99.), 0);
+        assert_eq!(geometry.x_for_index(text.len()), 70.);
+    }
+}
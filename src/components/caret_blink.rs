@@ -0,0 +1,152 @@
+use gpui::Context;
+use std::time::Duration;
+
+const CARET_BLINK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Blink state for a text editor's caret. The timer only runs while the
+/// editor has focus; an unfocused editor draws no caret, so it has nothing
+/// to blink.
+#[derive(Debug, Default)]
+pub struct CaretBlink {
+    hidden: bool,
+    focused: bool,
+    running: bool,
+    /// Bumped when the blink starts over, so ticks from an older timer are
+    /// ignored.
+    epoch: usize,
+    /// Keeps the caret solid instead of blinking.
+    reduce_motion: bool,
+}
+
+impl CaretBlink {
+    pub fn visible(&self) -> bool {
+        !self.hidden
+    }
+
+    /// Shows the caret solid and starts the blink over on the next render.
+    pub fn pause(&mut self) {
+        self.hidden = false;
+        self.running = false;
+        self.epoch += 1;
+    }
+
+    pub fn set_reduce_motion(&mut self, reduce_motion: bool) {
+        self.reduce_motion = reduce_motion;
+        self.pause();
+    }
+
+    /// Records whether the editor has focus. Returns the epoch to start a
+    /// timer for when the blink should start running.
+    fn start(&mut self, focused: bool) -> Option<usize> {
+        self.focused = focused;
+        if !focused || self.running || self.reduce_motion {
+            return None;
+        }
+        self.running = true;
+        Some(self.epoch)
+    }
+
+    /// Toggles the caret for a timer tick. Returns whether that timer should
+    /// keep going.
+    fn tick(&mut self, epoch: usize) -> bool {
+        if !self.running || epoch != self.epoch {
+            return false;
+        }
+        if !self.focused {
+            self.running = false;
+            self.hidden = false;
+            return false;
+        }
+        self.hidden = !self.hidden;
+        true
+    }
+}
+
+/// Implemented by the editors that draw a blinking caret.
+pub trait BlinkingCaret: Sized + 'static {
+    fn caret_blink(&mut self) -> &mut CaretBlink;
+
+    fn pause_caret_blink(&mut self, cx: &mut Context<Self>) {
+        self.caret_blink().pause();
+        cx.notify();
+    }
+
+    fn set_reduce_motion(&mut self, reduce_motion: bool, cx: &mut Context<Self>) {
+        self.caret_blink().set_reduce_motion(reduce_motion);
+        cx.notify();
+    }
+
+    /// Called from `render`, which runs again whenever focus moves. Starts
+    /// the timer once the editor is focused; it stops by itself at the first
+    /// tick after focus leaves.
+    fn sync_caret_blink(&mut self, focused: bool, cx: &mut Context<Self>) {
+        if let Some(epoch) = self.caret_blink().start(focused) {
+            schedule_blink(epoch, cx);
+        }
+    }
+}
+
+fn schedule_blink<T: BlinkingCaret>(epoch: usize, cx: &mut Context<T>) {
+    cx.spawn(async move |this, cx| {
+        cx.background_executor().timer(CARET_BLINK_INTERVAL).await;
+        this.update(cx, |editor, cx| {
+            if editor.caret_blink().tick(epoch) {
+                schedule_blink(epoch, cx);
+                cx.notify();
+            }
+        })
+        .ok();
+    })
+    .detach();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blinks_only_while_focused() {
+        let mut blink = CaretBlink::default();
+        assert_eq!(blink.start(false), None);
+
+        let epoch = blink.start(true).unwrap();
+        assert_eq!(blink.start(true), None, "one timer at a time");
+        assert!(blink.tick(epoch));
+        assert!(!blink.visible());
+
+        blink.start(false);
+        assert!(!blink.tick(epoch));
+        assert!(blink.visible());
+        assert_eq!(blink.start(false), None);
+        assert!(blink.start(true).is_some());
+    }
+
+    #[test]
+    fn pausing_drops_the_running_timer() {
+        let mut blink = CaretBlink::default();
+        let epoch = blink.start(true).unwrap();
+        assert!(blink.tick(epoch));
+
+        blink.pause();
+        assert!(blink.visible());
+        assert!(!blink.tick(epoch));
+        let restarted = blink.start(true).unwrap();
+        assert_ne!(restarted, epoch);
+        assert!(blink.tick(restarted));
+    }
+
+    #[test]
+    fn reduce_motion_keeps_the_caret_solid() {
+        let mut blink = CaretBlink::default();
+        let epoch = blink.start(true).unwrap();
+        assert!(blink.tick(epoch));
+
+        blink.set_reduce_motion(true);
+        assert!(blink.visible());
+        assert!(!blink.tick(epoch));
+        assert_eq!(blink.start(true), None);
+
+        blink.set_reduce_motion(false);
+        assert!(blink.start(true).is_some());
+    }
+}
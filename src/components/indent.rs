@@ -0,0 +1,265 @@
+use crate::models::IndentStyle;
+use std::collections::HashMap;
+
+fn next_tab_stop(column: usize, tab_width: usize) -> usize {
+    let tab_width = tab_width.max(1);
+    (column / tab_width + 1) * tab_width
+}
+
+/// The leading spaces and tabs of `line`.
+pub fn leading_indent(line: &str) -> &str {
+    let rest = line.trim_start_matches([' ', '\t']);
+    &line[..line.len() - rest.len()]
+}
+
+/// Visual width of `indent` in columns, with each tab advancing to the next
+/// tab stop.
+pub fn indent_columns(indent: &str, tab_width: usize) -> usize {
+    indent.chars().fold(0, |column, c| match c {
+        '\t' => next_tab_stop(column, tab_width),
+        _ => column + 1,
+    })
+}
+
+/// Indentation `columns` wide written in `style`. Tab style fills whole tab
+/// stops with tabs and pads any remainder with spaces.
+pub fn indent_for_columns(columns: usize, style: IndentStyle, tab_width: usize) -> String {
+    match style {
+        IndentStyle::Spaces(_) | IndentStyle::Auto => " ".repeat(columns),
+        IndentStyle::Tab => {
+            let tab_width = tab_width.max(1);
+            let mut indent = "\t".repeat(columns / tab_width);
+            indent.push_str(&" ".repeat(columns % tab_width));
+            indent
+        }
+    }
+}
+
+/// Rewrites existing, possibly mixed, indentation in `style` at the same
+/// visual width.
+pub fn normalize_indent(indent: &str, style: IndentStyle, tab_width: usize) -> String {
+    indent_for_columns(indent_columns(indent, tab_width), style, tab_width)
+}
+
+/// What the Tab key inserts with the caret at visual `column`.
+pub fn indent_unit(style: IndentStyle, column: usize) -> String {
+    match style {
+        IndentStyle::Spaces(width) => {
+            let width = width.max(1);
+            " ".repeat(width - column % width)
+        }
+        IndentStyle::Tab => "\t".to_string(),
+        IndentStyle::Auto => indent_unit(IndentStyle::default(), column),
+    }
+}
+
+/// `indent` with one level removed, written in `style`.
+pub fn outdent(indent: &str, style: IndentStyle, tab_width: usize) -> String {
+    let step = match style {
+        IndentStyle::Spaces(width) => width.max(1),
+        IndentStyle::Tab => tab_width.max(1),
+        IndentStyle::Auto => return outdent(indent, IndentStyle::default(), tab_width),
+    };
+    let columns = indent_columns(indent, tab_width);
+    let outdented = columns.saturating_sub(1) / step * step;
+    indent_for_columns(outdented, style, tab_width)
+}
+
+/// The indentation `content` predominantly uses: tabs when more lines are
+/// indented with tabs than with spaces, otherwise spaces at the most common
+/// step between a line and a more indented one below it. Content without
+/// indentation gets the default style.
+pub fn detect_indent(content: &str) -> IndentStyle {
+    let mut tab_lines = 0;
+    let mut space_lines = 0;
+    let mut smallest_indent: Option<usize> = None;
+    let mut step_counts: HashMap<usize, usize> = HashMap::new();
+    let mut previous_spaces = 0;
+
+    for line in content.lines().filter(|line| !line.trim().is_empty()) {
+        let indent = leading_indent(line);
+        if indent.starts_with('\t') {
+            tab_lines += 1;
+            previous_spaces = 0;
+            continue;
+        }
+
+        let spaces = indent.len() - indent.trim_start_matches(' ').len();
+        if spaces > 1 {
+            space_lines += 1;
+            smallest_indent = Some(smallest_indent.map_or(spaces, |smallest| smallest.min(spaces)));
+        }
+        let step = spaces.saturating_sub(previous_spaces);
+        if (2..=8).contains(&step) {
+            *step_counts.entry(step).or_default() += 1;
+        }
+        previous_spaces = spaces;
+    }
+
+    if tab_lines > space_lines {
+        return IndentStyle::Tab;
+    }
+    let most_common_step = step_counts
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))
+        .map(|(step, _)| step);
+    match most_common_step.or(smallest_indent.map(|width| width.min(8))) {
+        Some(width) => IndentStyle::Spaces(width),
+        None => IndentStyle::default(),
+    }
+}
+
+/// `style` with `Auto` replaced by what `content` uses.
+pub fn resolve_indent_style(style: IndentStyle, content: &str) -> IndentStyle {
+    match style {
+        IndentStyle::Auto => detect_indent(content),
+        style => style,
+    }
+}
+
+/// A line with its tabs expanded to spaces for shaping, so tabs render at
+/// the configured width, plus the mapping back to the original offsets.
+pub struct ExpandedLine {
+    pub text: String,
+    /// Display offset of every source byte, and of the end of the line.
+    display_offsets: Vec<usize>,
+    /// `(display, source)` start offsets of each source character.
+    char_starts: Vec<(usize, usize)>,
+}
+
+impl ExpandedLine {
+    pub fn new(line: &str, tab_width: usize) -> Self {
+        let mut text = String::with_capacity(line.len());
+        let mut display_offsets = vec![0; line.len() + 1];
+        let mut char_starts = Vec::new();
+        let mut column = 0;
+
+        for (index, c) in line.char_indices() {
+            char_starts.push((text.len(), index));
+            for offset in &mut display_offsets[index..index + c.len_utf8()] {
+                *offset = text.len();
+            }
+
+            if c == '\t' {
+                let stop = next_tab_stop(column, tab_width);
+                text.push_str(&" ".repeat(stop - column));
+                column = stop;
+            } else {
+                text.push(c);
+                column += 1;
+            }
+        }
+        display_offsets[line.len()] = text.len();
+
+        Self {
+            text,
+            display_offsets,
+            char_starts,
+        }
+    }
+
+    pub fn display_offset(&self, source_offset: usize) -> usize {
+        self.display_offsets[source_offset.min(self.display_offsets.len() - 1)]
+    }
+
+    /// The source offset of the character whose display text starts at
+    /// `display_offset`. The extra spaces of an expanded tab have none.
+    pub fn source_index(&self, display_offset: usize) -> Option<usize> {
+        self.char_starts
+            .binary_search_by_key(&display_offset, |&(display, _)| display)
+            .ok()
+            .map(|position| self.char_starts[position].1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mixed_indentation_is_measured_by_tab_stops() {
+        assert_eq!(indent_columns("", 4), 0);
+        assert_eq!(indent_columns("    ", 4), 4);
+        assert_eq!(indent_columns("\t", 4), 4);
+        assert_eq!(indent_columns("  \t", 4), 4);
+        assert_eq!(indent_columns("\t  ", 4), 6);
+        assert_eq!(indent_columns(" \t \t", 8), 16);
+    }
+
+    #[test]
+    fn space_style_normalizes_and_indents_with_spaces() {
+        let style = IndentStyle::Spaces(4);
+        assert_eq!(normalize_indent("\t  ", style, 4), "      ");
+        assert_eq!(indent_unit(style, 0), "    ");
+        assert_eq!(indent_unit(style, 6), "  ");
+        assert_eq!(outdent("      ", style, 4), "    ");
+        assert_eq!(outdent("\t\t", style, 4), "    ");
+        assert_eq!(outdent("  ", style, 4), "");
+    }
+
+    #[test]
+    fn tab_style_normalizes_and_indents_with_tabs() {
+        let style = IndentStyle::Tab;
+        assert_eq!(normalize_indent("        ", style, 4), "\t\t");
+        assert_eq!(normalize_indent("  \t  ", style, 4), "\t  ");
+        assert_eq!(indent_unit(style, 3), "\t");
+        assert_eq!(outdent("\t\t", style, 4), "\t");
+        assert_eq!(outdent("    \t", style, 4), "\t");
+    }
+
+    #[test]
+    fn detects_the_predominant_indentation() {
+        assert_eq!(detect_indent(""), IndentStyle::default());
+        assert_eq!(detect_indent("plain\ntext\n"), IndentStyle::default());
+        assert_eq!(
+            detect_indent("- a\n  - b\n    - c\n  - d\n"),
+            IndentStyle::Spaces(2)
+        );
+        assert_eq!(
+            detect_indent("fn main() {\n    if x {\n        y();\n    }\n}\n"),
+            IndentStyle::Spaces(4)
+        );
+        assert_eq!(
+            detect_indent("a:\n\tb\n\t\tc\n  d\n\te\n"),
+            IndentStyle::Tab
+        );
+        // Only ever indented once, at an uneven depth.
+        assert_eq!(
+            detect_indent("note\n\n   quoted\n   more\n"),
+            IndentStyle::Spaces(3)
+        );
+        // A single-space continuation says nothing about the style.
+        assert_eq!(detect_indent("a\n b\n"), IndentStyle::default());
+    }
+
+    #[test]
+    fn auto_style_resolves_against_the_content() {
+        let tabbed = "a\n\tb\n";
+        assert_eq!(
+            resolve_indent_style(IndentStyle::Auto, tabbed),
+            IndentStyle::Tab
+        );
+        assert_eq!(
+            resolve_indent_style(IndentStyle::Spaces(2), tabbed),
+            IndentStyle::Spaces(2)
+        );
+    }
+
+    #[test]
+    fn expanded_lines_map_offsets_both_ways() {
+        let line = "a\tb\té";
+        let expanded = ExpandedLine::new(line, 4);
+        assert_eq!(expanded.text, "a   b   é");
+
+        assert_eq!(expanded.display_offset(0), 0);
+        assert_eq!(expanded.display_offset(1), 1);
+        assert_eq!(expanded.display_offset(2), 4);
+        assert_eq!(expanded.display_offset(4), 8);
+        assert_eq!(expanded.display_offset(line.len()), expanded.text.len());
+
+        assert_eq!(expanded.source_index(1), Some(1));
+        assert_eq!(expanded.source_index(2), None);
+        assert_eq!(expanded.source_index(4), Some(2));
+        assert_eq!(expanded.source_index(8), Some(4));
+    }
+}
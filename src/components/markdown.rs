@@ -0,0 +1,430 @@
+use crate::components::indent::{indent_columns, leading_indent};
+use std::ops::Range;
+
+/// A block of a note's markdown as the preview lays it out.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MarkdownBlock {
+    Heading {
+        level: usize,
+        text: String,
+    },
+    Paragraph(String),
+    /// `marker` is what the preview shows in front of the item: a bullet,
+    /// the item's number, or a checkbox.
+    ListItem {
+        depth: usize,
+        marker: String,
+        text: String,
+    },
+    Quote(String),
+    CodeBlock(String),
+    Rule,
+}
+
+/// A run of text sharing one inline style.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InlineSpan {
+    pub text: String,
+    pub bold: bool,
+    pub italic: bool,
+    pub code: bool,
+    pub link: bool,
+    /// Part of a search match.
+    pub highlight: bool,
+}
+
+impl MarkdownBlock {
+    /// The text the preview shows for the block, without markup.
+    pub fn rendered_text(&self) -> String {
+        match self {
+            MarkdownBlock::Heading { text, .. }
+            | MarkdownBlock::Paragraph(text)
+            | MarkdownBlock::ListItem { text, .. }
+            | MarkdownBlock::Quote(text) => {
+                parse_inline(text).into_iter().map(|span| span.text).collect()
+            }
+            MarkdownBlock::CodeBlock(code) => code.clone(),
+            MarkdownBlock::Rule => String::new(),
+        }
+    }
+}
+
+/// Splits `text` into blocks. Consecutive lines of a paragraph or quote are
+/// joined with spaces; a fence left open runs to the end of the note.
+pub fn parse_markdown(text: &str) -> Vec<MarkdownBlock> {
+    let mut blocks = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut quote: Vec<&str> = Vec::new();
+    let mut code: Option<Vec<&str>> = None;
+
+    fn flush(blocks: &mut Vec<MarkdownBlock>, paragraph: &mut Vec<&str>, quote: &mut Vec<&str>) {
+        if !paragraph.is_empty() {
+            blocks.push(MarkdownBlock::Paragraph(paragraph.join(" ")));
+            paragraph.clear();
+        }
+        if !quote.is_empty() {
+            blocks.push(MarkdownBlock::Quote(quote.join(" ")));
+            quote.clear();
+        }
+    }
+
+    for line in text.lines() {
+        let trimmed = line.trim();
+
+        if let Some(code_lines) = code.as_mut() {
+            if trimmed.starts_with("```") {
+                blocks.push(MarkdownBlock::CodeBlock(code_lines.join("\n")));
+                code = None;
+            } else {
+                code_lines.push(line);
+            }
+            continue;
+        }
+
+        if trimmed.starts_with("```") {
+            flush(&mut blocks, &mut paragraph, &mut quote);
+            code = Some(Vec::new());
+        } else if trimmed.is_empty() {
+            flush(&mut blocks, &mut paragraph, &mut quote);
+        } else if let Some(quoted) = trimmed.strip_prefix('>') {
+            if !paragraph.is_empty() {
+                flush(&mut blocks, &mut paragraph, &mut quote);
+            }
+            quote.push(quoted.trim());
+        } else if let Some(heading) = heading(trimmed) {
+            flush(&mut blocks, &mut paragraph, &mut quote);
+            blocks.push(heading);
+        } else if is_rule(trimmed) {
+            flush(&mut blocks, &mut paragraph, &mut quote);
+            blocks.push(MarkdownBlock::Rule);
+        } else if let Some(item) = list_item(line) {
+            flush(&mut blocks, &mut paragraph, &mut quote);
+            blocks.push(item);
+        } else {
+            if !quote.is_empty() {
+                flush(&mut blocks, &mut paragraph, &mut quote);
+            }
+            paragraph.push(trimmed);
+        }
+    }
+
+    flush(&mut blocks, &mut paragraph, &mut quote);
+    if let Some(code_lines) = code {
+        blocks.push(MarkdownBlock::CodeBlock(code_lines.join("\n")));
+    }
+    blocks
+}
+
+fn heading(line: &str) -> Option<MarkdownBlock> {
+    let level = line.bytes().take_while(|&byte| byte == b'#').count();
+    let rest = &line[level..];
+    if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with(' ')) {
+        return None;
+    }
+    Some(MarkdownBlock::Heading {
+        level,
+        text: rest.trim().trim_end_matches('#').trim_end().to_string(),
+    })
+}
+
+fn is_rule(line: &str) -> bool {
+    let marks: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    marks.len() >= 3
+        && ['-', '*', '_']
+            .into_iter()
+            .any(|mark| marks.chars().all(|c| c == mark))
+}
+
+fn list_item(line: &str) -> Option<MarkdownBlock> {
+    let indent = leading_indent(line);
+    let rest = &line[indent.len()..];
+    let depth = indent_columns(indent, 4) / 2;
+
+    let (marker, text) = if let Some(text) = ["- ", "* ", "+ "]
+        .into_iter()
+        .find_map(|bullet| rest.strip_prefix(bullet))
+    {
+        match ["[ ] ", "[x] ", "[X] "]
+            .into_iter()
+            .position(|task| text.starts_with(task))
+        {
+            Some(0) => ("☐".to_string(), &text[4..]),
+            Some(_) => ("☑".to_string(), &text[4..]),
+            None => ("•".to_string(), text),
+        }
+    } else {
+        let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+        let text = rest[digits..].strip_prefix(". ").filter(|_| digits > 0)?;
+        (format!("{}.", &rest[..digits]), text)
+    };
+
+    Some(MarkdownBlock::ListItem {
+        depth,
+        marker,
+        text: text.trim().to_string(),
+    })
+}
+
+/// Splits a block's text on `**bold**`, `*italic*`, `` `code` `` and
+/// `[links](url)`, which keep only their text. Markers without a closing
+/// partner are left as typed.
+pub fn parse_inline(text: &str) -> Vec<InlineSpan> {
+    let mut spans: Vec<InlineSpan> = Vec::new();
+    let mut style = InlineSpan::default();
+    let mut rest = text;
+
+    let push = |spans: &mut Vec<InlineSpan>, text: &str, style: &InlineSpan| {
+        if text.is_empty() {
+            return;
+        }
+        match spans.last_mut() {
+            Some(last)
+                if (last.bold, last.italic, last.code, last.link)
+                    == (style.bold, style.italic, style.code, style.link) =>
+            {
+                last.text.push_str(text)
+            }
+            _ => spans.push(InlineSpan {
+                text: text.to_string(),
+                ..style.clone()
+            }),
+        }
+    };
+
+    while let Some(c) = rest.chars().next() {
+        if let Some(after) = rest.strip_prefix('`') {
+            if let Some(end) = after.find('`') {
+                let code = InlineSpan {
+                    code: true,
+                    ..style.clone()
+                };
+                push(&mut spans, &after[..end], &code);
+                rest = &after[end + 1..];
+                continue;
+            }
+        } else if let Some(after) = rest.strip_prefix("**") {
+            if style.bold || after.contains("**") {
+                style.bold = !style.bold;
+                rest = after;
+                continue;
+            }
+        } else if let Some(after) = rest.strip_prefix('*') {
+            if style.italic || after.contains('*') {
+                style.italic = !style.italic;
+                rest = after;
+                continue;
+            }
+        } else if let Some((label, after)) = rest.strip_prefix('[').and_then(split_link) {
+            let link = InlineSpan {
+                link: true,
+                ..style.clone()
+            };
+            push(&mut spans, label, &link);
+            rest = after;
+            continue;
+        }
+
+        push(&mut spans, &rest[..c.len_utf8()], &style);
+        rest = &rest[c.len_utf8()..];
+    }
+
+    spans
+}
+
+/// Splits `spans` where the byte ranges in `matches`, taken over the spans'
+/// joined text, begin and end, and highlights the parts inside them.
+pub fn highlight_ranges(spans: Vec<InlineSpan>, matches: &[Range<usize>]) -> Vec<InlineSpan> {
+    let mut highlighted = Vec::with_capacity(spans.len());
+    let mut offset = 0;
+
+    for span in spans {
+        let end = offset + span.text.len();
+        let piece = |range: Range<usize>, highlight: bool| InlineSpan {
+            text: span.text[range.start - offset..range.end - offset].to_string(),
+            highlight,
+            ..span.clone()
+        };
+
+        let mut cursor = offset;
+        for range in matches
+            .iter()
+            .filter(|range| range.start < end && range.end > offset)
+        {
+            let start = range.start.max(offset);
+            if start > cursor {
+                highlighted.push(piece(cursor..start, false));
+            }
+            cursor = range.end.min(end);
+            highlighted.push(piece(start..cursor, true));
+        }
+        if cursor < end {
+            highlighted.push(piece(cursor..end, false));
+        }
+        offset = end;
+    }
+
+    highlighted
+}
+
+/// `label](url) rest` into the label and what follows the link.
+fn split_link(text: &str) -> Option<(&str, &str)> {
+    let (label, url_rest) = text.split_once("](")?;
+    let end = url_rest.find(')')?;
+    Some((label, &url_rest[end + 1..]))
+}
+
+/// `url` as it can be written between a link's parentheses. Whitespace,
+/// brackets and parentheses would end the link early, so they are
+/// percent-encoded, along with `%` itself.
+pub fn link_destination(url: &str) -> String {
+    let mut destination = String::with_capacity(url.len());
+    for c in url.chars() {
+        if c.is_whitespace() || c.is_control() || "()[]<>%\\".contains(c) {
+            let mut bytes = [0; 4];
+            for byte in c.encode_utf8(&mut bytes).bytes() {
+                destination.push_str(&format!("%{:02X}", byte));
+            }
+        } else {
+            destination.push(c);
+        }
+    }
+    destination
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks_are_split_by_line_shape() {
+        let text = "# Plan\nfirst line\nsecond line\n\n- milk\n  - [x] eggs\n2. bread\n\n> quoted\n> more\n---\n```\nlet x = 1;\n\n```\n#hashtag";
+        assert_eq!(
+            parse_markdown(text),
+            vec![
+                MarkdownBlock::Heading {
+                    level: 1,
+                    text: "Plan".to_string()
+                },
+                MarkdownBlock::Paragraph("first line second line".to_string()),
+                MarkdownBlock::ListItem {
+                    depth: 0,
+                    marker: "•".to_string(),
+                    text: "milk".to_string()
+                },
+                MarkdownBlock::ListItem {
+                    depth: 1,
+                    marker: "☑".to_string(),
+                    text: "eggs".to_string()
+                },
+                MarkdownBlock::ListItem {
+                    depth: 0,
+                    marker: "2.".to_string(),
+                    text: "bread".to_string()
+                },
+                MarkdownBlock::Quote("quoted more".to_string()),
+                MarkdownBlock::Rule,
+                MarkdownBlock::CodeBlock("let x = 1;\n".to_string()),
+                MarkdownBlock::Paragraph("#hashtag".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn unclosed_fence_runs_to_the_end() {
+        assert_eq!(
+            parse_markdown("intro\n```\ncode"),
+            vec![
+                MarkdownBlock::Paragraph("intro".to_string()),
+                MarkdownBlock::CodeBlock("code".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn inline_styles_need_a_closing_marker() {
+        let span = |text: &str| InlineSpan {
+            text: text.to_string(),
+            ..InlineSpan::default()
+        };
+
+        assert_eq!(
+            parse_inline("a **bold** and *it* `x*y` [site](https://example.com)"),
+            vec![
+                span("a "),
+                InlineSpan {
+                    bold: true,
+                    ..span("bold")
+                },
+                span(" and "),
+                InlineSpan {
+                    italic: true,
+                    ..span("it")
+                },
+                span(" "),
+                InlineSpan {
+                    code: true,
+                    ..span("x*y")
+                },
+                span(" "),
+                InlineSpan {
+                    link: true,
+                    ..span("site")
+                },
+            ]
+        );
+        assert_eq!(parse_inline("2 * 3 = 6"), vec![span("2 * 3 = 6")]);
+        assert_eq!(parse_inline("**open"), vec![span("**open")]);
+    }
+
+    #[test]
+    fn link_destinations_escape_what_would_end_the_link() {
+        let path = "/Users/me/Application Support/tap/photo (1).png";
+        let destination = link_destination(path);
+        assert_eq!(
+            destination,
+            "/Users/me/Application%20Support/tap/photo%20%281%29.png"
+        );
+        assert_eq!(link_destination("50% off\tnow"), "50%25%20off%09now");
+
+        let span = |text: &str| InlineSpan {
+            text: text.to_string(),
+            ..InlineSpan::default()
+        };
+        assert_eq!(
+            parse_inline(&format!("![image]({}) after", destination)),
+            vec![
+                span("!"),
+                InlineSpan {
+                    link: true,
+                    ..span("image")
+                },
+                span(" after"),
+            ]
+        );
+    }
+
+    #[test]
+    fn matches_are_highlighted_across_styled_spans() {
+        let spans = parse_inline("a **bold** move");
+        let text = MarkdownBlock::Paragraph("a **bold** move".to_string()).rendered_text();
+        assert_eq!(text, "a bold move");
+
+        let start = text.find("ld m").unwrap();
+        let highlighted = highlight_ranges(spans, &[start..start + 4, text.len() - 2..text.len()]);
+        let pieces: Vec<(&str, bool, bool)> = highlighted
+            .iter()
+            .map(|span| (span.text.as_str(), span.bold, span.highlight))
+            .collect();
+        assert_eq!(
+            pieces,
+            vec![
+                ("a ", false, false),
+                ("bo", true, false),
+                ("ld", true, true),
+                (" m", false, true),
+                ("o", false, false),
+                ("ve", false, true),
+            ]
+        );
+    }
+}
@@ -1,4 +1,14 @@
+mod bidi;
+mod caret_blink;
+mod indent;
+mod markdown;
 mod note_app;
 mod note_content;
+mod save_transforms;
+mod shortcuts;
+mod undo;
 
-pub use note_app::NoteApp;
+pub use note_app::{
+    ExportAllAsJsonLines, ExportSelectedAsJson, ExportSelectedAsMarkdown, ExportToPdf,
+    ImportJsonLines, NoteApp,
+};
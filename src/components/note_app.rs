@@ -1,37 +1,213 @@
-use crate::models::{Database, Note};
+use crate::models::{
+    Attachment, ContentTransform, DEFAULT_DATE_FORMAT, Database, EditorHighlights, IndentStyle,
+    LineEnding, Maintenance, NewNotePlacement, Note, NoteRepository, NoteWriter, Session,
+    NoteChange, Settings, SidebarDensity, SortOrder, UndoLimits, WriteOutcome, decrypt_content,
+    encrypt_content, format_timestamp, normalize_newlines, without_writes,
+};
 use crate::util::{
-    dump_db_contents, get_db_path,
+    dump_db_contents,
+    export::{
+        export_notes_to_json, export_notes_to_jsonl, export_notes_to_markdown,
+        import_notes_from_jsonl,
+    },
+    get_db_path, get_export_dir, get_session_path, get_settings_path,
+    macos_accessibility::{announce, reduce_motion_enabled},
     macos_menu::{ContextMenu, MenuAction},
-    NOTE_TO_DELETE,
+    macos_pdf::export_note_to_pdf,
+    macos_status_item::update_status_item,
+    line_from_url, note_id_from_url, note_url, sanitize_filename, save_attachment_bytes,
+    watch_files,
+    AppRequest, send_app_request, take_app_requests,
+};
+use crate::components::bidi::LineGeometry;
+use crate::components::caret_blink::{BlinkingCaret, CaretBlink};
+use crate::components::markdown::{
+    InlineSpan, MarkdownBlock, highlight_ranges, link_destination, parse_inline, parse_markdown,
 };
+use crate::components::indent::{
+    ExpandedLine, indent_columns, indent_unit, leading_indent, normalize_indent, outdent,
+    resolve_indent_style,
+};
+use crate::components::save_transforms::SaveTransforms;
+use crate::components::shortcuts::{ShortcutAction, action_for_keystroke, shortcuts_by_category};
+use crate::components::undo::{UndoHistory, diff_edit};
+use futures::StreamExt;
+use futures::channel::mpsc::{self, UnboundedReceiver};
 use gpui::{
-    Action, App, ClipboardItem, CursorStyle, ElementId, ElementInputHandler, Entity,
-    EntityInputHandler, FocusHandle, Focusable, FontWeight, GlobalElementId, KeyDownEvent,
+    Action, Animation, AnimationExt, App, ClipboardEntry, ClipboardItem, CursorStyle, ElementId,
+    ElementInputHandler, Entity, EntityInputHandler, FocusHandle, Focusable, FontWeight,
+    GlobalElementId, Image, ImageFormat, KeyDownEvent,
     LayoutId, Menu, MenuItem, MouseButton, MouseDownEvent, MouseMoveEvent, MouseUpEvent, PaintQuad,
-    Pixels, Point, Render, ShapedLine, SharedString, Style, TextRun, UTF16Selection,
-    UnderlineStyle, Window, div, point, prelude::*, px, relative, rgb, rgba, size,
+    Pixels, Point, PromptLevel, Render, ScrollHandle, ShapedLine, SharedString, Style, TextRun,
+    UTF16Selection, UnderlineStyle, Window, actions, deferred, div, point, prelude::*, px,
+    relative, rgb, rgba, size,
 };
+use std::collections::{HashMap, HashSet};
 use std::ops::Range;
 use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use unicode_segmentation::UnicodeSegmentation;
 use uuid::Uuid;
-use objc::{class, msg_send, sel, sel_impl};
-use std::sync::Once;
-use lazy_static::lazy_static;
-use block::ConcreteBlock;
 
-const LINE_HEIGHT: f32 = 20.0;
+actions!(
+    tap,
+    [
+        ExportToPdf,
+        ExportSelectedAsMarkdown,
+        ExportSelectedAsJson,
+        ExportAllAsJsonLines,
+        ImportJsonLines
+    ]
+);
+
+const SAVED_FLASH_DURATION: Duration = Duration::from_millis(1500);
+const CLOSE_FLUSH_TIMEOUT: Duration = Duration::from_secs(2);
+/// Quitting waits on the UI thread, so it gives the writer less time than
+/// closing the window does.
+const QUIT_FLUSH_TIMEOUT: Duration = Duration::from_millis(500);
+const SAVE_AND_CLOSE_TIMEOUT: Duration = Duration::from_secs(30);
+/// The order the sidebar lists notes in; a note added during the session is
+/// placed by it too.
+const SIDEBAR_ORDER: SortOrder = SortOrder::Manual;
+/// Long enough for the busy overlay to be drawn before a blocking operation.
+const BUSY_OVERLAY_DELAY: Duration = Duration::from_millis(32);
+const PROPORTIONAL_FONT_FAMILY: &str = ".SystemUIFont";
+const SCROLLBAR_WIDTH: f32 = 8.;
+const MIN_SCROLLBAR_THUMB: f32 = 24.;
+const RECENT_NOTES_SHOWN: usize = 5;
+const ZOOM_STEP: f32 = 0.1;
+const MIN_ZOOM: f32 = 0.5;
+const MAX_ZOOM: f32 = 3.0;
+const ONBOARDING_STEPS: &[(&str, &str)] = &[
+    (
+        "Create notes",
+        "Click the + button at the top of the sidebar, or press ⌘N, to start a new note.",
+    ),
+    (
+        "Delete notes",
+        "Right-click a note in the sidebar and choose Delete. It moves to the trash first.",
+    ),
+    (
+        "Rename notes",
+        "Click the title above the editor, or press ⌘R, to edit a note's title.",
+    ),
+];
+const DATE_FORMAT_PRESETS: &[&str] = &[
+    DEFAULT_DATE_FORMAT,
+    "%b %-d, %Y %-I:%M %p",
+    "%d/%m/%Y %H:%M",
+];
+const LINE_SPACING_PRESETS: &[f32] = &[1.25, 1.5, 1.75, 2.0];
+const PARAGRAPH_SPACING_PRESETS: &[f32] = &[0.0, 4.0, 8.0];
+const CONTENT_PADDING_PRESETS: &[f32] = &[8.0, 16.0, 24.0, 32.0];
+const UNDO_STEP_PRESETS: &[usize] = &[100, 500, 2000];
+const AUTO_CLOSE_PAIRS: &[(char, char)] = &[
+    ('(', ')'),
+    ('[', ']'),
+    ('{', '}'),
+    ('"', '"'),
+    ('`', '`'),
+];
 
 pub struct NoteApp {
     db: Arc<Database>,
+    writer: NoteWriter,
+    settings: Settings,
+    save_transforms: Arc<SaveTransforms>,
     notes: Vec<Note>,
     active_note_id: Option<Uuid>,
     editor: Entity<NoteEditor>,
     content_focus_handle: FocusHandle,
+    sidebar_focus_handle: FocusHandle,
     title_edit_mode: bool,
     title_text: String,
     title_focus_handle: FocusHandle,
     title_editor: Entity<TitleEditor>,
+    show_replace_panel: bool,
+    replace_find_editor: Entity<TitleEditor>,
+    search_editor: Entity<TitleEditor>,
+    search_query: String,
+    replace_with_editor: Entity<TitleEditor>,
+    replace_case_sensitive: bool,
+    replace_status: Option<String>,
+    _db_watcher: Option<notify::RecommendedWatcher>,
+    /// Periodic checkpointing of the database, off when the interval is 0.
+    _db_maintenance: Option<Maintenance>,
+    error_message: Option<String>,
+    show_trash: bool,
+    /// Starred notes while the sidebar shows only Favorites.
+    favorite_ids: Option<HashSet<Uuid>>,
+    /// The starred notes last shown in the menu bar item, `None` until it
+    /// has been created.
+    status_item_notes: Option<Vec<(Uuid, String)>>,
+    show_archived: bool,
+    trashed_count: usize,
+    trashed_notes: Vec<Note>,
+    auto_title_note_id: Option<Uuid>,
+    selected_ids: HashSet<Uuid>,
+    status_message: Option<String>,
+    show_shortcuts: bool,
+    show_settings: bool,
+    /// Size of the database file, measured when the settings open and after
+    /// compacting.
+    database_size: Option<u64>,
+    /// Groups of identical notes, oldest first, while they are being reviewed.
+    duplicate_groups: Option<Vec<Vec<Uuid>>>,
+    onboarding_step: Option<usize>,
+    busy: Option<String>,
+    reduce_motion: bool,
+    unlocked_passphrases: HashMap<Uuid, String>,
+    passphrase_editor: Entity<TitleEditor>,
+    show_encrypt_prompt: bool,
+    show_note_info: bool,
+    /// Attachments row of the info panel, loaded when the panel opens or
+    /// another note becomes active.
+    note_attachments: String,
+    show_template_menu: bool,
+    open_note_ids: Vec<Uuid>,
+    caret_status: ((usize, usize), usize),
+    /// Set while the status bar shows "Saved"; each write gets a new number
+    /// so its fade starts over.
+    saved_flash: Option<usize>,
+    saved_flash_count: usize,
+    content_scroll: ScrollHandle,
+    preview_scroll: ScrollHandle,
+    sidebar_scroll: ScrollHandle,
+    /// Where the pointer grabbed the scrollbar thumb, measured from the
+    /// thumb's top, while it is being dragged.
+    scrollbar_drag: Option<f32>,
+    /// Cached copy of a note that was deleted from the database by another
+    /// process while open here, kept until the user recreates or discards it.
+    missing_note: Option<Note>,
+    /// Focus the editor on the next render, for requests that arrive without
+    /// a window at hand.
+    pending_editor_focus: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditMode {
+    Normal,
+    Insert,
+}
+
+/// Every load takes a new generation, so a save set up for an earlier load
+/// can tell it is stale even when it names the same note.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct EditorBinding {
+    note_id: Option<Uuid>,
+    generation: u64,
+}
+
+impl EditorBinding {
+    fn rebind(&mut self, note_id: Option<Uuid>) -> Self {
+        self.generation += 1;
+        self.note_id = note_id;
+        *self
+    }
+
+    fn is_current(&self, captured: &Self) -> bool {
+        self.note_id.is_some() && self == captured
+    }
 }
 
 pub struct NoteEditor {
@@ -40,10 +216,26 @@ pub struct NoteEditor {
     selected_range: Range<usize>,
     selection_reversed: bool,
     marked_range: Option<Range<usize>>,
-    last_layout: Option<ShapedLine>,
+    last_lines: Vec<(ShapedLine, LineGeometry, usize)>,
     last_bounds: Option<gpui::Bounds<Pixels>>,
     is_selecting: bool,
-    on_change: Option<Box<dyn Fn(String, &mut Context<NoteEditor>)>>,
+    caret_blink: CaretBlink,
+    auto_close_brackets: bool,
+    paste_url_as_link: bool,
+    indent_style: IndentStyle,
+    tab_width: usize,
+    highlights: EditorHighlights,
+    history: UndoHistory,
+    line_height: Pixels,
+    paragraph_spacing: Pixels,
+    vim_mode: bool,
+    edit_mode: EditMode,
+    pending_vim_operator: Option<char>,
+    binding: EditorBinding,
+    read_only: bool,
+    on_change: Option<Box<dyn Fn(EditorBinding, String, &mut Context<NoteEditor>)>>,
+    on_paste_image: Option<Box<dyn Fn(Image, &mut Context<NoteEditor>) -> Option<String>>>,
+    on_read_only_edit: Option<Box<dyn Fn(&mut Context<NoteEditor>)>>,
 }
 
 pub struct TitleEditor {
@@ -51,25 +243,155 @@ pub struct TitleEditor {
     content: SharedString,
     selected_range: Range<usize>,
     selection_reversed: bool,
+    caret_blink: CaretBlink,
+    masked: bool,
+    placeholder: SharedString,
     on_change: Option<Box<dyn Fn(String, &mut Context<TitleEditor>)>>,
 }
 
 impl NoteEditor {
+    /// Replaces the text and moves the caret to its end. Reloading the text
+    /// the editor already shows, as happens when focus moves between the
+    /// title and the editor, leaves the selection untouched.
     fn set_content(&mut self, content: impl Into<SharedString>, cx: &mut Context<Self>) {
-        self.content = content.into();
-        self.selected_range = self.content.len()..self.content.len();
+        let content = content.into();
+        let selected_range =
+            selection_for_new_content(&self.content, &self.selected_range, &content);
+        if selected_range == self.selected_range && content == self.content {
+            return;
+        }
+
+        if content != self.content {
+            self.history.clear();
+        }
+        self.content = content;
+        self.selected_range = selected_range;
         self.selection_reversed = false;
         self.marked_range = None;
         cx.notify();
     }
 
+    fn set_content_preserving_selection(
+        &mut self,
+        content: impl Into<SharedString>,
+        cx: &mut Context<Self>,
+    ) {
+        let content = content.into();
+        if content == self.content {
+            return;
+        }
+
+        self.selected_range = clamp_selection(&self.selected_range, &content);
+        self.content = content;
+        self.marked_range = None;
+        self.history.clear();
+        cx.notify();
+    }
+
     fn set_on_change<F>(&mut self, callback: F)
     where
-        F: Fn(String, &mut Context<NoteEditor>) + 'static,
+        F: Fn(EditorBinding, String, &mut Context<NoteEditor>) + 'static,
     {
         self.on_change = Some(Box::new(callback));
     }
 
+    fn set_on_read_only_edit<F>(&mut self, callback: F)
+    where
+        F: Fn(&mut Context<NoteEditor>) + 'static,
+    {
+        self.on_read_only_edit = Some(Box::new(callback));
+    }
+
+    fn refuse_read_only_edit(&self, cx: &mut Context<Self>) -> bool {
+        if !self.read_only {
+            return false;
+        }
+        if let Some(on_read_only_edit) = &self.on_read_only_edit {
+            on_read_only_edit(cx);
+        }
+        true
+    }
+
+    fn emit_change(&self, cx: &mut Context<Self>) {
+        if let Some(on_change) = &self.on_change {
+            on_change(self.binding, self.content.to_string(), cx);
+        }
+    }
+
+    fn record_edit(&mut self, previous: &str, selection_before: Range<usize>) {
+        if let Some(edit) = diff_edit(previous, &self.content, selection_before) {
+            self.history.record(edit);
+        }
+    }
+
+    /// Returns false when a locked note refused the edit.
+    fn edit(
+        &mut self,
+        range: Range<usize>,
+        new_text: &str,
+        new_cursor: usize,
+        cx: &mut Context<Self>,
+    ) -> bool {
+        if self.refuse_read_only_edit(cx) {
+            return false;
+        }
+
+        let previous = self.content.clone();
+        let selection_before = self.selected_range.clone();
+        let (content, caret) = splice(&self.content, range, new_text, new_cursor);
+        self.content = content.into();
+        self.selected_range = caret..caret;
+        self.selection_reversed = false;
+        self.marked_range = None;
+        self.record_edit(&previous, selection_before);
+
+        self.emit_change(cx);
+
+        cx.notify();
+        true
+    }
+
+    fn undo(&mut self, cx: &mut Context<Self>) {
+        if self.refuse_read_only_edit(cx) {
+            return;
+        }
+        if let Some((content, selection)) = self.history.undo(&self.content) {
+            self.apply_history_step(content, selection, cx);
+        }
+    }
+
+    fn redo(&mut self, cx: &mut Context<Self>) {
+        if self.refuse_read_only_edit(cx) {
+            return;
+        }
+        if let Some((content, selection)) = self.history.redo(&self.content) {
+            self.apply_history_step(content, selection, cx);
+        }
+    }
+
+    fn apply_history_step(
+        &mut self,
+        content: String,
+        selection: Range<usize>,
+        cx: &mut Context<Self>,
+    ) {
+        self.selected_range = clamp_selection(&selection, &content);
+        self.selection_reversed = false;
+        self.marked_range = None;
+        self.content = content.into();
+
+        self.emit_change(cx);
+
+        cx.notify();
+    }
+
+    fn set_on_paste_image<F>(&mut self, callback: F)
+    where
+        F: Fn(Image, &mut Context<NoteEditor>) -> Option<String> + 'static,
+    {
+        self.on_paste_image = Some(Box::new(callback));
+    }
+
     fn on_mouse_down(
         &mut self,
         event: &MouseDownEvent,
@@ -77,8 +399,17 @@ impl NoteEditor {
         cx: &mut Context<Self>,
     ) {
         self.is_selecting = true;
-
-        if event.modifiers.shift {
+        self.pause_caret_blink(cx);
+
+        if event.click_count >= 3 && !event.modifiers.shift {
+            // A triple click takes the whole line; dragging on from it would
+            // shrink the selection back to a character range.
+            self.is_selecting = false;
+            self.selection_reversed = false;
+            let offset = self.index_for_mouse_position(event.position);
+            self.selected_range = line_selection_range(&self.content, offset);
+            cx.notify();
+        } else if event.modifiers.shift {
             self.select_to(self.index_for_mouse_position(event.position), cx);
         } else {
             self.move_to(self.index_for_mouse_position(event.position), cx)
@@ -100,40 +431,24 @@ impl NoteEditor {
             return 0;
         }
 
-        let (Some(bounds), Some(line)) = (self.last_bounds.as_ref(), self.last_layout.as_ref())
-        else {
+        let Some(bounds) = self.last_bounds.as_ref() else {
             return 0;
         };
 
-        let line_height = LINE_HEIGHT;
         let relative_y = (position.y - bounds.top()).0;
-        let line_index = (relative_y / line_height).floor() as usize;
-        let lines: Vec<&str> = self.content.split('\n').collect();
-
-        if line_index >= lines.len() {
-            return self.content.len();
-        }
-
-        let mut offset = 0;
-        for i in 0..line_index {
-            offset += lines[i].len() + 1;
-        }
-
-        if position.x < bounds.left() {
-            return offset;
-        }
-
-        let current_line = lines[line_index];
-        if current_line.is_empty() {
-            return offset;
-        }
-
-        let x_within_line = position.x - bounds.left();
-        let closest_index = line
-            .closest_index_for_x(x_within_line)
-            .min(current_line.len());
+        let line_index = line_index_for_y(relative_y, self.line_pitch().0);
+        let x_within_line = (position.x - bounds.left()).0;
+
+        offset_for_line_click(&self.content, line_index, |line_text| {
+            self.last_lines
+                .get(line_index)
+                .map(|(_, geometry, _)| geometry.index_for_x(x_within_line))
+                .unwrap_or(line_text.len())
+        })
+    }
 
-        offset + closest_index
+    fn line_pitch(&self) -> Pixels {
+        self.line_height + self.paragraph_spacing
     }
 
     fn move_to(&mut self, offset: usize, cx: &mut Context<Self>) {
@@ -183,12 +498,62 @@ impl NoteEditor {
     }
 
     fn on_backspace(&mut self, window: &mut Window, cx: &mut Context<Self>) {
-        if self.selected_range.is_empty() {
+        if let Some(pair_range) = self.empty_pair_around_cursor() {
+            self.selected_range = pair_range;
+        } else if self.selected_range.is_empty() {
             self.select_to(self.previous_boundary(self.cursor_offset()), cx)
         }
         self.replace_text_in_range(None, "", window, cx)
     }
 
+    fn empty_pair_around_cursor(&self) -> Option<Range<usize>> {
+        if !self.auto_close_brackets || !self.selected_range.is_empty() {
+            return None;
+        }
+
+        let cursor = self.cursor_offset();
+        let prev_char = self.content[..cursor].chars().next_back()?;
+        let next_char = self.content[cursor..].chars().next()?;
+
+        AUTO_CLOSE_PAIRS
+            .iter()
+            .any(|&(open, close)| open == prev_char && close == next_char)
+            .then(|| cursor - prev_char.len_utf8()..cursor + next_char.len_utf8())
+    }
+
+    fn handle_auto_close(&mut self, new_text: &str, cx: &mut Context<Self>) -> bool {
+        if !self.auto_close_brackets {
+            return false;
+        }
+
+        let mut chars = new_text.chars();
+        let (Some(ch), None) = (chars.next(), chars.next()) else {
+            return false;
+        };
+
+        let range = self.selected_range.clone();
+        let next_char = self.content[range.end..].chars().next();
+
+        if range.is_empty()
+            && next_char == Some(ch)
+            && AUTO_CLOSE_PAIRS.iter().any(|&(_, close)| close == ch)
+        {
+            self.move_to(range.end + ch.len_utf8(), cx);
+            return true;
+        }
+
+        let Some(&(open, close)) = AUTO_CLOSE_PAIRS.iter().find(|&&(open, _)| open == ch) else {
+            return false;
+        };
+
+        let wrapped = format!("{}{}{}", open, &self.content[range.clone()], close);
+        let inner_start = range.start + open.len_utf8();
+        if self.edit(range.clone(), &wrapped, inner_start, cx) {
+            self.selected_range = inner_start..inner_start + range.len();
+        }
+        true
+    }
+
     fn on_delete(&mut self, window: &mut Window, cx: &mut Context<Self>) {
         if self.selected_range.is_empty() {
             self.select_to(self.next_boundary(self.cursor_offset()), cx)
@@ -212,9 +577,141 @@ impl NoteEditor {
         }
     }
 
+    fn set_edit_mode(&mut self, mode: EditMode, cx: &mut Context<Self>) {
+        self.edit_mode = mode;
+        self.pending_vim_operator = None;
+        cx.notify();
+    }
+
+    fn delete_lines(&mut self, cx: &mut Context<Self>) {
+        let range = deleted_lines_range(&self.content, self.selected_range.clone());
+        self.edit(range.clone(), "", range.start, cx);
+        let line = self.line_at_offset(self.cursor_offset());
+        self.move_to(self.offset_at_line_start(line), cx);
+    }
+
+    fn handle_vim_key(&mut self, event: &KeyDownEvent, cx: &mut Context<Self>) -> bool {
+        let keystroke = &event.keystroke;
+        if !self.vim_mode || keystroke.modifiers.platform || keystroke.modifiers.control {
+            return false;
+        }
+
+        let cursor = self.cursor_offset();
+        let line = self.line_at_offset(cursor);
+        let line_start = self.offset_at_line_start(line);
+        let line_end = self.offset_at_line_end(line);
+
+        if self.edit_mode == EditMode::Insert {
+            if keystroke.key != "escape" {
+                return false;
+            }
+
+            self.set_edit_mode(EditMode::Normal, cx);
+            if cursor > line_start {
+                self.move_to(self.previous_boundary(cursor), cx);
+            }
+            return true;
+        }
+
+        let key = keystroke
+            .key_char
+            .as_deref()
+            .unwrap_or(keystroke.key.as_str());
+
+        if self.pending_vim_operator.take() == Some('d') {
+            if key == "d" {
+                self.delete_lines(cx);
+            }
+            return true;
+        }
+
+        match key {
+            "h" | "arrowleft" => {
+                if cursor > line_start {
+                    self.move_to(self.previous_boundary(cursor), cx);
+                }
+            }
+            "l" | "arrowright" => {
+                if cursor < line_end {
+                    self.move_to(self.next_boundary(cursor), cx);
+                }
+            }
+            "j" | "arrowdown" => self.move_down(cx),
+            "k" | "arrowup" => self.move_up(cx),
+            "0" => self.move_to(line_start, cx),
+            "$" => self.move_to(line_end, cx),
+            "i" => self.set_edit_mode(EditMode::Insert, cx),
+            "a" => {
+                if cursor < line_end {
+                    self.move_to(self.next_boundary(cursor), cx);
+                }
+                self.set_edit_mode(EditMode::Insert, cx);
+            }
+            "o" => {
+                self.edit(line_end..line_end, "\n", line_end + 1, cx);
+                self.set_edit_mode(EditMode::Insert, cx);
+            }
+            "x" => {
+                if cursor < line_end {
+                    self.edit(cursor..self.next_boundary(cursor), "", cursor, cx);
+                }
+            }
+            "d" => self.pending_vim_operator = Some('d'),
+            _ => {}
+        }
+
+        true
+    }
+
     fn on_key_down(&mut self, event: &KeyDownEvent, window: &mut Window, cx: &mut Context<Self>) {
+        self.pause_caret_blink(cx);
+
+        // Keys pressed mid-composition belong to the input method; Enter
+        // commits the marked text instead of starting a new line.
+        if is_composing(self.marked_range.as_ref()) {
+            return;
+        }
+
+        if event.keystroke.key == "z" && event.keystroke.modifiers.platform {
+            if event.keystroke.modifiers.shift {
+                self.redo(cx);
+            } else {
+                self.undo(cx);
+            }
+            cx.stop_propagation();
+            return;
+        }
+
+        if event.keystroke.key == "k"
+            && event.keystroke.modifiers.platform
+            && event.keystroke.modifiers.shift
+        {
+            self.delete_lines(cx);
+            cx.stop_propagation();
+            return;
+        }
+
+        if self.handle_vim_key(event, cx) {
+            cx.stop_propagation();
+            return;
+        }
+
+        if event.keystroke.key == "tab" && !event.keystroke.modifiers.platform {
+            if event.keystroke.modifiers.shift {
+                self.outdent_line(cx);
+            } else {
+                self.insert_indent(cx);
+            }
+            cx.stop_propagation();
+            return;
+        }
+
         if event.keystroke.key == "enter" {
-            self.replace_text_in_range(None, "\n", window, cx);
+            if event.keystroke.modifiers.shift {
+                self.replace_text_in_range(None, "\n", window, cx);
+            } else {
+                self.insert_newline_continuing_list(cx);
+            }
             return;
         }
 
@@ -257,12 +754,11 @@ impl NoteEditor {
                 self.move_down(cx);
             }
         } else if event.keystroke.key == "home" {
-            let line = self.line_at_offset(self.cursor_offset());
-            let line_start = self.offset_at_line_start(line);
+            let target = home_offset(&self.content, self.cursor_offset());
             if event.keystroke.modifiers.shift {
-                self.select_to(line_start, cx);
+                self.select_to(target, cx);
             } else {
-                self.move_to(line_start, cx);
+                self.move_to(target, cx);
             }
         } else if event.keystroke.key == "end" {
             let line = self.line_at_offset(self.cursor_offset());
@@ -274,7 +770,10 @@ impl NoteEditor {
             }
         } else if event.keystroke.key == "a" && event.keystroke.modifiers.platform {
             self.select_all(cx);
-        } else if event.keystroke.key == "c" && event.keystroke.modifiers.platform {
+        } else if event.keystroke.key == "c"
+            && event.keystroke.modifiers.platform
+            && !event.keystroke.modifiers.shift
+        {
             if !self.selected_range.is_empty() {
                 cx.write_to_clipboard(ClipboardItem::new_string(
                     (&self.content[self.selected_range.clone()]).to_string(),
@@ -288,8 +787,29 @@ impl NoteEditor {
                 self.replace_text_in_range(None, "", window, cx);
             }
         } else if event.keystroke.key == "v" && event.keystroke.modifiers.platform {
-            if let Some(text) = cx.read_from_clipboard().and_then(|item| item.text()) {
-                self.replace_text_in_range(None, &text, window, cx);
+            let Some(item) = cx.read_from_clipboard() else {
+                return;
+            };
+
+            if let Some(text) = item.text() {
+                let text = normalize_newlines(&text);
+                let link = if self.paste_url_as_link {
+                    markdown_link_for_paste(&self.content[self.selected_range.clone()], &text)
+                } else {
+                    None
+                };
+                self.replace_text_in_range(None, link.as_deref().unwrap_or(&text), window, cx);
+            } else if let Some(image) = item.entries().iter().find_map(|entry| match entry {
+                ClipboardEntry::Image(image) => Some(image.clone()),
+                _ => None,
+            }) {
+                let reference = self
+                    .on_paste_image
+                    .as_ref()
+                    .and_then(|on_paste_image| on_paste_image(image, cx));
+                if let Some(reference) = reference {
+                    self.replace_text_in_range(None, &reference, window, cx);
+                }
             }
         }
     }
@@ -332,16 +852,94 @@ impl NoteEditor {
         self.offset_from_utf16(range_utf16.start)..self.offset_from_utf16(range_utf16.end)
     }
 
-    fn line_at_offset(&self, offset: usize) -> usize {
-        let mut current_offset = 0;
-        for (i, line) in self.content.split('\n').enumerate() {
-            let line_end = current_offset + line.len();
-            if offset <= line_end {
-                return i;
+    /// Enter inside a list item starts the next item; Enter on an empty item
+    /// removes its marker to end the list. Otherwise the new line keeps the
+    /// current line's indentation, rewritten in the configured indent style.
+    fn insert_newline_continuing_list(&mut self, cx: &mut Context<Self>) {
+        let start = self.selected_range.start;
+        let line_start = self.offset_at_line_start(self.line_at_offset(start));
+        let line = self.content[line_start..start].to_string();
+        let indent = leading_indent(&line);
+        let new_indent = normalize_indent(indent, self.effective_indent_style(), self.tab_width);
+
+        match list_item_marker(&line) {
+            Some((marker_len, _)) if line[marker_len..].trim().is_empty() => {
+                self.edit(line_start..self.selected_range.end, "", line_start, cx);
+            }
+            Some((_, next_marker)) => {
+                let marker = &next_marker[indent.len()..];
+                let text = format!("\n{}{}", new_indent, marker);
+                self.edit(self.selected_range.clone(), &text, start + text.len(), cx);
             }
-            current_offset = line_end + 1;
+            None => {
+                let text = format!("\n{}", new_indent);
+                self.edit(self.selected_range.clone(), &text, start + text.len(), cx);
+            }
+        }
+    }
+
+    fn effective_indent_style(&self) -> IndentStyle {
+        resolve_indent_style(self.indent_style, &self.content)
+    }
+
+    fn insert_indent(&mut self, cx: &mut Context<Self>) {
+        let start = self.selected_range.start;
+        let line_start = self.offset_at_line_start(self.line_at_offset(start));
+        let column = indent_columns(&self.content[line_start..start], self.tab_width);
+        let unit = indent_unit(self.effective_indent_style(), column);
+        self.edit(self.selected_range.clone(), &unit, start + unit.len(), cx);
+    }
+
+    fn outdent_line(&mut self, cx: &mut Context<Self>) {
+        let cursor = self.cursor_offset();
+        let line_start = self.offset_at_line_start(self.line_at_offset(cursor));
+        let line_end = self.offset_at_line_end(self.line_at_offset(cursor));
+        let indent = leading_indent(&self.content[line_start..line_end]).to_string();
+        let outdented = outdent(&indent, self.effective_indent_style(), self.tab_width);
+        if outdented == indent {
+            return;
         }
-        self.content.split('\n').count() - 1
+
+        let indent_end = line_start + indent.len();
+        let caret = if cursor >= indent_end {
+            cursor - indent.len() + outdented.len()
+        } else {
+            line_start + outdented.len()
+        };
+        self.edit(line_start..indent_end, &outdented, caret, cx);
+    }
+
+    fn line_at_offset(&self, offset: usize) -> usize {
+        line_index_at(&self.content, offset)
+    }
+
+    /// Zero-based, with columns counted in graphemes.
+    pub fn cursor_line_column(&self) -> (usize, usize) {
+        let cursor = self.cursor_offset();
+        let line = self.line_at_offset(cursor);
+        let line_start = self.offset_at_line_start(line);
+        (line, grapheme_column(&self.content, line_start, cursor))
+    }
+
+    /// Puts the caret at a zero-based line and grapheme column. A line past
+    /// the end lands on the last line and a column past the line's end on
+    /// the end of that line.
+    pub fn set_cursor_line_column(&mut self, line: usize, column: usize, cx: &mut Context<Self>) {
+        let offset = offset_for_line_column(&self.content, line, column);
+        self.selection_reversed = false;
+        self.move_to(offset, cx);
+    }
+
+    /// Counts from one, as the status bar shows it.
+    pub fn caret_line_column(&self) -> (usize, usize) {
+        let (line, column) = self.cursor_line_column();
+        (line + 1, column + 1)
+    }
+
+    pub fn selection_length(&self) -> usize {
+        self.content
+            .get(self.selected_range.clone())
+            .map_or(0, |selected| selected.graphemes(true).count())
     }
 
     fn offset_at_line_start(&self, line_number: usize) -> usize {
@@ -367,74 +965,631 @@ impl NoteEditor {
         self.content.len()
     }
 
-    fn line_length(&self, line_number: usize) -> usize {
-        let lines: Vec<&str> = self.content.split('\n').collect();
-        if line_number < lines.len() {
-            lines[line_number].len()
-        } else {
-            0
+    fn move_up(&mut self, cx: &mut Context<Self>) {
+        if let Some(offset) = vertical_move_offset(&self.content, self.cursor_offset(), true) {
+            self.move_to(offset, cx);
         }
     }
 
-    fn move_up(&mut self, cx: &mut Context<Self>) {
-        let cursor = self.cursor_offset();
-        let current_line = self.line_at_offset(cursor);
-
-        if current_line > 0 {
-            let current_line_start = self.offset_at_line_start(current_line);
-            let x_offset = cursor - current_line_start;
-
-            let prev_line = current_line - 1;
-            let prev_line_start = self.offset_at_line_start(prev_line);
-            let prev_line_len = self.line_length(prev_line);
-
-            let new_offset = prev_line_start + x_offset.min(prev_line_len);
-            self.move_to(new_offset, cx);
+    fn move_down(&mut self, cx: &mut Context<Self>) {
+        if let Some(offset) = vertical_move_offset(&self.content, self.cursor_offset(), false) {
+            self.move_to(offset, cx);
         }
     }
+}
 
-    fn move_down(&mut self, cx: &mut Context<Self>) {
-        let cursor = self.cursor_offset();
-        let current_line = self.line_at_offset(cursor);
-        let line_count = self.content.split('\n').count();
+/// Where Up (`up`) or Down takes a caret at `offset`: the same byte column
+/// on the neighbouring line, clamped to that line's end. `None` when there
+/// is no line in that direction.
+fn vertical_move_offset(text: &str, offset: usize, up: bool) -> Option<usize> {
+    let lines: Vec<&str> = text.split('\n').collect();
+    let line = line_index_at(text, offset);
+    let target = if up { line.checked_sub(1)? } else { line + 1 };
+    let target_text = lines.get(target)?;
+
+    let line_start = |index: usize| -> usize { lines[..index].iter().map(|l| l.len() + 1).sum() };
+    let column = offset.min(text.len()) - line_start(line);
+    Some(line_start(target) + floor_char_boundary(target_text, column))
+}
 
-        if current_line < line_count - 1 {
-            let current_line_start = self.offset_at_line_start(current_line);
-            let x_offset = cursor - current_line_start;
+/// The byte offset of grapheme `column` on zero-based `line`, clamped to
+/// the last line and to the end of the line.
+fn offset_for_line_column(text: &str, line: usize, column: usize) -> usize {
+    let lines: Vec<&str> = text.split('\n').collect();
+    let line = line.min(lines.len() - 1);
+    let line_start: usize = lines[..line].iter().map(|line| line.len() + 1).sum();
+    let column_bytes: usize = lines[line].graphemes(true).take(column).map(str::len).sum();
+    line_start + column_bytes
+}
 
-            let next_line = current_line + 1;
-            let next_line_start = self.offset_at_line_start(next_line);
-            let next_line_len = self.line_length(next_line);
+/// Returns the zero-based line containing `offset`. An offset sitting on a
+/// `\n` belongs to the line that newline terminates; the offset right after
+/// it is the start of the next line.
+fn line_index_at(text: &str, offset: usize) -> usize {
+    let offset = offset.min(text.len());
+    text.as_bytes()[..offset]
+        .iter()
+        .filter(|&&byte| byte == b'\n')
+        .count()
+}
 
-            let new_offset = next_line_start + x_offset.min(next_line_len);
-            self.move_to(new_offset, cx);
-        }
+fn save_new_note(
+    repository: &NoteRepository,
+    notes: &[Note],
+    placement: NewNotePlacement,
+    mut new_note: Note,
+) -> Result<Note, rusqlite::Error> {
+    let sort_indices = notes.iter().map(|note| note.sort_index);
+    let placed = match placement {
+        NewNotePlacement::Top => sort_indices.min().map(|min| min - 1),
+        NewNotePlacement::Bottom => sort_indices.max().map(|max| max + 1),
+    };
+    new_note.sort_index = placed.unwrap_or(new_note.sort_index);
+    repository.create_note(&new_note)?;
+    Ok(new_note)
+}
+
+/// Lists a just-saved note where `order` puts it and selects it. Archived
+/// notes follow the listed ones, so it goes among the listed ones, where the
+/// next reload puts it.
+fn list_new_note(
+    notes: &mut Vec<Note>,
+    active_note_id: &mut Option<Uuid>,
+    order: SortOrder,
+    new_note: Note,
+) {
+    let listed = notes.iter().take_while(|note| !note.archived).count();
+    let index = order.insertion_index(&notes[..listed], &new_note);
+    *active_note_id = Some(new_note.id);
+    notes.insert(index, new_note);
+}
+
+/// Where Home takes the caret from `cursor`: the first non-whitespace
+/// character on its line, or the line start when it's already there. On a
+/// whitespace-only line the first stop is the end of the whitespace.
+fn home_offset(text: &str, cursor: usize) -> usize {
+    let cursor = cursor.min(text.len());
+    let line_start = text[..cursor].rfind('\n').map_or(0, |index| index + 1);
+    let line_end = text[cursor..].find('\n').map_or(text.len(), |index| cursor + index);
+    let line = &text[line_start..line_end];
+    let first_non_whitespace = line_start + line.len() - line.trim_start().len();
+    if cursor == first_non_whitespace {
+        line_start
+    } else {
+        first_non_whitespace
     }
 }
 
-impl EntityInputHandler for NoteEditor {
-    fn text_for_range(
-        &mut self,
-        range_utf16: Range<usize>,
-        actual_range: &mut Option<Range<usize>>,
-        _window: &mut Window,
-        _cx: &mut Context<Self>,
-    ) -> Option<String> {
-        let range = self.range_from_utf16(&range_utf16);
-        actual_range.replace(self.range_to_utf16(&range));
-        Some(self.content[range].to_string())
+fn grapheme_column(text: &str, line_start: usize, offset: usize) -> usize {
+    text.get(line_start..offset)
+        .map_or(0, |prefix| prefix.graphemes(true).count())
+}
+
+/// Recognizes `- `, `* `, `+ ` and `1. ` list items, with any leading
+/// indentation. Returns the byte length of the line's indent and marker, and
+/// the marker to start the following item with.
+fn list_item_marker(line: &str) -> Option<(usize, String)> {
+    let indent = leading_indent(line);
+    let rest = &line[indent.len()..];
+
+    if let Some(bullet) = ["- ", "* ", "+ "]
+        .into_iter()
+        .find(|bullet| rest.starts_with(bullet))
+    {
+        return Some((indent.len() + bullet.len(), format!("{}{}", indent, bullet)));
     }
 
-    fn selected_text_range(
-        &mut self,
-        _ignore_disabled_input: bool,
-        _window: &mut Window,
-        _cx: &mut Context<Self>,
-    ) -> Option<UTF16Selection> {
-        Some(UTF16Selection {
-            range: self.range_to_utf16(&self.selected_range),
-            reversed: self.selection_reversed,
-        })
+    let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+    if digits > 0 && rest[digits..].starts_with(". ") {
+        let number: u64 = rest[..digits].parse().ok()?;
+        return Some((
+            indent.len() + digits + 2,
+            format!("{}{}. ", indent, number + 1),
+        ));
+    }
+
+    None
+}
+
+/// The first line of `content`, before `Settings::limit_title` cuts it down.
+/// Titles are stored in the clear, so encrypted and locked notes never get one.
+fn auto_title(note: &Note, content: &str) -> Option<String> {
+    if note.encrypted || note.locked {
+        return None;
+    }
+    let first_line = content.lines().next().unwrap_or("").trim();
+    (!first_line.is_empty()).then(|| first_line.to_string())
+}
+
+fn markdown_link_for_paste(selection: &str, pasted: &str) -> Option<String> {
+    let url = pasted.trim();
+    let is_url = ["http://", "https://", "mailto:"].into_iter().any(|scheme| {
+        url.len() > scheme.len()
+            && url
+                .get(..scheme.len())
+                .is_some_and(|prefix| prefix.eq_ignore_ascii_case(scheme))
+    }) && !url.contains(char::is_whitespace);
+
+    (is_url && !selection.is_empty()).then(|| format!("[{}]({})", selection, url))
+}
+
+fn selection_for_new_content(
+    old_content: &str,
+    selection: &Range<usize>,
+    new_content: &str,
+) -> Range<usize> {
+    if old_content == new_content {
+        selection.clone()
+    } else {
+        new_content.len()..new_content.len()
+    }
+}
+
+/// Splits a line `len` bytes long into runs at the edges of `marked` and of
+/// the part of `selected` inside it, pairing each run with its underline
+/// thickness: none outside the marked text, `thickness` under it and twice
+/// that under the selected clause.
+fn marked_text_spans(
+    len: usize,
+    marked: Range<usize>,
+    selected: Range<usize>,
+    thickness: f32,
+) -> Vec<(usize, Option<f32>)> {
+    let marked = marked.start.min(len)..marked.end.min(len);
+    let clause = selected.start.clamp(marked.start, marked.end)
+        ..selected.end.clamp(marked.start, marked.end);
+
+    [
+        (marked.start, None),
+        (clause.start - marked.start, Some(thickness)),
+        (clause.end - clause.start, Some(thickness * 2.)),
+        (marked.end - clause.end, Some(thickness)),
+        (len - marked.end, None),
+    ]
+    .into_iter()
+    .filter(|(len, _)| *len > 0)
+    .collect()
+}
+
+/// The parts of `selected` outside `marked`. Marked text marks its selected
+/// clause with a heavier underline instead, which a fill would drown out.
+fn selection_outside_marked(
+    selected: &Range<usize>,
+    marked: Option<&Range<usize>>,
+) -> Vec<Range<usize>> {
+    let Some(marked) = marked else {
+        return vec![selected.clone()];
+    };
+
+    [
+        selected.start..selected.end.min(marked.start),
+        selected.start.max(marked.end)..selected.end,
+    ]
+    .into_iter()
+    .filter(|range| !range.is_empty())
+    .collect()
+}
+
+fn is_composing(marked_range: Option<&Range<usize>>) -> bool {
+    marked_range.is_some_and(|range| !range.is_empty())
+}
+
+fn scrollbar_thumb(viewport: f32, max_scroll: f32, scroll_top: f32) -> Option<(f32, f32)> {
+    if viewport <= 0. || max_scroll <= 0. {
+        return None;
+    }
+
+    let height = (viewport * viewport / (viewport + max_scroll))
+        .max(MIN_SCROLLBAR_THUMB)
+        .min(viewport);
+    let progress = (scroll_top / max_scroll).clamp(0., 1.);
+    Some((progress * (viewport - height), height))
+}
+
+fn scroll_top_for_thumb(thumb_top: f32, thumb_height: f32, viewport: f32, max_scroll: f32) -> f32 {
+    let travel = viewport - thumb_height;
+    if travel <= 0. {
+        return 0.;
+    }
+    (thumb_top / travel).clamp(0., 1.) * max_scroll
+}
+
+const ANNOUNCED_CONTENT_CHARS: usize = 1000;
+
+fn note_announcement(
+    title: &str,
+    position: Option<(usize, usize)>,
+    content: Option<&str>,
+) -> String {
+    let mut message = title.to_string();
+    if let Some((index, count)) = position {
+        message.push_str(&format!(", note {} of {}", index + 1, count));
+    }
+    let body = match content.map(str::trim) {
+        None => "Locked".to_string(),
+        Some("") => "Empty note".to_string(),
+        Some(text) => text.chars().take(ANNOUNCED_CONTENT_CHARS).collect(),
+    };
+    format!("{}. {}", message, body)
+}
+
+/// The note to keep open once `removed` leaves `ids`, the sidebar order from
+/// before: `active` if it is another note that still exists, otherwise the
+/// note that took the removed one's place, else the one before it.
+fn active_after_removal(ids: &[Uuid], active: Option<Uuid>, removed: Uuid) -> Option<Uuid> {
+    if let Some(active) = active.filter(|&active| active != removed) {
+        return Some(active);
+    }
+    match ids.iter().position(|&id| id == removed) {
+        Some(position) => ids
+            .get(position + 1)
+            .or_else(|| position.checked_sub(1).and_then(|previous| ids.get(previous)))
+            .copied(),
+        None => ids.first().copied(),
+    }
+}
+
+/// `selection` limited to `content`, with each end moved back onto a char
+/// boundary.
+fn clamp_selection(selection: &Range<usize>, content: &str) -> Range<usize> {
+    let clamp = |offset: usize| {
+        let mut offset = offset.min(content.len());
+        while !content.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        offset
+    };
+    clamp(selection.start)..clamp(selection.end)
+}
+
+/// The horizontal extent of the part of `range` on the line that starts at
+/// `line_start`. A range running past the line is clipped to its end.
+fn range_extent_on_line(
+    geometry: &LineGeometry,
+    line_start: usize,
+    range: &Range<usize>,
+) -> (f32, f32) {
+    let line_end = line_start + geometry.text_len();
+    let start_x = geometry.x_for_index(range.start.clamp(line_start, line_end) - line_start);
+    let end_x = geometry.x_for_index(range.end.clamp(line_start, line_end) - line_start);
+    (start_x.min(end_x), start_x.max(end_x))
+}
+
+fn line_index_for_y(relative_y: f32, line_pitch: f32) -> usize {
+    if relative_y <= 0. || line_pitch <= 0. {
+        return 0;
+    }
+    (relative_y / line_pitch).floor() as usize
+}
+
+/// Maps a click on `line_index` to an offset in `text`. `index_for_x`
+/// resolves the column on a non-empty line; a click anywhere on an empty
+/// line lands on its start, and a click below the last line lands at the end.
+fn offset_for_line_click(
+    text: &str,
+    line_index: usize,
+    index_for_x: impl FnOnce(&str) -> usize,
+) -> usize {
+    let mut line_start = 0;
+    for (index, line) in text.split('\n').enumerate() {
+        if index == line_index {
+            if line.is_empty() {
+                return line_start;
+            }
+            let column = floor_char_boundary(line, index_for_x(line));
+            return line_start + column;
+        }
+        line_start += line.len() + 1;
+    }
+    text.len()
+}
+
+/// The line holding `offset`, together with the newline that ends it so a
+/// copied line pastes back as a line. The last line has no newline to take.
+fn line_selection_range(text: &str, offset: usize) -> Range<usize> {
+    let offset = floor_char_boundary(text, offset.min(text.len()));
+    let start = text[..offset].rfind('\n').map_or(0, |index| index + 1);
+    let end = text[offset..]
+        .find('\n')
+        .map_or(text.len(), |index| offset + index + 1);
+    start..end
+}
+
+/// Counts shown in the note info panel. Characters are graphemes, and an
+/// empty note still has the one line the caret sits on.
+#[derive(Debug, PartialEq, Eq)]
+struct TextStats {
+    words: usize,
+    characters: usize,
+    lines: usize,
+}
+
+fn text_stats(text: &str) -> TextStats {
+    TextStats {
+        words: text.unicode_words().count(),
+        characters: text.graphemes(true).count(),
+        lines: text.split('\n').count(),
+    }
+}
+
+/// Whole minutes to read `content` at `wpm` words a minute, rounded up so
+/// any note with text takes at least one. Empty notes take 0, and a `wpm`
+/// of 0 counts as 1.
+fn reading_time_minutes(content: &str, wpm: usize) -> usize {
+    if content.trim().is_empty() {
+        return 0;
+    }
+    content.unicode_words().count().div_ceil(wpm.max(1)).max(1)
+}
+
+/// What deleting the lines `selection` touches removes: the lines and the
+/// newline after them, or the newline before them when they end the text.
+/// A selection ending at the very start of a line leaves that line alone.
+fn deleted_lines_range(text: &str, selection: Range<usize>) -> Range<usize> {
+    let mut end = selection.end;
+    if end > selection.start && text[..end].ends_with('\n') {
+        end -= 1;
+    }
+
+    let start = text[..selection.start].rfind('\n').map_or(0, |index| index + 1);
+    match text[end..].find('\n') {
+        Some(index) => start..end + index + 1,
+        None if start > 0 => start - 1..text.len(),
+        None => start..text.len(),
+    }
+}
+
+fn splice(text: &str, range: Range<usize>, new_text: &str, new_cursor: usize) -> (String, usize) {
+    let mut result = String::with_capacity(text.len() - range.len() + new_text.len());
+    result.push_str(&text[..range.start]);
+    result.push_str(new_text);
+    result.push_str(&text[range.end..]);
+    let caret = floor_char_boundary(&result, new_cursor);
+    (result, caret)
+}
+
+const FALLBACK_TITLE: &str = "Untitled Note";
+
+/// Only the display falls back; the stored title is untouched, so renaming
+/// still starts from what is saved.
+fn display_title(note: &Note) -> String {
+    if note.title.trim().is_empty() {
+        FALLBACK_TITLE.to_string()
+    } else {
+        note.title.clone()
+    }
+}
+
+/// Archived notes stay out of the menu bar like they stay out of the main
+/// list.
+fn status_item_notes(notes: &[Note]) -> Vec<(Uuid, String)> {
+    notes
+        .iter()
+        .filter(|note| note.starred && !note.archived)
+        .map(|note| (note.id, display_title(note)))
+        .collect()
+}
+
+fn note_as_markdown(title: &str, content: &str) -> String {
+    format!("# {}\n\n{}", title, content)
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 3] = ["KB", "MB", "GB"];
+    if bytes < 1024 {
+        return format!("{} bytes", bytes);
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+fn on_off(enabled: bool) -> String {
+    if enabled { "On" } else { "Off" }.to_string()
+}
+
+/// The preset after `current`, wrapping around. A value set by hand in
+/// settings.json that isn't a preset goes back to the first one.
+fn next_preset(presets: &[f32], current: f32) -> f32 {
+    presets
+        .iter()
+        .position(|preset| (preset - current).abs() < 0.001)
+        .map_or(presets[0], |index| presets[(index + 1) % presets.len()])
+}
+
+/// Case-insensitive match of `query` against a note's title, and its content
+/// unless the note is encrypted. An empty query matches every note.
+fn note_matches_query(note: &Note, query: &str) -> bool {
+    if query.is_empty() {
+        return true;
+    }
+
+    let query = query.to_lowercase();
+    note.title.to_lowercase().contains(&query)
+        || (!note.encrypted && note.content.to_lowercase().contains(&query))
+}
+
+/// Byte ranges of the non-overlapping case-insensitive occurrences of
+/// `query` in `text`, left to right.
+fn query_match_ranges(text: &str, query: &str) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    if query.is_empty() {
+        return ranges;
+    }
+
+    let mut search_from = 0;
+    for (start, _) in text.char_indices() {
+        if start < search_from {
+            continue;
+        }
+
+        let mut text_chars = text[start..].char_indices();
+        let mut matched_len = Some(0);
+        for query_char in query.chars() {
+            match text_chars.next() {
+                Some((offset, c)) if c.to_lowercase().eq(query_char.to_lowercase()) => {
+                    matched_len = Some(offset + c.len_utf8());
+                }
+                _ => {
+                    matched_len = None;
+                    break;
+                }
+            }
+        }
+
+        if let Some(len) = matched_len {
+            ranges.push(start..start + len);
+            search_from = start + len;
+        }
+    }
+    ranges
+}
+
+fn render_highlighted_title(title: &str, query: &str) -> impl IntoElement {
+    let mut segments = Vec::new();
+    let mut end_of_last_match = 0;
+    for range in query_match_ranges(title, query) {
+        if range.start > end_of_last_match {
+            segments.push((title[end_of_last_match..range.start].to_string(), false));
+        }
+        segments.push((title[range.clone()].to_string(), true));
+        end_of_last_match = range.end;
+    }
+    if end_of_last_match < title.len() || segments.is_empty() {
+        segments.push((title[end_of_last_match..].to_string(), false));
+    }
+
+    div()
+        .flex()
+        .overflow_hidden()
+        .whitespace_nowrap()
+        .children(segments.into_iter().map(|(text, matched)| {
+            div()
+                .when(matched, |segment| {
+                    segment
+                        .flex_none()
+                        .font_weight(FontWeight::BOLD)
+                        .bg(rgb(0xfff1b8))
+                        .rounded_sm()
+                })
+                .when(!matched, |segment| segment.truncate())
+                .child(text)
+        }))
+}
+
+fn render_markdown_block(block: MarkdownBlock, query: &str) -> gpui::AnyElement {
+    match block {
+        MarkdownBlock::Heading { level, text } => {
+            let heading = div()
+                .font_weight(FontWeight::BOLD)
+                .child(render_inline(&text, query));
+            match level {
+                1 => heading.text_2xl(),
+                2 => heading.text_xl(),
+                3 => heading.text_lg(),
+                _ => heading,
+            }
+            .into_any_element()
+        }
+        MarkdownBlock::Paragraph(text) => render_inline(&text, query).into_any_element(),
+        MarkdownBlock::ListItem {
+            depth,
+            marker,
+            text,
+        } => div()
+            .flex()
+            .gap_2()
+            .pl(px(16.0 * depth as f32))
+            .child(div().flex_none().text_color(rgb(0x888888)).child(marker))
+            .child(render_inline(&text, query))
+            .into_any_element(),
+        MarkdownBlock::Quote(text) => div()
+            .pl_3()
+            .border_l_2()
+            .border_color(rgb(0xd0d0d0))
+            .text_color(rgb(0x666666))
+            .child(render_inline(&text, query))
+            .into_any_element(),
+        MarkdownBlock::CodeBlock(code) => div()
+            .flex()
+            .flex_col()
+            .p_2()
+            .rounded_md()
+            .bg(rgb(0xf5f5f5))
+            .font_family("monospace")
+            .text_sm()
+            .children(code.lines().map(|line| {
+                let span = InlineSpan {
+                    text: line.to_string(),
+                    ..InlineSpan::default()
+                };
+                render_spans(vec![span], query).min_h(px(16.0))
+            }))
+            .into_any_element(),
+        MarkdownBlock::Rule => div().h(px(1.0)).bg(rgb(0xE0E0E0)).into_any_element(),
+    }
+}
+
+fn render_inline(text: &str, query: &str) -> gpui::Div {
+    render_spans(parse_inline(text), query)
+}
+
+fn render_spans(spans: Vec<InlineSpan>, query: &str) -> gpui::Div {
+    let text: String = spans.iter().map(|span| span.text.as_str()).collect();
+    let spans = highlight_ranges(spans, &query_match_ranges(&text, query));
+
+    div()
+        .flex()
+        .flex_wrap()
+        .children(spans.into_iter().map(|span| {
+            div()
+                .when(span.highlight, |this| this.bg(rgb(0xfff1b8)).rounded_sm())
+                .when(span.bold, |this| this.font_weight(FontWeight::BOLD))
+                .when(span.italic, |this| this.italic())
+                .when(span.code, |this| {
+                    this.font_family("monospace").bg(rgb(0xf0f0f0)).rounded_sm()
+                })
+                .when(span.link, |this| this.text_color(rgb(0x4287f5)))
+                .child(span.text)
+        }))
+}
+
+fn floor_char_boundary(text: &str, offset: usize) -> usize {
+    let mut offset = offset.min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+impl EntityInputHandler for NoteEditor {
+    fn text_for_range(
+        &mut self,
+        range_utf16: Range<usize>,
+        actual_range: &mut Option<Range<usize>>,
+        _window: &mut Window,
+        _cx: &mut Context<Self>,
+    ) -> Option<String> {
+        let range = self.range_from_utf16(&range_utf16);
+        actual_range.replace(self.range_to_utf16(&range));
+        Some(self.content[range].to_string())
+    }
+
+    fn selected_text_range(
+        &mut self,
+        _ignore_disabled_input: bool,
+        _window: &mut Window,
+        _cx: &mut Context<Self>,
+    ) -> Option<UTF16Selection> {
+        Some(UTF16Selection {
+            range: self.range_to_utf16(&self.selected_range),
+            reversed: self.selection_reversed,
+        })
     }
 
     fn marked_text_range(
@@ -458,23 +1613,27 @@ impl EntityInputHandler for NoteEditor {
         _: &mut Window,
         cx: &mut Context<Self>,
     ) {
+        if self.vim_mode && self.edit_mode == EditMode::Normal {
+            return;
+        }
+        if self.refuse_read_only_edit(cx) {
+            return;
+        }
+
+        if range_utf16.is_none()
+            && self.marked_range.is_none()
+            && self.handle_auto_close(new_text, cx)
+        {
+            return;
+        }
+
         let range = range_utf16
             .as_ref()
             .map(|range_utf16| self.range_from_utf16(range_utf16))
             .or(self.marked_range.clone())
             .unwrap_or(self.selected_range.clone());
 
-        self.content =
-            (self.content[0..range.start].to_owned() + new_text + &self.content[range.end..])
-                .into();
-        self.selected_range = range.start + new_text.len()..range.start + new_text.len();
-        self.marked_range.take();
-
-        if let Some(on_change) = &self.on_change {
-            on_change(self.content.to_string(), cx);
-        }
-
-        cx.notify();
+        self.edit(range.clone(), new_text, range.start + new_text.len(), cx);
     }
 
     fn replace_and_mark_text_in_range(
@@ -485,27 +1644,27 @@ impl EntityInputHandler for NoteEditor {
         _window: &mut Window,
         cx: &mut Context<Self>,
     ) {
+        if self.vim_mode && self.edit_mode == EditMode::Normal {
+            return;
+        }
+        if self.refuse_read_only_edit(cx) {
+            return;
+        }
+
         let range = range_utf16
             .as_ref()
             .map(|range_utf16| self.range_from_utf16(range_utf16))
             .or(self.marked_range.clone())
             .unwrap_or(self.selected_range.clone());
 
-        self.content =
-            (self.content[0..range.start].to_owned() + new_text + &self.content[range.end..])
-                .into();
+        self.edit(range.clone(), new_text, range.start + new_text.len(), cx);
         self.marked_range = Some(range.start..range.start + new_text.len());
-        self.selected_range = new_selected_range_utf16
+        if let Some(new_range) = new_selected_range_utf16
             .as_ref()
             .map(|range_utf16| self.range_from_utf16(range_utf16))
-            .map(|new_range| new_range.start + range.start..new_range.end + range.end)
-            .unwrap_or_else(|| range.start + new_text.len()..range.start + new_text.len());
-
-        if let Some(on_change) = &self.on_change {
-            on_change(self.content.to_string(), cx);
+        {
+            self.selected_range = new_range.start + range.start..new_range.end + range.end;
         }
-
-        cx.notify();
     }
 
     fn bounds_for_range(
@@ -515,17 +1674,15 @@ impl EntityInputHandler for NoteEditor {
         _window: &mut Window,
         _cx: &mut Context<Self>,
     ) -> Option<gpui::Bounds<Pixels>> {
-        let last_layout = self.last_layout.as_ref()?;
         let range = self.range_from_utf16(&range_utf16);
+        let line_index = self.line_at_offset(range.start);
+        let (_, geometry, line_start) = self.last_lines.get(line_index)?;
+        let line_top = bounds.top() + self.line_pitch() * line_index as f32;
+        let (left, right) = range_extent_on_line(geometry, *line_start, &range);
+
         Some(gpui::Bounds::from_corners(
-            point(
-                bounds.left() + last_layout.x_for_index(range.start),
-                bounds.top(),
-            ),
-            point(
-                bounds.left() + last_layout.x_for_index(range.end),
-                bounds.bottom(),
-            ),
+            point(bounds.left() + px(left), line_top),
+            point(bounds.left() + px(right), line_top + self.line_height),
         ))
     }
 
@@ -535,16 +1692,26 @@ impl EntityInputHandler for NoteEditor {
         _window: &mut Window,
         _cx: &mut Context<Self>,
     ) -> Option<usize> {
-        let line_point = self.last_bounds?.localize(&point)?;
-        let last_layout = self.last_layout.as_ref()?;
-
-        assert_eq!(last_layout.text, self.content);
-        let utf8_index = last_layout.index_for_x(point.x - line_point.x)?;
+        self.last_bounds?.localize(&point)?;
+        let utf8_index = self.index_for_mouse_position(point);
         Some(self.offset_to_utf16(utf8_index))
     }
 }
 
 impl TitleEditor {
+    fn new(cx: &mut Context<Self>) -> Self {
+        Self {
+            focus_handle: cx.focus_handle(),
+            content: SharedString::from(""),
+            selected_range: 0..0,
+            selection_reversed: false,
+            caret_blink: CaretBlink::default(),
+            masked: false,
+            placeholder: SharedString::default(),
+            on_change: None,
+        }
+    }
+
     fn set_content(&mut self, content: impl Into<SharedString>, cx: &mut Context<Self>) {
         self.content = content.into();
         self.selected_range = self.content.len()..self.content.len();
@@ -604,6 +1771,8 @@ impl TitleEditor {
     }
 
     fn on_key_down(&mut self, event: &KeyDownEvent, window: &mut Window, cx: &mut Context<Self>) {
+        self.pause_caret_blink(cx);
+
         if event.keystroke.key_char.is_some() {
             return;
         } else if event.keystroke.key == "backspace" {
@@ -727,8 +1896,16 @@ impl Focusable for TitleEditor {
     }
 }
 
+impl BlinkingCaret for TitleEditor {
+    fn caret_blink(&mut self) -> &mut CaretBlink {
+        &mut self.caret_blink
+    }
+}
+
 impl Render for TitleEditor {
-    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let focused = self.focus_handle.is_focused(window);
+        self.sync_caret_blink(focused, cx);
         div()
             .flex()
             .size_full()
@@ -786,12 +1963,24 @@ impl Element for TitleEditorView {
         cx: &mut App,
     ) -> Self::PrepaintState {
         let editor = self.editor.read(cx);
-        let content = editor.content.clone();
         let focus_handle = editor.focus_handle.clone();
+        let (content, cursor_index) = if editor.masked {
+            let mask = '•';
+            let masked_cursor =
+                editor.content[..editor.selected_range.start].chars().count() * mask.len_utf8();
+            let masked: String = editor.content.chars().map(|_| mask).collect();
+            (SharedString::from(masked), masked_cursor)
+        } else {
+            (editor.content.clone(), editor.selected_range.start)
+        };
 
         let style = window.text_style();
         let font_size = style.font_size.to_pixels(window.rem_size());
-        let text_color = style.color;
+        let (content, text_color) = if content.is_empty() && !editor.placeholder.is_empty() {
+            (editor.placeholder.clone(), rgb(0xAAAAAA).into())
+        } else {
+            (content, style.color)
+        };
 
         let run = TextRun {
             len: content.len(),
@@ -810,7 +1999,7 @@ impl Element for TitleEditorView {
         let cursor = if focus_handle.is_focused(window)
             && editor.selected_range.start == editor.selected_range.end
         {
-            let cursor_pos = text.x_for_index(editor.selected_range.start);
+            let cursor_pos = text.x_for_index(cursor_index);
             Some(gpui::fill(
                 gpui::Bounds::new(
                     point(bounds.left() + cursor_pos, bounds.top()),
@@ -848,7 +2037,7 @@ impl Element for TitleEditorView {
                 .paint(bounds.origin, window.line_height(), window, cx)
                 .unwrap();
 
-            if focus_handle.is_focused(window) {
+            if focus_handle.is_focused(window) && self.editor.read(cx).caret_blink.visible() {
                 if let Some(cursor) = state.cursor {
                     window.paint_quad(cursor);
                 }
@@ -862,7 +2051,7 @@ struct EditorView {
 }
 
 struct PrepaintState {
-    lines: Vec<(ShapedLine, usize)>,
+    lines: Vec<(ShapedLine, LineGeometry, usize)>,
     cursor: Option<PaintQuad>,
     selection: Vec<PaintQuad>,
 }
@@ -892,9 +2081,9 @@ impl Element for EditorView {
         let mut style = Style::default();
         style.size.width = relative(1.).into();
 
-        let content = self.editor.read(cx).content.clone();
-        let line_count = content.split('\n').count();
-        let height = window.line_height().0 * line_count as f32;
+        let editor = self.editor.read(cx);
+        let line_count = editor.content.split('\n').count();
+        let height = editor.line_pitch().0 * line_count as f32;
 
         style.size.height = px(height).into();
         (window.request_layout(style, [], cx), ())
@@ -912,9 +2101,17 @@ impl Element for EditorView {
         let content = editor.content.clone();
         let selected_range = editor.selected_range.clone();
         let cursor = editor.cursor_offset();
+        let line_height = editor.line_height;
+        let line_pitch = editor.line_pitch();
         let style = window.text_style();
         let font_size = style.font_size.to_pixels(window.rem_size());
         let text_color = style.color;
+        let tab_width = editor.tab_width;
+        let highlights = editor.highlights;
+        let marked_range = editor
+            .marked_range
+            .clone()
+            .filter(|marked_range| !marked_range.is_empty());
         let mut shaped_lines = Vec::new();
         let mut offset = 0;
         let mut selections = Vec::new();
@@ -930,9 +2127,11 @@ impl Element for EditorView {
                 } else {
                     0
                 };
+            let expanded = ExpandedLine::new(line_text, tab_width);
+            let display_len = expanded.text.len();
 
             let run = TextRun {
-                len: line_text.len(),
+                len: display_len,
                 font: style.font(),
                 color: text_color,
                 background_color: None,
@@ -940,83 +2139,91 @@ impl Element for EditorView {
                 strikethrough: None,
             };
 
-            let runs = if let Some(marked_range) = editor.marked_range.as_ref() {
-                if offset + total_len > marked_range.start && offset < marked_range.end {
-                    let marked_start = marked_range.start.saturating_sub(offset);
-                    let marked_end = (marked_range.end - offset).min(line_len);
-
-                    vec![
-                        TextRun {
-                            len: marked_start.min(line_len),
-                            ..run.clone()
-                        },
-                        TextRun {
-                            len: marked_end.saturating_sub(marked_start),
-                            underline: Some(UnderlineStyle {
-                                color: Some(run.color),
-                                thickness: px(1.0),
-                                wavy: false,
-                            }),
-                            ..run.clone()
-                        },
-                        TextRun {
-                            len: line_len.saturating_sub(marked_end),
-                            ..run.clone()
-                        },
-                    ]
+            let runs = match marked_range.as_ref() {
+                Some(marked_range)
+                    if offset + total_len > marked_range.start && offset < marked_range.end =>
+                {
+                    let to_display = |source: usize| {
+                        expanded.display_offset(source.saturating_sub(offset).min(line_len))
+                    };
+                    let underline_color = highlights
+                        .marked_underline
+                        .map_or(run.color, |color| rgba(color).into());
+
+                    marked_text_spans(
+                        display_len,
+                        to_display(marked_range.start)..to_display(marked_range.end),
+                        to_display(selected_range.start)..to_display(selected_range.end),
+                        highlights.marked_underline_thickness,
+                    )
                     .into_iter()
-                    .filter(|run| run.len > 0)
+                    .map(|(len, thickness)| TextRun {
+                        len,
+                        underline: thickness.map(|thickness| UnderlineStyle {
+                            color: Some(underline_color),
+                            thickness: px(thickness),
+                            wavy: false,
+                        }),
+                        ..run.clone()
+                    })
                     .collect()
-                } else {
-                    vec![run.clone()]
                 }
-            } else {
-                vec![run.clone()]
+                _ => vec![run.clone()],
             };
 
             let shaped = window
                 .text_system()
-                .shape_line(SharedString::from(line_text), font_size, &runs)
+                .shape_line(SharedString::from(expanded.text.clone()), font_size, &runs)
                 .unwrap();
+            let geometry = LineGeometry::new(
+                line_text,
+                shaped.runs.iter().flat_map(|run| {
+                    run.glyphs.iter().filter_map(|glyph| {
+                        let index = expanded.source_index(glyph.index)?;
+                        Some((index, glyph.position.x.0))
+                    })
+                }),
+                shaped.width.0,
+                bounds.size.width.0,
+            );
 
             let line_index = shaped_lines.len();
-            let line_y = bounds.top() + (line_index as f32 * window.line_height());
+            let line_y = bounds.top() + (line_index as f32 * line_pitch);
 
             if !selected_range.is_empty() {
                 if offset + line_len >= selected_range.start && offset < selected_range.end {
-                    let sel_start = (selected_range.start.saturating_sub(offset)).min(line_len);
-                    let sel_end = (selected_range.end.saturating_sub(offset)).min(line_len);
-
-                    if sel_start < sel_end {
-                        selections.push(gpui::fill(
-                            gpui::Bounds::from_corners(
-                                point(bounds.left() + shaped.x_for_index(sel_start), line_y),
-                                point(
-                                    bounds.left() + shaped.x_for_index(sel_end),
-                                    line_y + window.line_height(),
+                    for range in selection_outside_marked(&selected_range, marked_range.as_ref()) {
+                        let sel_start = (range.start.saturating_sub(offset)).min(line_len);
+                        let sel_end = (range.end.saturating_sub(offset)).min(line_len);
+
+                        for (left, right) in geometry.selection_spans(sel_start..sel_end) {
+                            selections.push(gpui::fill(
+                                gpui::Bounds::from_corners(
+                                    point(bounds.left() + px(left), line_y),
+                                    point(bounds.left() + px(right), line_y + line_height),
                                 ),
-                            ),
-                            rgba(0x3311ff30),
-                        ));
+                                rgba(highlights.selection),
+                            ));
+                        }
                     }
                 }
             } else if offset <= cursor && cursor <= offset + total_len {
                 let cursor_pos = if cursor > offset + line_len {
-                    shaped.x_for_index(line_len)
+                    geometry.x_for_index(line_len)
                 } else {
-                    shaped.x_for_index(cursor - offset)
+                    geometry.x_for_index(cursor - offset)
                 };
 
                 cursor_quad = Some(gpui::fill(
                     gpui::Bounds::new(
-                        point(bounds.left() + cursor_pos, line_y),
-                        size(px(2.), window.line_height()),
+                        point(bounds.left() + px(cursor_pos), line_y),
+                        size(px(2.), line_height),
                     ),
                     gpui::blue(),
                 ));
             }
 
-            shaped_lines.push((shaped, offset));
+            shaped_lines.push((shaped, geometry, offset));
             offset += total_len;
         }
 
@@ -1037,6 +2244,8 @@ impl Element for EditorView {
         cx: &mut App,
     ) {
         let focus_handle = self.editor.read(cx).focus_handle.clone();
+        let line_height = self.editor.read(cx).line_height;
+        let line_pitch = self.editor.read(cx).line_pitch();
         window.handle_input(
             &focus_handle,
             ElementInputHandler::new(bounds, self.editor.clone()),
@@ -1047,14 +2256,17 @@ impl Element for EditorView {
             window.paint_quad(selection);
         }
 
-        for (i, (line, _)) in prepaint.lines.iter().enumerate() {
-            let y_offset = i as f32 * window.line_height();
-            let line_origin = point(bounds.origin.x, bounds.origin.y + y_offset);
-            line.paint(line_origin, window.line_height(), window, cx)
+        for (i, (line, geometry, _)) in prepaint.lines.iter().enumerate() {
+            let y_offset = i as f32 * line_pitch;
+            let line_origin = point(
+                bounds.origin.x + px(geometry.origin_x),
+                bounds.origin.y + y_offset,
+            );
+            line.paint(line_origin, line_height, window, cx)
                 .unwrap();
         }
 
-        if focus_handle.is_focused(window) {
+        if focus_handle.is_focused(window) && self.editor.read(cx).caret_blink.visible() {
             if let Some(cursor) = prepaint.cursor.take() {
                 window.paint_quad(cursor);
             }
@@ -1063,9 +2275,7 @@ impl Element for EditorView {
         let lines = std::mem::take(&mut prepaint.lines);
 
         self.editor.update(cx, |editor, _cx| {
-            if let Some((first_line, _)) = lines.first() {
-                editor.last_layout = Some(first_line.clone());
-            }
+            editor.last_lines = lines;
             editor.last_bounds = Some(bounds);
         });
     }
@@ -1077,8 +2287,16 @@ impl Focusable for NoteEditor {
     }
 }
 
+impl BlinkingCaret for NoteEditor {
+    fn caret_blink(&mut self) -> &mut CaretBlink {
+        &mut self.caret_blink
+    }
+}
+
 impl Render for NoteEditor {
-    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let focused = self.focus_handle.is_focused(window);
+        self.sync_caret_blink(focused, cx);
         div()
             .flex()
             .size_full()
@@ -1132,7 +2350,13 @@ impl NoteApp {
             }
         };
 
-        let notes = match db.notes.list_notes() {
+        let settings = Settings::load(&get_settings_path());
+        Self::purge_expired_trash(&db, &settings);
+        let db_maintenance = (settings.maintenance_interval_minutes > 0).then(|| {
+            db.start_maintenance(Duration::from_secs(settings.maintenance_interval_minutes * 60))
+        });
+
+        let notes = match Self::load_notes(&db) {
             Ok(notes) => {
                 println!("Loaded {} notes from database", notes.len());
                 println!(
@@ -1141,15 +2365,17 @@ impl NoteApp {
                 );
 
                 if notes.is_empty() {
-                    let mut welcome_note = Note::new("Welcome".into());
-                    welcome_note.content = "Welcome to your new note-taking app!".into();
+                    let welcome_note = Note::with_content(
+                        "Welcome".into(),
+                        "Welcome to your new note-taking app!",
+                    );
 
                     if let Err(e) = db.notes.create_note(&welcome_note) {
                         eprintln!("Failed to create welcome note: {}", e);
                     } else {
                         println!("Created welcome note");
 
-                        match db.notes.list_notes() {
+                        match db.notes.list_notes(SortOrder::Manual) {
                             Ok(notes_after_welcome) => {
                                 println!(
                                     "After creating welcome note, there are {} notes in DB",
@@ -1168,8 +2394,8 @@ impl NoteApp {
             }
             Err(e) => {
                 eprintln!("Failed to load notes: {}", e);
-                let mut welcome_note = Note::new("Welcome".into());
-                welcome_note.content = "Welcome to your new note-taking app!".into();
+                let welcome_note =
+                    Note::with_content("Welcome".into(), "Welcome to your new note-taking app!");
                 vec![welcome_note]
             }
         };
@@ -1187,13 +2413,29 @@ impl NoteApp {
                 selected_range: 0..0,
                 selection_reversed: false,
                 marked_range: None,
-                last_layout: None,
+                last_lines: Vec::new(),
                 last_bounds: None,
                 is_selecting: false,
+                caret_blink: CaretBlink::default(),
+                auto_close_brackets: settings.auto_close_brackets,
+                paste_url_as_link: settings.paste_url_as_link,
+                indent_style: settings.indent_style,
+                tab_width: settings.tab_width,
+                highlights: settings.editor_highlights,
+                history: UndoHistory::new(settings.undo_limits),
+                line_height: px(settings.editor_line_height()),
+                paragraph_spacing: px(settings.paragraph_spacing),
+                vim_mode: settings.vim_mode,
+                edit_mode: EditMode::Normal,
+                pending_vim_operator: None,
+                binding: EditorBinding::default(),
+                read_only: false,
                 on_change: None,
+                on_paste_image: None,
+                on_read_only_edit: None,
             };
 
-            if let Some(first_note) = notes.first() {
+            if let Some(first_note) = notes.first().filter(|note| !note.encrypted) {
                 editor.content = first_note.content.clone().into();
                 editor.selected_range = editor.content.len()..editor.content.len();
             }
@@ -1206,39 +2448,19 @@ impl NoteApp {
             content: SharedString::from(initial_title.clone()),
             selected_range: initial_title.len()..initial_title.len(),
             selection_reversed: false,
+            caret_blink: CaretBlink::default(),
+            masked: false,
+            placeholder: SharedString::from("Untitled"),
             on_change: None,
         });
 
-        let db_clone = db.clone();
-        let active_note_id_clone = active_note_id;
-        editor.update(cx, move |editor, cx| {
-            editor.set_on_change(move |content, _cx| {
-                if let Some(note_id) = active_note_id_clone {
-                    if let Ok(Some(existing_note)) = db_clone.notes.get_note(&note_id.to_string()) {
-                        if let Err(e) = db_clone.notes.update_note(&Note {
-                            id: note_id,
-                            title: existing_note.title,
-                            content: content.clone(),
-                            created_at: existing_note.created_at,
-                        }) {
-                            eprintln!("Failed to update note content: {}", e);
-                        }
-                    }
-                }
-            });
-        });
-
-        let db_clone = db.clone();
-        let active_note_id_clone = active_note_id;
-        let app_entity = cx.entity();
         let app_entity_clone = cx.entity();
-        let active_note_id_for_on_change = active_note_id;
         title_editor.update(cx, move |editor, cx| {
             editor.set_on_change(move |new_title, _cx| {
                 app_entity_clone.update(_cx, |app, cx| {
                     app.title_text = new_title.clone();
 
-                    if let Some(note_id) = active_note_id_for_on_change {
+                    if let Some(note_id) = app.active_note_id {
                         for note in &mut app.notes {
                             if note.id == note_id {
                                 note.title = new_title.clone();
@@ -1256,602 +2478,4021 @@ impl NoteApp {
             println!("Failed to dump database: {}", e);
         }
 
-        Self {
+        let db_watcher = Self::watch_db_changes(&db, &db_path, cx);
+        Self::listen_for_app_requests(cx);
+
+        let (outcome_sender, outcome_receiver) = mpsc::unbounded();
+        let writer = NoteWriter::spawn(db.clone(), outcome_sender);
+        Self::watch_write_outcomes(outcome_receiver, cx);
+
+        let onboarding_step = (!settings.onboarding_completed).then_some(0);
+        let mut app = Self {
             db,
+            writer,
+            settings,
+            save_transforms: Arc::new(SaveTransforms::with_builtins()),
             notes,
             active_note_id,
             editor,
             content_focus_handle: cx.focus_handle(),
+            sidebar_focus_handle: cx.focus_handle(),
             title_edit_mode: false,
             title_text: initial_title,
             title_focus_handle: cx.focus_handle(),
             title_editor,
+            show_replace_panel: false,
+            replace_find_editor: cx.new(|cx| TitleEditor::new(cx)),
+            search_editor: cx.new(|cx| TitleEditor::new(cx)),
+            search_query: String::new(),
+            replace_with_editor: cx.new(|cx| TitleEditor::new(cx)),
+            replace_case_sensitive: false,
+            replace_status: None,
+            _db_watcher: db_watcher,
+            _db_maintenance: db_maintenance,
+            error_message: None,
+            show_trash: false,
+            favorite_ids: None,
+            status_item_notes: None,
+            show_archived: false,
+            trashed_count: 0,
+            trashed_notes: Vec::new(),
+            auto_title_note_id: None,
+            selected_ids: HashSet::new(),
+            status_message: None,
+            show_shortcuts: false,
+            show_settings: false,
+            database_size: None,
+            duplicate_groups: None,
+            onboarding_step,
+            busy: None,
+            reduce_motion: false,
+            unlocked_passphrases: HashMap::new(),
+            passphrase_editor: cx.new(|cx| {
+                let mut editor = TitleEditor::new(cx);
+                editor.masked = true;
+                editor
+            }),
+            show_encrypt_prompt: false,
+            show_note_info: false,
+            note_attachments: String::new(),
+            show_template_menu: false,
+            open_note_ids: Vec::new(),
+            caret_status: ((1, 1), 0),
+            saved_flash: None,
+            saved_flash_count: 0,
+            content_scroll: ScrollHandle::new(),
+            preview_scroll: ScrollHandle::new(),
+            sidebar_scroll: ScrollHandle::new(),
+            scrollbar_drag: None,
+            missing_note: None,
+            pending_editor_focus: false,
+        };
+
+        cx.observe(&app.search_editor, |this, search_editor, cx| {
+            let query = search_editor.read(cx).content.trim().to_string();
+            if query != this.search_query {
+                this.search_query = query;
+                this.scroll_preview_to_match(cx);
+                cx.notify();
+            }
+        })
+        .detach();
+
+        let app_entity = cx.entity().downgrade();
+        app.editor.update(cx, |editor, _cx| {
+            editor.set_on_read_only_edit(move |cx| {
+                let app = app_entity.clone();
+                cx.defer(move |cx| {
+                    app.update(cx, |app, cx| app.report_status("Note is locked", cx)).ok();
+                });
+            });
+        });
+
+        cx.observe(&app.editor, |this, editor, cx| {
+            let editor = editor.read(cx);
+            let status = (editor.caret_line_column(), editor.selection_length());
+            if status != this.caret_status {
+                this.caret_status = status;
+                cx.notify();
+            }
+        })
+        .detach();
+
+        cx.on_app_quit(|app, cx| {
+            if app.title_edit_mode {
+                app.save_title(cx);
+            }
+            if !app.writer.flush(QUIT_FLUSH_TIMEOUT) {
+                app.report_error("Quitting before the latest edits were saved", cx);
+            }
+            async {}
+        })
+        .detach();
+
+        app.sync_save_transforms();
+        app.set_reduce_motion(reduce_motion_enabled(), cx);
+        app.sync_status_item();
+
+        let session = Session::load(&get_session_path());
+        app.open_note_ids = session
+            .open_note_ids
+            .into_iter()
+            .filter(|id| app.notes.iter().any(|note| note.id == *id))
+            .collect();
+        let restored_active_id = session
+            .active_note_id
+            .filter(|id| app.open_note_ids.contains(id))
+            .or_else(|| app.open_note_ids.first().copied())
+            .or(active_note_id);
+
+        if let Some(note_id) = restored_active_id {
+            app.set_active_note(note_id, cx);
         }
+        app.refresh_trash(cx);
+
+        app
     }
 
-    pub fn dump_database(&self) {
-        println!("Dumping database contents:");
-        match dump_db_contents() {
-            Ok(_) => println!("Database dump completed"),
-            Err(e) => println!("Failed to dump database: {}", e),
-        }
+    /// Every note outside the trash: the main list followed by the archive,
+    /// which the sidebar filters between.
+    fn load_notes(db: &Database) -> Result<Vec<Note>, rusqlite::Error> {
+        let mut notes = db.notes.list_notes(SIDEBAR_ORDER)?;
+        notes.extend(db.notes.list_archived(SIDEBAR_ORDER)?);
+        Ok(notes)
     }
 
-    pub fn add_note(&mut self, window: &mut Window, cx: &mut Context<Self>) {
-        let title = format!("Untitled {}", self.notes.len() + 1);
-        let new_note = Note::new(title.clone());
-        let new_id = new_note.id;
-
-        println!("Adding new note with ID: {}", new_id);
-
-        match self.db.notes.create_note(&new_note) {
-            Ok(()) => {
-                println!(
-                    "Successfully saved new note to database with ID: {}",
-                    new_id
-                );
-                self.notes.push(new_note);
-                self.active_note_id = Some(new_id);
-
-                self.editor.update(cx, |editor, cx| {
-                    editor.set_content("", cx);
-                });
-
-                self.title_text = title;
-                self.title_editor.update(cx, |editor, cx| {
-                    editor.set_content(self.title_text.clone(), cx);
-                });
+    fn purge_expired_trash(db: &Database, settings: &Settings) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let cutoff = now.saturating_sub(settings.trash_retention_days * 24 * 60 * 60);
+
+        match db.notes.purge_older_than(cutoff) {
+            Ok(0) => {}
+            Ok(purged) => {
+                println!("Purged {} notes from trash", purged);
+                if let Err(e) = db.notes.vacuum() {
+                    eprintln!("Failed to compact database after purge: {}", e);
+                }
+            }
+            Err(e) => eprintln!("Failed to purge trash: {}", e),
+        }
+    }
 
-                
-                let db_clone = self.db.clone();
-                let active_id = new_id;
-                self.editor.update(cx, move |editor, cx| {
-                    editor.set_on_change(move |content, _cx| {
-                        if let Ok(Some(existing_note)) =
-                            db_clone.notes.get_note(&active_id.to_string())
-                        {
-                            if let Err(e) = db_clone.notes.update_note(&Note {
-                                id: active_id,
-                                title: existing_note.title,
-                                content: content.clone(),
-                                created_at: existing_note.created_at,
-                            }) {
-                                eprintln!("Failed to update note content: {}", e);
-                            }
-                        }
-                    });
-                });
+    pub fn compact_database(&mut self, cx: &mut Context<Self>) {
+        let compacted = self.db.notes.database_size_bytes().and_then(|before| {
+            self.db.notes.vacuum()?;
+            Ok((before, self.db.notes.database_size_bytes()?))
+        });
 
-                self.dump_database();
+        if let Ok((_, after)) = compacted {
+            self.database_size = Some(after);
+        }
+        match compacted {
+            Ok((before, after)) => self.report_status(
+                format!(
+                    "Database compacted from {} to {}",
+                    format_size(before),
+                    format_size(after)
+                ),
+                cx,
+            ),
+            Err(e) => self.report_error(format!("Failed to compact database: {}", e), cx),
+        }
+    }
 
-                
-                let editor_focus = self.editor.read(cx).focus_handle.clone();
-                editor_focus.focus(window);
+    fn refresh_trash(&mut self, cx: &mut Context<Self>) {
+        match self.db.notes.count_trashed() {
+            Ok(count) => self.trashed_count = count,
+            Err(e) => self.report_error(format!("Failed to count trashed notes: {}", e), cx),
+        }
 
-                cx.notify();
-            }
-            Err(e) => {
-                eprintln!("Failed to save new note: {}", e);
+        if self.show_trash {
+            match self.db.notes.list_trashed() {
+                Ok(notes) => self.trashed_notes = notes,
+                Err(e) => self.report_error(format!("Failed to load trashed notes: {}", e), cx),
             }
+        } else {
+            self.trashed_notes.clear();
         }
     }
 
-    pub fn delete_note(&mut self, id: Uuid, cx: &mut Context<Self>) {
-        
-        if let Err(e) = self.db.notes.delete_note(&id.to_string()) {
-            eprintln!("Failed to delete note: {}", e);
+    pub fn toggle_trash(&mut self, cx: &mut Context<Self>) {
+        self.show_trash = !self.show_trash;
+        self.refresh_trash(cx);
+        cx.notify();
+    }
+
+    pub fn restore_note(&mut self, id: Uuid, cx: &mut Context<Self>) {
+        if let Err(e) = self.db.notes.restore_note(id) {
+            self.report_error(format!("Failed to restore note: {}", e), cx);
             return;
         }
 
-        
-        self.notes.retain(|note| note.id != id);
-
-        
-        if self.active_note_id == Some(id) {
-            self.active_note_id = self.notes.first().map(|note| note.id);
+        match Self::load_notes(&self.db) {
+            Ok(notes) => self.notes = notes,
+            Err(e) => self.report_error(format!("Failed to reload notes after restore: {}", e), cx),
+        }
+        self.refresh_trash(cx);
+        self.sync_status_item();
+        self.set_active_note(id, cx);
+    }
 
-            if let Some(new_active_id) = self.active_note_id {
-                self.set_active_note(new_active_id, cx);
-            } else {
-                
-                self.editor.update(cx, |editor, cx| {
-                    editor.set_content("", cx);
-                });
-                self.title_text = String::new();
-                self.title_editor.update(cx, |editor, cx| {
-                    editor.set_content("", cx);
-                });
-            }
+    pub fn delete_note_permanently(&mut self, id: Uuid, cx: &mut Context<Self>) {
+        if let Err(e) = self.db.notes.delete_note(id) {
+            self.report_error(format!("Failed to delete note: {}", e), cx);
+            return;
         }
 
+        self.refresh_trash(cx);
         cx.notify();
     }
 
-    pub fn set_active_note(&mut self, id: Uuid, cx: &mut Context<Self>) {
-        let db_clone = self.db.clone();
-        let active_id = id;
-
-        let fresh_note = self.db.notes.get_note(&id.to_string()).ok().flatten();
-
-        if let Some(note) = fresh_note.clone() {
-            for cached_note in &mut self.notes {
-                if cached_note.id == id {
-                    *cached_note = note.clone();
-                    break;
+    fn bind_editor_to_note(&self, note_id: Uuid, cx: &mut Context<Self>) {
+        let writer = self.writer.clone();
+        let passphrase = self.unlocked_passphrases.get(&note_id).cloned();
+        let app = cx.entity().downgrade();
+        self.editor.update(cx, move |editor, _cx| {
+            let binding = editor.binding.rebind(Some(note_id));
+            editor.set_on_change(move |current, content, cx| {
+                if !current.is_current(&binding) {
+                    return;
                 }
-            }
 
-            self.active_note_id = Some(id);
-            self.title_edit_mode = false;
-            self.title_text = note.title.clone();
+                writer.save_content(note_id, content, passphrase.clone());
 
-            let content = note.content.clone();
-            self.editor.update(cx, move |editor, cx| {
-                editor.set_content(content, cx);
+                // The preview is rendered by the app, so it has to redraw too.
+                let app = app.clone();
+                cx.defer(move |cx| {
+                    app.update(cx, |app, cx| {
+                        if app.settings.split_preview {
+                            cx.notify();
+                        }
+                    })
+                    .ok();
+                });
             });
+        });
 
-            self.editor.update(cx, move |editor, cx| {
-                editor.set_on_change(move |content, _cx| {
-                    if let Ok(Some(existing_note)) = db_clone.notes.get_note(&active_id.to_string())
-                    {
-                        if let Err(e) = db_clone.notes.update_note(&Note {
-                            id: active_id,
-                            title: existing_note.title,
-                            content: content.clone(),
-                            created_at: existing_note.created_at,
-                        }) {
-                            eprintln!("Failed to update note content: {}", e);
-                        }
+        let db = self.db.clone();
+        let app = cx.entity().downgrade();
+        self.editor.update(cx, move |editor, _cx| {
+            editor.set_on_paste_image(move |image, cx| {
+                match Self::store_image_attachment(&db, note_id, &image) {
+                    Ok(reference) => {
+                        let app = app.clone();
+                        cx.defer(move |cx| {
+                            app.update(cx, |app, cx| {
+                                app.load_note_attachments();
+                                cx.notify();
+                            })
+                            .ok();
+                        });
+                        Some(reference)
                     }
-                });
+                    Err(message) => {
+                        let app = app.clone();
+                        cx.defer(move |cx| {
+                            app.update(cx, |app, cx| app.report_error(message, cx)).ok();
+                        });
+                        None
+                    }
+                }
             });
-        } else {
-            let fallback_note = self.notes.iter().find(|n| n.id == id).cloned();
+        });
+    }
 
-            if let Some(note) = fallback_note {
-                self.active_note_id = Some(id);
-                self.title_edit_mode = false;
-                self.title_text = note.title.clone();
+    fn store_image_attachment(
+        db: &Database,
+        note_id: Uuid,
+        image: &Image,
+    ) -> Result<String, String> {
+        let (mime, extension) = match image.format {
+            ImageFormat::Png => ("image/png", "png"),
+            ImageFormat::Jpeg => ("image/jpeg", "jpg"),
+            ImageFormat::Gif => ("image/gif", "gif"),
+            ImageFormat::Webp => ("image/webp", "webp"),
+            ImageFormat::Svg => ("image/svg+xml", "svg"),
+            ImageFormat::Bmp => ("image/bmp", "bmp"),
+            ImageFormat::Tiff => ("image/tiff", "tiff"),
+        };
 
-                let content = note.content.clone();
-                self.editor.update(cx, move |editor, cx| {
-                    editor.set_content(content, cx);
-                });
+        let attachment_id = Uuid::new_v4();
+        let path = save_attachment_bytes(attachment_id, extension, &image.bytes)
+            .map_err(|e| format!("Failed to save pasted image: {}", e))?;
+        let path = path.to_string_lossy().to_string();
 
-                self.editor.update(cx, move |editor, cx| {
-                    editor.set_on_change(move |content, _cx| {
-                        if let Ok(Some(existing_note)) =
-                            db_clone.notes.get_note(&active_id.to_string())
-                        {
-                            if let Err(e) = db_clone.notes.update_note(&Note {
-                                id: active_id,
-                                title: existing_note.title,
-                                content: content.clone(),
-                                created_at: existing_note.created_at,
-                            }) {
-                                eprintln!("Failed to update note content: {}", e);
-                            }
-                        }
-                    });
-                });
+        let mut attachment = Attachment::new(note_id, mime.to_string(), path.clone());
+        attachment.id = attachment_id;
+        db.attachments
+            .create_attachment(&attachment)
+            .map_err(|e| format!("Failed to record pasted image: {}", e))?;
+
+        Ok(format!("![image]({})", link_destination(&path)))
+    }
+
+    fn adopt_title_from_content(&mut self, note_id: Uuid, content: &str, cx: &mut Context<Self>) {
+        if self.auto_title_note_id != Some(note_id) || self.title_edit_mode {
+            return;
+        }
+        let Some(note) = self.notes.iter().find(|note| note.id == note_id) else {
+            return;
+        };
+        let Some(title) = auto_title(note, content) else {
+            return;
+        };
+
+        let title = self.settings.limit_title(&title);
+        if title == self.title_text {
+            return;
+        }
+
+        self.writer.update(note_id, NoteChange::Title(title.clone()));
+        for note in &mut self.notes {
+            if note.id == note_id {
+                note.title = title.clone();
+                break;
             }
         }
+        self.sync_status_item();
 
+        self.title_text = title.clone();
+        self.title_editor.update(cx, |editor, cx| {
+            editor.set_content(title, cx);
+        });
         cx.notify();
     }
 
-    pub fn get_active_note(&self) -> Option<&Note> {
-        if let Some(id) = self.active_note_id {
-            self.notes.iter().find(|note| note.id == id)
-        } else {
-            None
-        }
+    pub fn report_error(&mut self, message: impl Into<String>, cx: &mut Context<Self>) {
+        let message = message.into();
+        eprintln!("{}", message);
+        self.error_message = Some(message);
+        cx.notify();
     }
 
-    pub fn toggle_title_edit_mode(&mut self, window: &mut Window, cx: &mut Context<Self>) {
-        let current_edit_mode = self.title_edit_mode;
-        let new_edit_mode = !current_edit_mode;
+    pub fn dismiss_error(&mut self, cx: &mut Context<Self>) {
+        self.error_message = None;
+        cx.notify();
+    }
 
-        let title_to_use: Option<String> = if let Some(active_note) = self.get_active_note() {
-            Some(active_note.title.clone())
-        } else {
-            None
+    pub fn report_status(&mut self, message: impl Into<String>, cx: &mut Context<Self>) {
+        let message = message.into();
+        self.status_message = Some(message);
+        cx.notify();
+    }
+
+    pub fn dismiss_status(&mut self, cx: &mut Context<Self>) {
+        self.status_message = None;
+        cx.notify();
+    }
+
+    fn watch_db_changes(
+        db: &Arc<Database>,
+        db_path: &std::path::Path,
+        cx: &mut Context<Self>,
+    ) -> Option<notify::RecommendedWatcher> {
+        let (sender, mut receiver) = mpsc::unbounded();
+        // Other connections append to the write-ahead log, so changes show up
+        // there before they're checkpointed into the database file.
+        let mut wal_path = db_path.as_os_str().to_owned();
+        wal_path.push("-wal");
+        let paths = [db_path.to_path_buf(), std::path::PathBuf::from(wal_path)];
+        let watcher = match watch_files(&paths, sender) {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                eprintln!("Failed to watch database for external changes: {}", e);
+                return None;
+            }
         };
 
-        if let Some(title_ref) = &title_to_use {
-            self.title_edit_mode = new_edit_mode;
+        // The app's own saves touch the same files; they don't move the
+        // connection's data version, so only other processes trigger a reload.
+        let db = Arc::clone(db);
+        cx.spawn(async move |this, cx| {
+            let mut seen_version = db.data_version().ok();
+            while receiver.next().await.is_some() {
+                while receiver.try_recv().is_ok() {}
+                let db = Arc::clone(&db);
+                let version = cx
+                    .background_executor()
+                    .spawn(async move { db.data_version().ok() })
+                    .await;
+                if version.is_some() && version == seen_version {
+                    continue;
+                }
+                seen_version = version;
 
-            if new_edit_mode {
-                self.title_text = title_ref.clone();
-                self.title_editor.update(cx, |editor, cx| {
-                    editor.set_content(title_ref.clone(), cx);
-                });
-            } else {
-                let title_content = self.title_editor.read(cx).content.to_string();
-
-                if title_content.trim().is_empty() {
-                    self.title_text = title_ref.clone();
-                    self.title_editor.update(cx, |editor, cx| {
-                        editor.set_content(title_ref.clone(), cx);
-                    });
-                    if let Some(active_id) = self.active_note_id {
-                        for note in &mut self.notes {
-                            if note.id == active_id {
-                                note.title = title_ref.clone();
-                                break;
-                            }
-                        }
-                    }
-                } else {
-                    self.title_text = title_content;
-                    self.save_title(cx);
+                if this
+                    .update(cx, |app, cx| app.reload_notes_from_disk(cx))
+                    .is_err()
+                {
+                    break;
                 }
             }
+        })
+        .detach();
 
-            cx.notify();
+        Some(watcher)
+    }
 
-            let main_editor_focus = self.editor.read(cx).focus_handle.clone();
-            main_editor_focus.focus(window);
-        }
+    fn watch_write_outcomes(
+        mut receiver: UnboundedReceiver<WriteOutcome>,
+        cx: &mut Context<Self>,
+    ) {
+        cx.spawn(async move |this, cx| {
+            while let Some(outcome) = receiver.next().await {
+                if this
+                    .update(cx, |app, cx| app.handle_write_outcome(outcome, cx))
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        })
+        .detach();
     }
 
-    pub fn save_title(&mut self, cx: &mut Context<Self>) {
-        if self.title_text.trim().is_empty() {
-            if let Some(note_id) = self.active_note_id {
-                if let Ok(Some(existing_note)) = self.db.notes.get_note(&note_id.to_string()) {
-                    let default_title = "Untitled Note".to_string();
-
-                    
-                    let final_title = if existing_note.title.trim().is_empty() {
-                        println!("Existing title in database is empty, using default title");
-                        default_title
+    fn handle_write_outcome(&mut self, outcome: WriteOutcome, cx: &mut Context<Self>) {
+        match outcome {
+            WriteOutcome::Saved {
+                note_id,
+                content,
+                written,
+            } => {
+                self.adopt_title_from_content(note_id, &content, cx);
+                if written {
+                    self.flash_saved(cx);
+                }
+            }
+            WriteOutcome::EncryptionChanged { note_id, encrypted } => {
+                if let Some(note) = self.notes.iter_mut().find(|note| note.id == note_id) {
+                    note.encrypted = encrypted;
+                }
+                if !encrypted {
+                    self.unlocked_passphrases.remove(&note_id);
+                }
+                self.report_status(
+                    if encrypted {
+                        "Note encrypted"
                     } else {
-                        existing_note.title.clone()
-                    };
-
-                    self.title_text = final_title.clone();
-                    self.title_editor.update(cx, |editor, cx| {
-                        editor.set_content(final_title.clone(), cx);
-                    });
-
-                    
-                    if existing_note.title.trim().is_empty() {
-                        if let Err(e) = self.db.notes.update_note(&Note {
-                            id: note_id,
-                            title: final_title.clone(),
-                            content: existing_note.content.clone(),
-                            created_at: existing_note.created_at,
-                        }) {
-                            eprintln!("Failed to update note title: {}", e);
-                        } else {
-                            
-                            for note in &mut self.notes {
-                                if note.id == note_id {
-                                    note.title = final_title;
-                                    break;
-                                }
-                            }
-                        }
+                        "Note encryption removed"
+                    },
+                    cx,
+                );
+            }
+            WriteOutcome::Missing { note_id, content } => {
+                if let Some(mut note) = self.notes.iter().find(|note| note.id == note_id).cloned() {
+                    if !note.encrypted {
+                        note.content = content;
                     }
-
-                    cx.notify();
+                    self.mark_note_missing(note, cx);
                 }
             }
-            return;
-        }
-
-        if let Some(note_id) = self.active_note_id {
-            if let Ok(Some(existing_note)) = self.db.notes.get_note(&note_id.to_string()) {
-                if existing_note.title != self.title_text {
-                    if let Err(e) = self.db.notes.update_note(&Note {
-                        id: note_id,
-                        title: self.title_text.clone(),
-                        content: existing_note.content.clone(),
-                        created_at: existing_note.created_at,
-                    }) {
-                        eprintln!("Failed to update note title: {}", e);
-                    } else {
-                        for note in &mut self.notes {
-                            if note.id == note_id {
-                                note.title = self.title_text.clone();
-                                break;
-                            }
-                        }
+            WriteOutcome::Updated { change, .. } => match change {
+                NoteChange::MoveToTop | NoteChange::MoveToBottom => {
+                    match Self::load_notes(&self.db) {
+                        Ok(notes) => self.notes = notes,
+                        Err(e) => self.report_error(
+                            format!("Failed to reload notes after move: {}", e),
+                            cx,
+                        ),
                     }
+                    self.sync_status_item();
+                    cx.notify();
+                }
+                NoteChange::Trash => {
+                    self.refresh_trash(cx);
+                    cx.notify();
                 }
+                _ => {}
+            },
+            WriteOutcome::UpdateFailed { note_id, message } => {
+                eprintln!("Update of note {} failed", note_id);
+                self.report_error(message, cx);
+                // The change was already shown; go back to what was stored.
+                self.reload_notes_from_disk(cx);
+                self.refresh_trash(cx);
+                let read_only = self
+                    .active_note_id
+                    .and_then(|id| self.notes.iter().find(|note| note.id == id))
+                    .is_some_and(|note| note.locked);
+                self.editor.update(cx, |editor, _cx| editor.read_only = read_only);
+            }
+            WriteOutcome::Failed { note_id, message } => {
+                eprintln!("Write for note {} failed", note_id);
+                self.report_error(message, cx);
             }
         }
     }
 
-    pub fn handle_title_key_down(
-        &mut self,
-        event: &KeyDownEvent,
-        window: &mut Window,
-        cx: &mut Context<Self>,
-    ) {
-        if event.keystroke.key == "enter" {
-            let title_content = self.title_editor.read(cx).content.to_string();
-            if title_content.trim().is_empty() {
-                if let Some(active_note) = self.get_active_note() {
-                    self.title_editor.update(cx, |editor, cx| {
-                        editor.set_content(active_note.title.clone(), cx);
-                    });
-                    self.on_title_blur(window, cx);
-                }
-            } else {
-                self.toggle_title_edit_mode(window, cx);
-            }
-        } else if event.keystroke.key == "escape" {
-            self.on_title_blur(window, cx);
-        }
+    /// Keeps the window open until queued note writes have reached the
+    /// database. Writes that don't finish in time are left to the user:
+    /// keep waiting, close anyway, or cancel.
+    pub fn guard_window_close(&self, window: &mut Window, cx: &mut Context<Self>) {
+        let app = cx.entity().downgrade();
+        window.on_window_should_close(cx, move |window, cx| {
+            app.update(cx, |app, cx| app.flush_before_close(window, cx))
+                .unwrap_or(true)
+        });
     }
 
-    
-    
-    pub fn on_title_blur(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+    /// Lets the window close at once when nothing is queued. Otherwise the
+    /// close is held while the writer catches up off the UI thread, then
+    /// finished or turned into a prompt.
+    fn flush_before_close(&mut self, window: &mut Window, cx: &mut Context<Self>) -> bool {
         if self.title_edit_mode {
-            println!("Title edit mode is active, handling blur event");
-            let title_content;
-            let should_revert;
-            let original_title = self.get_active_note().map(|note| note.title.clone());
+            self.save_title(cx);
+        }
+        if !self.writer.has_pending_writes() {
+            return true;
+        }
 
-            {
-                title_content = self.title_editor.read(cx).content.to_string();
-                should_revert = title_content.trim().is_empty();
-                println!(
-                    "Title content: '{}', should revert: {}",
-                    title_content, should_revert
-                );
-            }
+        let writer = self.writer.clone();
+        cx.spawn_in(window, async move |this, cx| {
+            let queued = writer.clone();
+            let flushed = cx
+                .background_executor()
+                .spawn(async move { queued.flush(CLOSE_FLUSH_TIMEOUT) })
+                .await;
+            let answer = if flushed {
+                None
+            } else {
+                let Ok(answer) = this.update_in(cx, |app, window, cx| {
+                    app.settings.confirm_close_with_unsaved_edits.then(|| {
+                        window.prompt(
+                            PromptLevel::Warning,
+                            "Some edits haven't been saved yet",
+                            Some("Tap is still writing your latest changes to disk."),
+                            &["Save and Close", "Close Anyway", "Cancel"],
+                            cx,
+                        )
+                    })
+                }) else {
+                    return;
+                };
+                if answer.is_none() {
+                    eprintln!("Closing with note writes still queued");
+                }
+                answer
+            };
 
-            if should_revert {
-                if let Some(title) = original_title {
-                    let default_title = "Untitled Note".to_string();
-                    let final_title = if title.trim().is_empty() {
-                        println!("Original title is empty, using default title");
-                        default_title
-                    } else {
-                        println!("Reverting to original title: '{}'", title);
-                        title.clone()
-                    };
+            let close = match answer {
+                None => true,
+                Some(answer) => match answer.await {
+                    Ok(0) => {
+                        let flushed = cx
+                            .background_executor()
+                            .spawn(async move { writer.flush(SAVE_AND_CLOSE_TIMEOUT) })
+                            .await;
+                        if !flushed {
+                            this.update(cx, |app, cx| {
+                                app.report_error("Edits are still being saved, try again", cx)
+                            })
+                            .ok();
+                        }
+                        flushed
+                    }
+                    Ok(1) => true,
+                    _ => false,
+                },
+            };
+            if close {
+                cx.update(|window, _cx| window.remove_window()).ok();
+            }
+        })
+        .detach();
+        false
+    }
 
-                    self.title_text = final_title.clone();
-                    self.title_editor.update(cx, |editor, cx| {
-                        editor.set_content(final_title.clone(), cx);
-                    });
+    fn flash_saved(&mut self, cx: &mut Context<Self>) {
+        self.saved_flash_count += 1;
+        let flash = self.saved_flash_count;
+        self.saved_flash = Some(flash);
+        cx.spawn(async move |this, cx| {
+            cx.background_executor().timer(SAVED_FLASH_DURATION).await;
+            this.update(cx, |app, cx| {
+                if app.saved_flash == Some(flash) {
+                    app.saved_flash = None;
+                    cx.notify();
+                }
+            })
+            .ok();
+        })
+        .detach();
+        cx.notify();
+    }
 
-                    if let Some(active_id) = self.active_note_id {
-                        for note in &mut self.notes {
-                            if note.id == active_id {
-                                note.title = final_title.clone();
-                                println!("Updated note title in memory for note ID: {}", active_id);
-                                break;
-                            }
-                        }
+    fn listen_for_app_requests(cx: &mut Context<Self>) {
+        let Some(mut requests) = take_app_requests() else {
+            eprintln!("App requests already have a listener");
+            return;
+        };
 
-                        
-                        if title.trim().is_empty() {
-                            if let Ok(Some(existing_note)) =
-                                self.db.notes.get_note(&active_id.to_string())
-                            {
-                                if let Err(e) = self.db.notes.update_note(&Note {
-                                    id: active_id,
-                                    title: final_title,
-                                    content: existing_note.content.clone(),
-                                    created_at: existing_note.created_at,
-                                }) {
-                                    eprintln!("Failed to update note title: {}", e);
-                                }
-                            }
-                        }
-                    }
+        cx.spawn(async move |this, cx| {
+            while let Some(request) = requests.next().await {
+                if this
+                    .update(cx, |app, cx| app.handle_app_request(request, cx))
+                    .is_err()
+                {
+                    break;
                 }
-            } else {
-                println!("Saving new title: '{}'", title_content);
-                self.title_text = title_content;
-                self.save_title(cx);
             }
+        })
+        .detach();
+    }
 
-            self.title_edit_mode = false;
-            println!("Exiting title edit mode");
-            cx.notify();
+    fn handle_app_request(&mut self, request: AppRequest, cx: &mut Context<Self>) {
+        match request {
+            AppRequest::Menu(action) => self.handle_menu_action(action, cx),
+            AppRequest::QuickCapture => self.capture_new_note(cx),
+            AppRequest::ReduceMotionChanged => self.set_reduce_motion(reduce_motion_enabled(), cx),
+            AppRequest::OpenUrl(url) => self.open_url(&url, cx),
+        }
+    }
 
-            let main_editor_focus = self.editor.read(cx).focus_handle.clone();
-            println!("Focusing main editor");
-            main_editor_focus.focus(window);
+    fn handle_menu_action(&mut self, action: MenuAction, cx: &mut Context<Self>) {
+        match action {
+            MenuAction::Delete(id) => self.delete_note(id, cx),
+            MenuAction::MoveToTop(id) => self.move_note_to_edge(id, true),
+            MenuAction::MoveToBottom(id) => self.move_note_to_edge(id, false),
+            MenuAction::CopyNote(id) => self.copy_note_to_clipboard(id, true, cx),
+            MenuAction::CopyContent(id) => self.copy_note_to_clipboard(id, false, cx),
+            MenuAction::ToggleLocked(id) => self.toggle_note_locked(id, cx),
+            MenuAction::ToggleStarred(id) => self.toggle_note_starred(id, cx),
+            MenuAction::ToggleArchived(id) => self.toggle_note_archived(id, cx),
+            MenuAction::CopyLink(id) => self.copy_note_link(id, cx),
+            MenuAction::OpenNote(id) => self.open_note_from_link(id, cx),
+            MenuAction::NewNote => self.capture_new_note(cx),
         }
     }
-}
 
-impl Focusable for NoteApp {
-    fn focus_handle(&self, cx: &App) -> FocusHandle {
-        self.content_focus_handle.clone()
+    /// Rebuilds the menu bar item's menu when the starred notes or their
+    /// titles have changed since it was last built. Called wherever stars,
+    /// titles or the note list change.
+    fn sync_status_item(&mut self) {
+        let notes = status_item_notes(&self.notes);
+        if self.status_item_notes.as_ref() != Some(&notes) {
+            update_status_item(&notes);
+            self.status_item_notes = Some(notes);
+        }
     }
-}
 
-impl Render for NoteApp {
-    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
-        
-        println!("Render called, checking for notes to delete");
-        match NOTE_TO_DELETE.lock() {
-            Ok(mut note_to_delete) => {
-                println!("Successfully locked NOTE_TO_DELETE mutex in render");
-                if let Some(id) = note_to_delete.take() {
-                    println!("Found note to delete with ID: {}", id);
-                    self.delete_note(id, cx);
-                    println!("Deletion completed for note: {}", id);
-                } else {
-                    println!("No notes queued for deletion");
-                }
-            },
-            Err(e) => {
-                println!("Failed to lock NOTE_TO_DELETE mutex in render: {:?}", e);
+    pub fn toggle_note_locked(&mut self, id: Uuid, cx: &mut Context<Self>) {
+        let Some(note) = self.notes.iter_mut().find(|note| note.id == id) else {
+            return;
+        };
+        let locked = !note.locked;
+        note.locked = locked;
+        self.writer.update(id, NoteChange::Locked(locked));
+
+        if self.active_note_id == Some(id) {
+            if locked {
+                self.title_edit_mode = false;
             }
+            self.editor.update(cx, |editor, _cx| editor.read_only = locked);
         }
-    
-        div()
-            .flex()
-            .bg(rgb(0xf5f5f5))
-            .size_full()
-            .on_mouse_down(
-                MouseButton::Left,
-                cx.listener(|view, event, window, cx| {
-                    if view.title_edit_mode {
-                        let title_focused =
-                            view.title_editor.read(cx).focus_handle.is_focused(window);
-                        if !title_focused {
-                            view.on_title_blur(window, cx);
-                        }
-                    }
-                }),
-            )
-            .child(self.render_sidebar(cx))
-            .child(self.render_content(cx))
+        self.report_status(if locked { "Note locked" } else { "Note unlocked" }, cx);
+    }
+
+    pub fn toggle_note_starred(&mut self, id: Uuid, cx: &mut Context<Self>) {
+        let Some(note) = self.notes.iter_mut().find(|note| note.id == id) else {
+            return;
+        };
+        let starred = !note.starred;
+        note.starred = starred;
+        self.writer.update(id, NoteChange::Starred(starred));
+        self.sync_status_item();
+
+        if let Some(favorite_ids) = &mut self.favorite_ids {
+            if starred {
+                favorite_ids.insert(id);
+            } else {
+                favorite_ids.remove(&id);
+            }
+        }
+        cx.notify();
+    }
+
+    fn refresh_favorites(&mut self) {
+        match self.db.notes.list_starred(SIDEBAR_ORDER) {
+            Ok(notes) => {
+                self.favorite_ids = Some(notes.into_iter().map(|note| note.id).collect())
+            }
+            Err(e) => eprintln!("Failed to load favorite notes: {}", e),
+        }
+    }
+
+    fn is_listed(&self, note: &Note) -> bool {
+        note.archived == self.show_archived
+            && self
+                .favorite_ids
+                .as_ref()
+                .is_none_or(|ids| ids.contains(&note.id))
+    }
+
+    /// When this takes the active note out of the current view, a neighbor is
+    /// selected as after deleting it.
+    pub fn toggle_note_archived(&mut self, id: Uuid, cx: &mut Context<Self>) {
+        let visible = self.visible_note_ids();
+        let Some(note) = self.notes.iter_mut().find(|note| note.id == id) else {
+            return;
+        };
+        let archived = !note.archived;
+        note.archived = archived;
+        self.writer.update(id, NoteChange::Archived(archived));
+        self.sync_status_item();
+        self.selected_ids.remove(&id);
+
+        if self.active_note_id == Some(id) {
+            if self.open_note_ids.contains(&id) {
+                self.close_tab(id, cx);
+            }
+            if self.active_note_id.is_none() || self.active_note_id == Some(id) {
+                match active_after_removal(&visible, None, id) {
+                    Some(neighbor_id) => self.set_active_note(neighbor_id, cx),
+                    None => self.clear_active_note(cx),
+                }
+            }
+        }
+        self.report_status(if archived { "Note archived" } else { "Note unarchived" }, cx);
+    }
+
+    pub fn toggle_archived_view(&mut self, cx: &mut Context<Self>) {
+        self.show_archived = !self.show_archived;
+        cx.notify();
+    }
+
+    pub fn toggle_favorites(&mut self, cx: &mut Context<Self>) {
+        if self.favorite_ids.take().is_none() {
+            self.refresh_favorites();
+        }
+        cx.notify();
+    }
+
+    /// The active note is copied from the editor so unsaved edits are included.
+    pub fn copy_note_to_clipboard(&mut self, id: Uuid, with_title: bool, cx: &mut Context<Self>) {
+        let Some(note) = self.notes.iter().find(|note| note.id == id).cloned() else {
+            return;
+        };
+        if self.is_note_locked(id) {
+            self.report_status("Unlock the note to copy it", cx);
+            return;
+        }
+
+        let content = if self.active_note_id == Some(id) {
+            self.editor.read(cx).content.to_string()
+        } else {
+            match self.readable_content(&note) {
+                Some(content) => content,
+                None => return,
+            }
+        };
+
+        let text = if with_title {
+            note_as_markdown(&note.title, &content)
+        } else {
+            content
+        };
+        cx.write_to_clipboard(ClipboardItem::new_string(text));
+        self.report_status(if with_title { "Note copied" } else { "Content copied" }, cx);
+    }
+
+    /// The link is plain text, so locked notes can be linked too.
+    pub fn copy_note_link(&mut self, id: Uuid, cx: &mut Context<Self>) {
+        cx.write_to_clipboard(ClipboardItem::new_string(note_url(id)));
+        self.report_status("Link copied", cx);
+    }
+
+    pub fn move_note_to_edge(&mut self, id: Uuid, to_top: bool) {
+        let change = if to_top {
+            NoteChange::MoveToTop
+        } else {
+            NoteChange::MoveToBottom
+        };
+        // The list is reloaded in the new order once the move is stored.
+        self.writer.update(id, change);
+    }
+
+    fn capture_new_note(&mut self, cx: &mut Context<Self>) {
+        cx.activate(true);
+        if self.create_placeholder_note("", cx) {
+            self.pending_editor_focus = true;
+            cx.notify();
+        }
+    }
+
+    fn set_reduce_motion(&mut self, reduce_motion: bool, cx: &mut Context<Self>) {
+        self.reduce_motion = reduce_motion;
+        self.editor
+            .update(cx, |editor, cx| editor.set_reduce_motion(reduce_motion, cx));
+        for title_editor in [
+            &self.title_editor,
+            &self.replace_find_editor,
+            &self.replace_with_editor,
+            &self.search_editor,
+            &self.passphrase_editor,
+        ] {
+            title_editor.update(cx, |editor, cx| editor.set_reduce_motion(reduce_motion, cx));
+        }
+        cx.notify();
+    }
+
+    fn open_url(&mut self, url: &str, cx: &mut Context<Self>) {
+        match note_id_from_url(url) {
+            Ok(id) => {
+                self.open_note_from_link(id, cx);
+                if let Some(line) = line_from_url(url) {
+                    self.jump_to_line(id, line, cx);
+                }
+            }
+            Err(message) => self.report_error(message, cx),
+        }
+    }
+
+    /// Moves the caret to the start of one-based `line` if `id` is the note
+    /// being edited.
+    fn jump_to_line(&mut self, id: Uuid, line: usize, cx: &mut Context<Self>) {
+        if self.active_note_id != Some(id) {
+            return;
+        }
+        self.editor.update(cx, |editor, cx| {
+            editor.set_cursor_line_column(line.saturating_sub(1), 0, cx)
+        });
+    }
+
+    pub fn open_note_from_link(&mut self, id: Uuid, cx: &mut Context<Self>) {
+        cx.activate(true);
+
+        if self.notes.iter().any(|note| note.id == id) {
+            self.selected_ids.clear();
+            self.set_active_note(id, cx);
+        } else if self.trashed_notes.iter().any(|note| note.id == id) {
+            self.report_error(format!("Note {} is in the Trash", id), cx);
+        } else {
+            self.report_error(format!("No note found with id {}", id), cx);
+        }
+    }
+
+    pub fn reload_notes_from_disk(&mut self, cx: &mut Context<Self>) {
+        let notes = match Self::load_notes(&self.db) {
+            Ok(notes) => notes,
+            Err(e) => {
+                eprintln!("Failed to reload notes after external change: {}", e);
+                return;
+            }
+        };
+
+        let previous_notes = std::mem::replace(&mut self.notes, notes);
+        self.sync_status_item();
+
+        if let Some(active_id) = self.active_note_id {
+            let still_listed = self.notes.iter().any(|note| note.id == active_id);
+            if !still_listed && matches!(self.db.notes.note_exists(active_id), Ok(false)) {
+                if let Some(position) = previous_notes.iter().position(|note| note.id == active_id)
+                {
+                    let ghost = previous_notes[position].clone();
+                    self.notes.insert(position.min(self.notes.len()), ghost.clone());
+                    self.mark_note_missing(ghost, cx);
+                }
+            }
+        }
+
+        let open_count = self.open_note_ids.len();
+        let notes = &self.notes;
+        self.open_note_ids
+            .retain(|id| notes.iter().any(|note| note.id == *id));
+        if self.open_note_ids.len() != open_count {
+            self.save_session(cx);
+        }
+
+        let Some(active_id) = self.active_note_id else {
+            if let Some(first_id) = self.notes.first().map(|note| note.id) {
+                self.set_active_note(first_id, cx);
+            }
+            cx.notify();
+            return;
+        };
+
+        let Some(disk_note) = self.notes.iter().find(|note| note.id == active_id).cloned() else {
+            self.active_note_id = self.notes.first().map(|note| note.id);
+            if let Some(new_active_id) = self.active_note_id {
+                self.set_active_note(new_active_id, cx);
+            }
+            cx.notify();
+            return;
+        };
+
+        if self.title_edit_mode {
+            for note in &mut self.notes {
+                if note.id == active_id {
+                    note.title = self.title_text.clone();
+                    break;
+                }
+            }
+        } else {
+            self.title_text = disk_note.title.clone();
+        }
+
+        let editor = self.editor.read(cx);
+        let editor_content = editor.content.to_string();
+        // Reloading now would throw away queued writes or uncommitted IME text.
+        let local_edits =
+            self.writer.has_pending_writes() || is_composing(editor.marked_range.as_ref());
+        let disk_content = if local_edits {
+            None
+        } else {
+            self.readable_content(&disk_note)
+        };
+        if let Some(disk_content) = disk_content {
+            // The stored copy may only differ by what the save transforms
+            // rewrote, which the editor deliberately doesn't show yet.
+            let saved_form = self
+                .save_transforms
+                .apply(&editor_content, &self.settings.save_transforms);
+            if disk_content != editor_content && disk_content != saved_form {
+                self.editor.update(cx, |editor, cx| {
+                    editor.set_content_preserving_selection(disk_content, cx);
+                });
+            }
+        }
+
+        cx.notify();
+    }
+
+    pub fn dump_database(&self) {
+        println!("Dumping database contents:");
+        match dump_db_contents() {
+            Ok(_) => println!("Database dump completed"),
+            Err(e) => println!("Failed to dump database: {}", e),
+        }
+    }
+
+    pub fn add_note(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if self.create_placeholder_note("", cx) {
+            self.focus_editor(window, cx);
+        }
+    }
+
+    pub fn click_new_note_button(
+        &mut self,
+        option_held: bool,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let has_templates = !self.settings.note_templates.is_empty();
+        if has_templates && (self.settings.choose_template_for_new_notes || option_held) {
+            // Clicking anywhere outside the menu closes it again.
+            self.show_template_menu = true;
+            cx.notify();
+        } else {
+            self.add_note(window, cx);
+        }
+    }
+
+    pub fn add_note_from_template(
+        &mut self,
+        index: Option<usize>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.show_template_menu = false;
+        let content = index
+            .and_then(|index| self.settings.note_templates.get(index))
+            .map(|template| template.content.clone())
+            .unwrap_or_default();
+        if self.create_placeholder_note(&content, cx) {
+            self.focus_editor(window, cx);
+        }
+    }
+
+    pub fn set_choose_template_for_new_notes(&mut self, enabled: bool, cx: &mut Context<Self>) {
+        self.settings.choose_template_for_new_notes = enabled;
+        self.save_settings(cx);
+        cx.notify();
+    }
+
+    /// The previous note is only discarded as an empty placeholder once the new
+    /// one is saved, so a failed save leaves it open as it was.
+    fn create_placeholder_note(&mut self, content: &str, cx: &mut Context<Self>) -> bool {
+        let replaced = self
+            .active_note_id
+            .filter(|previous_id| self.is_empty_placeholder(*previous_id, cx));
+
+        let existing = self.notes.len() - usize::from(replaced.is_some());
+        let title = format!("Untitled {}", existing + 1);
+        let Some(new_note) = self.insert_new_note(title, content, cx) else {
+            return false;
+        };
+
+        if let Some(previous_id) = replaced {
+            self.discard_if_empty_placeholder(previous_id, cx);
+        }
+        self.open_new_note(new_note, true, cx);
+        true
+    }
+
+    fn focus_editor(&self, window: &mut Window, cx: &mut Context<Self>) {
+        let editor_focus = self.editor.read(cx).focus_handle.clone();
+        editor_focus.focus(window);
+    }
+
+    /// With `auto_title` the title is a placeholder that may be replaced from
+    /// the note's first line.
+    fn create_note_titled(
+        &mut self,
+        title: String,
+        auto_title: bool,
+        cx: &mut Context<Self>,
+    ) -> bool {
+        match self.insert_new_note(title, "", cx) {
+            Some(new_note) => {
+                self.open_new_note(new_note, auto_title, cx);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Saves a new note named `title` and holding `content` without
+    /// touching anything on screen. Reports the error and returns `None`
+    /// when the save fails.
+    fn insert_new_note(
+        &mut self,
+        title: String,
+        content: &str,
+        cx: &mut Context<Self>,
+    ) -> Option<Note> {
+        let new_note = Note::with_content(self.settings.limit_title(&title), content);
+        println!("Adding new note with ID: {}", new_note.id);
+
+        let placement = self.settings.new_note_placement;
+        match save_new_note(&self.db.notes, &self.notes, placement, new_note) {
+            Ok(new_note) => {
+                println!(
+                    "Successfully saved new note to database with ID: {}",
+                    new_note.id
+                );
+                Some(new_note)
+            }
+            Err(e) => {
+                self.report_error(format!("Failed to save new note: {}", e), cx);
+                None
+            }
+        }
+    }
+
+    fn open_new_note(&mut self, new_note: Note, auto_title: bool, cx: &mut Context<Self>) {
+        let new_id = new_note.id;
+        let content = new_note.content.clone();
+        self.title_text = new_note.title.clone();
+        list_new_note(&mut self.notes, &mut self.active_note_id, SIDEBAR_ORDER, new_note);
+        self.auto_title_note_id =
+            (auto_title && self.settings.auto_title_from_content).then_some(new_id);
+
+        self.editor.update(cx, |editor, cx| {
+            editor.set_content(content, cx);
+        });
+        self.title_editor.update(cx, |editor, cx| {
+            editor.set_content(self.title_text.clone(), cx);
+        });
+
+        self.bind_editor_to_note(new_id, cx);
+        self.open_tab(new_id, cx);
+        self.reveal_active_note();
+
+        cx.notify();
+    }
+
+    pub fn delete_note(&mut self, id: Uuid, cx: &mut Context<Self>) {
+        self.writer.update(id, NoteChange::Trash);
+
+        let visible_before = self.visible_note_ids();
+        self.notes.retain(|note| note.id != id);
+        self.selected_ids.remove(&id);
+        self.sync_status_item();
+
+        if self.open_note_ids.contains(&id) {
+            self.close_tab(id, cx);
+        }
+        self.reselect_after_removal(&visible_before, id, cx);
+
+        cx.notify();
+    }
+
+    /// After `removed` drops out of the sidebar, whose order before that is
+    /// `visible_before`, opens its neighbour if it was active, or the first
+    /// note if nothing valid is active; clears the editor once none are left.
+    fn reselect_after_removal(
+        &mut self,
+        visible_before: &[Uuid],
+        removed: Uuid,
+        cx: &mut Context<Self>,
+    ) {
+        let active = self
+            .active_note_id
+            .filter(|&active| self.notes.iter().any(|note| note.id == active));
+        let next = active_after_removal(visible_before, active, removed)
+            .or_else(|| self.notes.first().map(|note| note.id));
+
+        match next {
+            Some(next) if self.active_note_id != Some(next) => self.set_active_note(next, cx),
+            Some(_) => {}
+            None if self.active_note_id.is_some() => self.clear_active_note(cx),
+            None => {}
+        }
+    }
+
+    pub fn review_duplicate_notes(&mut self, cx: &mut Context<Self>) {
+        match self.db.notes.find_duplicates() {
+            Ok(groups) => {
+                self.show_settings = false;
+                self.duplicate_groups = Some(groups);
+            }
+            Err(e) => self.report_error(format!("Failed to look for duplicates: {}", e), cx),
+        }
+        cx.notify();
+    }
+
+    /// Moves every note in the group but the oldest to the trash.
+    pub fn delete_duplicate_extras(&mut self, group_index: usize, cx: &mut Context<Self>) {
+        let Some(groups) = self.duplicate_groups.as_mut() else {
+            return;
+        };
+        if group_index >= groups.len() {
+            return;
+        }
+
+        let group = groups.remove(group_index);
+        for &id in &group[1..] {
+            self.delete_note(id, cx);
+        }
+        self.report_status(format!("Moved {} duplicate(s) to the trash", group.len() - 1), cx);
+    }
+
+    fn close_duplicate_review(&mut self, cx: &mut Context<Self>) {
+        self.duplicate_groups = None;
+        cx.notify();
+    }
+
+    /// Whether `id` still has its placeholder title and no content, and the
+    /// settings allow such notes to be discarded. Encrypted notes and the
+    /// note shown as missing from disk never count. For the open note the
+    /// editor's text is checked, since it may not have been saved yet.
+    fn is_empty_placeholder(&self, id: Uuid, cx: &App) -> bool {
+        if !self.settings.discard_empty_untitled_notes
+            || self.missing_note.as_ref().is_some_and(|missing| missing.id == id)
+        {
+            return false;
+        }
+        let Some(note) = self.notes.iter().find(|note| note.id == id) else {
+            return false;
+        };
+        if note.encrypted || !self.settings.is_placeholder_title(&note.title) {
+            return false;
+        }
+
+        if self.active_note_id == Some(id) {
+            self.editor.read(cx).content.trim().is_empty()
+        } else {
+            note.content.trim().is_empty()
+        }
+    }
+
+    fn discard_if_empty_placeholder(&mut self, id: Uuid, cx: &mut Context<Self>) {
+        if !self.is_empty_placeholder(id, cx) {
+            return;
+        }
+
+        if let Err(e) = self.db.notes.delete_note(id) {
+            eprintln!("Failed to discard empty note {}: {}", id, e);
+            return;
+        }
+
+        self.notes.retain(|note| note.id != id);
+        self.selected_ids.remove(&id);
+        if self.open_note_ids.contains(&id) {
+            self.open_note_ids.retain(|open_id| *open_id != id);
+            self.save_session(cx);
+        }
+    }
+
+    fn mark_note_missing(&mut self, note: Note, cx: &mut Context<Self>) {
+        if self.missing_note.as_ref().map(|missing| missing.id) != Some(note.id) {
+            eprintln!("Note {} no longer exists on disk", note.id);
+        }
+        self.missing_note = Some(note);
+        cx.notify();
+    }
+
+    pub fn recreate_missing_note(&mut self, cx: &mut Context<Self>) {
+        let Some(mut note) = self.missing_note.take() else {
+            return;
+        };
+
+        if self.active_note_id == Some(note.id) && !self.is_note_locked(note.id) {
+            let content = self.editor.read(cx).content.to_string();
+            note.content = match self.unlocked_passphrases.get(&note.id) {
+                Some(passphrase) if note.encrypted => match encrypt_content(passphrase, &content) {
+                    Ok(stored) => stored,
+                    Err(e) => {
+                        self.missing_note = Some(note);
+                        self.report_error(format!("Failed to encrypt note: {}", e), cx);
+                        return;
+                    }
+                },
+                _ => content,
+            };
+        }
+        note.updated_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+
+        match self.db.notes.create_note(&note) {
+            Ok(()) => {
+                if let Some(cached) = self.notes.iter_mut().find(|cached| cached.id == note.id) {
+                    *cached = note;
+                }
+                self.report_status("Note recreated", cx);
+            }
+            Err(e) => {
+                self.missing_note = Some(note);
+                self.report_error(format!("Failed to recreate note: {}", e), cx);
+            }
+        }
+    }
+
+    pub fn discard_missing_note(&mut self, cx: &mut Context<Self>) {
+        let Some(note) = self.missing_note.take() else {
+            return;
+        };
+
+        self.notes.retain(|cached| cached.id != note.id);
+        self.selected_ids.remove(&note.id);
+        self.sync_status_item();
+        self.close_tab(note.id, cx);
+
+        if self.active_note_id.is_none() {
+            if let Some(first_id) = self.notes.first().map(|cached| cached.id) {
+                self.set_active_note(first_id, cx);
+            }
+        }
+        cx.notify();
+    }
+
+    fn open_tab(&mut self, id: Uuid, cx: &mut Context<Self>) {
+        if !self.open_note_ids.contains(&id) {
+            self.open_note_ids.push(id);
+        }
+        self.save_session(cx);
+    }
+
+    pub fn close_tab(&mut self, id: Uuid, cx: &mut Context<Self>) {
+        let Some(position) = self.open_note_ids.iter().position(|open_id| *open_id == id) else {
+            return;
+        };
+        self.open_note_ids.remove(position);
+
+        if self.active_note_id == Some(id) {
+            let neighbor = self
+                .open_note_ids
+                .get(position)
+                .or_else(|| self.open_note_ids.last())
+                .copied();
+
+            match neighbor {
+                Some(neighbor_id) => self.set_active_note(neighbor_id, cx),
+                None => self.clear_active_note(cx),
+            }
+        }
+
+        self.save_session(cx);
+        cx.notify();
+    }
+
+    fn clear_active_note(&mut self, cx: &mut Context<Self>) {
+        self.active_note_id = None;
+        self.title_edit_mode = false;
+        self.editor.update(cx, |editor, cx| {
+            editor.binding.rebind(None);
+            editor.set_content("", cx);
+        });
+        self.title_text = String::new();
+        self.title_editor.update(cx, |editor, cx| {
+            editor.set_content("", cx);
+        });
+    }
+
+    fn save_session(&mut self, cx: &mut Context<Self>) {
+        let session = Session {
+            open_note_ids: self.open_note_ids.clone(),
+            active_note_id: self.active_note_id,
+        };
+
+        if let Err(e) = session.save(&get_session_path()) {
+            self.report_error(format!("Failed to save session: {}", e), cx);
+        }
+    }
+
+    pub fn set_active_note(&mut self, id: Uuid, cx: &mut Context<Self>) {
+        let opening = self.active_note_id != Some(id);
+        if self.auto_title_note_id != Some(id) {
+            self.auto_title_note_id = None;
+        }
+
+        if let Some(previous_id) = self.active_note_id.filter(|previous_id| *previous_id != id) {
+            self.discard_if_empty_placeholder(previous_id, cx);
+        }
+
+        let fresh_note = self.db.notes.get_note(id);
+
+        if let Ok(Some(note)) = fresh_note {
+            for cached_note in &mut self.notes {
+                if cached_note.id == id {
+                    *cached_note = note.clone();
+                    break;
+                }
+            }
+
+            self.active_note_id = Some(id);
+            self.title_edit_mode = false;
+            self.title_text = note.title.clone();
+            self.load_note_into_editor(&note, cx);
+            self.open_tab(id, cx);
+        } else {
+            let fallback_note = self.notes.iter().find(|n| n.id == id).cloned();
+
+            if let Some(note) = fallback_note {
+                self.active_note_id = Some(id);
+                self.title_edit_mode = false;
+                self.title_text = note.title.clone();
+                self.load_note_into_editor(&note, cx);
+                self.open_tab(id, cx);
+
+                if matches!(fresh_note, Ok(None)) {
+                    self.mark_note_missing(note, cx);
+                }
+            }
+        }
+
+        if opening && self.active_note_id == Some(id) {
+            self.announce_active_note(cx);
+            self.reveal_active_note();
+            self.load_note_attachments();
+        }
+
+        cx.notify();
+    }
+
+    pub fn get_active_note(&self) -> Option<&Note> {
+        if let Some(id) = self.active_note_id {
+            self.notes.iter().find(|note| note.id == id)
+        } else {
+            None
+        }
+    }
+
+    pub fn toggle_title_edit_mode(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.auto_title_note_id = None;
+
+        let Some((title, locked)) = self
+            .get_active_note()
+            .map(|note| (note.title.clone(), note.locked))
+        else {
+            return;
+        };
+        if locked && !self.title_edit_mode {
+            self.report_status("Note is locked", cx);
+            return;
+        }
+
+        self.title_edit_mode = !self.title_edit_mode;
+
+        if self.title_edit_mode {
+            self.title_text = title.clone();
+            self.title_editor.update(cx, |editor, cx| {
+                editor.set_content(title, cx);
+            });
+        } else {
+            self.title_text = self.title_editor.read(cx).content.to_string();
+            self.save_title(cx);
+        }
+
+        cx.notify();
+
+        let main_editor_focus = self.editor.read(cx).focus_handle.clone();
+        main_editor_focus.focus(window);
+    }
+
+    /// Commits `title_text` to the active note. An empty title keeps the
+    /// note's stored title, or the configured default if that is empty too.
+    pub fn save_title(&mut self, cx: &mut Context<Self>) {
+        let Some(note_id) = self.active_note_id else {
+            return;
+        };
+        let existing_note = match self.db.notes.get_note(note_id) {
+            Ok(Some(note)) => note,
+            Ok(None) => return,
+            Err(e) => {
+                self.report_error(format!("Failed to load note title: {}", e), cx);
+                return;
+            }
+        };
+
+        let final_title = self
+            .settings
+            .resolve_title(&self.title_text, &existing_note.title);
+
+        if final_title != self.title_text {
+            self.title_text = final_title.clone();
+            self.title_editor.update(cx, |editor, cx| {
+                editor.set_content(final_title.clone(), cx);
+            });
+        }
+
+        if existing_note.title != final_title {
+            self.writer.update(note_id, NoteChange::Title(final_title.clone()));
+        }
+
+        if let Some(note) = self.notes.iter_mut().find(|note| note.id == note_id) {
+            note.title = final_title;
+        }
+        self.sync_status_item();
+        cx.notify();
+    }
+
+    pub fn handle_title_key_down(
+        &mut self,
+        event: &KeyDownEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if event.keystroke.key == "enter" || event.keystroke.key == "escape" {
+            self.on_title_blur(window, cx);
+        }
+    }
+
+    pub fn on_title_blur(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if self.title_edit_mode {
+            self.title_text = self.title_editor.read(cx).content.to_string();
+            self.save_title(cx);
+
+            self.title_edit_mode = false;
+            cx.notify();
+
+            let main_editor_focus = self.editor.read(cx).focus_handle.clone();
+            main_editor_focus.focus(window);
+        }
+    }
+}
+
+impl NoteApp {
+    pub fn export_active_note_to_pdf(&mut self, cx: &mut Context<Self>) {
+        let Some(note) = self.get_active_note() else {
+            return;
+        };
+        if self.is_note_locked(note.id) {
+            return;
+        }
+
+        let title = note.title.clone();
+        let content = self.editor.read(cx).content.to_string();
+        let path = get_export_dir().join(format!("{}.pdf", sanitize_filename(&title)));
+
+        match export_note_to_pdf(&title, &content, &path) {
+            Ok(()) => self.report_status(format!("Exported note to {}", path.display()), cx),
+            Err(e) => self.report_error(format!("Failed to export note to PDF: {}", e), cx),
+        }
+    }
+
+    pub fn toggle_note_selection(&mut self, id: Uuid, cx: &mut Context<Self>) {
+        if self.selected_ids.is_empty() {
+            if let Some(active_id) = self.active_note_id {
+                self.selected_ids.insert(active_id);
+            }
+        }
+
+        if !self.selected_ids.remove(&id) {
+            self.selected_ids.insert(id);
+        }
+        cx.notify();
+    }
+
+    fn notes_for_export(&self) -> Result<Vec<Note>, rusqlite::Error> {
+        let ids: Vec<Uuid> = if self.selected_ids.is_empty() {
+            self.active_note_id.into_iter().collect()
+        } else {
+            self.notes
+                .iter()
+                .map(|note| note.id)
+                .filter(|id| self.selected_ids.contains(id))
+                .collect()
+        };
+
+        let mut notes = Vec::with_capacity(ids.len());
+        for mut note in self.db.notes.get_notes(&ids)? {
+            let Some(content) = self.readable_content(&note) else {
+                continue;
+            };
+            note.content = content;
+            note.encrypted = false;
+            notes.push(note);
+        }
+        Ok(notes)
+    }
+
+    pub fn export_selected_notes_as_markdown(&mut self, cx: &mut Context<Self>) {
+        let notes = match self.notes_for_export() {
+            Ok(notes) if !notes.is_empty() => notes,
+            Ok(_) => return,
+            Err(e) => {
+                self.report_error(format!("Failed to load notes for export: {}", e), cx);
+                return;
+            }
+        };
+
+        let dir = get_export_dir().join("Tap Export");
+        self.run_busy("Exporting notes…", cx, move |app, cx| {
+            match export_notes_to_markdown(&notes, &dir, app.settings.export_line_ending) {
+                Ok(count) => app.report_status(
+                    format!("Exported {} note(s) to {}", count, dir.display()),
+                    cx,
+                ),
+                Err(e) => app.report_error(format!("Failed to export notes: {}", e), cx),
+            }
+        });
+    }
+
+    pub fn export_selected_notes_as_json(&mut self, cx: &mut Context<Self>) {
+        let notes = match self.notes_for_export() {
+            Ok(notes) if !notes.is_empty() => notes,
+            Ok(_) => return,
+            Err(e) => {
+                self.report_error(format!("Failed to load notes for export: {}", e), cx);
+                return;
+            }
+        };
+
+        let path = get_export_dir().join("Tap Notes.json");
+        self.run_busy("Exporting notes…", cx, move |app, cx| {
+            match export_notes_to_json(&notes, &path) {
+                Ok(count) => app.report_status(
+                    format!("Exported {} note(s) to {}", count, path.display()),
+                    cx,
+                ),
+                Err(e) => app.report_error(format!("Failed to export notes: {}", e), cx),
+            }
+        });
+    }
+
+    pub fn export_all_notes_as_jsonl(&mut self, cx: &mut Context<Self>) {
+        let path = get_export_dir().join("Tap Notes.jsonl");
+        self.run_busy("Exporting notes…", cx, move |app, cx| {
+            match export_notes_to_jsonl(&app.db.notes, &path) {
+                Ok(count) => app.report_status(
+                    format!("Exported {} note(s) to {}", count, path.display()),
+                    cx,
+                ),
+                Err(e) => app.report_error(format!("Failed to export notes: {}", e), cx),
+            }
+        });
+    }
+
+    /// Imports the file written by `export_all_notes_as_jsonl`, leaving notes
+    /// that are already in the library alone.
+    pub fn import_notes_from_jsonl(&mut self, cx: &mut Context<Self>) {
+        let path = get_export_dir().join("Tap Notes.jsonl");
+        self.run_busy("Importing notes…", cx, move |app, cx| {
+            let result = import_notes_from_jsonl(&app.db.notes, &path);
+            match Self::load_notes(&app.db) {
+                Ok(notes) => app.notes = notes,
+                Err(e) => app.report_error(
+                    format!("Failed to reload notes after import: {}", e),
+                    cx,
+                ),
+            }
+            app.sync_status_item();
+            match result {
+                Ok(count) => app.report_status(
+                    format!("Imported {} note(s) from {}", count, path.display()),
+                    cx,
+                ),
+                Err(e) => app.report_error(format!("Failed to import notes: {}", e), cx),
+            }
+        });
+    }
+
+    fn announce_active_note(&self, cx: &App) {
+        let Some(id) = self.active_note_id else {
+            return;
+        };
+        let visible = self.visible_note_ids();
+        let position = visible
+            .iter()
+            .position(|visible_id| *visible_id == id)
+            .map(|index| (index, visible.len()));
+        let content = self.editor.read(cx).content.clone();
+        let content = (!self.is_note_locked(id)).then_some(content.as_ref());
+        announce(&note_announcement(&self.title_text, position, content));
+    }
+
+    /// Hands the writer the enabled save transforms, so every save stores
+    /// the transformed text, including the last ones before quitting. The
+    /// editor keeps what was typed, so a trimmed space doesn't vanish from
+    /// under the caret.
+    fn sync_save_transforms(&self) {
+        let enabled = self.settings.save_transforms.clone();
+        let transforms = Arc::clone(&self.save_transforms);
+        let transform: Option<ContentTransform> = (!enabled.is_empty())
+            .then(|| Box::new(move |content: &str| transforms.apply(content, &enabled)) as _);
+        self.writer.set_transform(transform);
+    }
+
+    fn is_note_locked(&self, id: Uuid) -> bool {
+        self.notes
+            .iter()
+            .any(|note| note.id == id && note.encrypted)
+            && !self.unlocked_passphrases.contains_key(&id)
+    }
+
+    fn readable_content(&self, note: &Note) -> Option<String> {
+        if !note.encrypted {
+            return Some(note.content.clone());
+        }
+
+        let passphrase = self.unlocked_passphrases.get(&note.id)?;
+        match decrypt_content(passphrase, &note.content) {
+            Ok(content) => Some(content),
+            Err(e) => {
+                eprintln!("Failed to decrypt note {}: {}", note.id, e);
+                None
+            }
+        }
+    }
+
+    fn load_note_into_editor(&mut self, note: &Note, cx: &mut Context<Self>) {
+        let content = self.readable_content(note);
+        let locked = content.is_none();
+
+        self.editor.update(cx, |editor, cx| {
+            // Detach the previous note's save before its text is replaced;
+            // binding below attaches this note's.
+            editor.binding.rebind(None);
+            editor.read_only = note.locked;
+            editor.set_content(content.unwrap_or_default(), cx);
+        });
+
+        if !locked {
+            self.bind_editor_to_note(note.id, cx);
+        }
+    }
+
+    pub fn toggle_encrypt_prompt(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.show_encrypt_prompt = !self.show_encrypt_prompt;
+        self.passphrase_editor.update(cx, |editor, cx| editor.set_content("", cx));
+        if self.show_encrypt_prompt {
+            let focus_handle = self.passphrase_editor.read(cx).focus_handle.clone();
+            focus_handle.focus(window);
+        }
+        cx.notify();
+    }
+
+    fn submit_passphrase(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(active_id) = self.active_note_id else {
+            return;
+        };
+
+        let passphrase = self.passphrase_editor.read(cx).content.to_string();
+        if passphrase.is_empty() {
+            return;
+        }
+        self.passphrase_editor.update(cx, |editor, cx| editor.set_content("", cx));
+
+        if self.is_note_locked(active_id) {
+            self.unlock_note(active_id, passphrase, cx);
+        } else if self.show_encrypt_prompt {
+            self.encrypt_note(active_id, passphrase, cx);
+            self.show_encrypt_prompt = false;
+        }
+
+        let main_editor_focus = self.editor.read(cx).focus_handle.clone();
+        main_editor_focus.focus(window);
+        cx.notify();
+    }
+
+    fn unlock_note(&mut self, id: Uuid, passphrase: String, cx: &mut Context<Self>) {
+        let note = match self.db.notes.get_note(id) {
+            Ok(Some(note)) => note,
+            Ok(None) => return,
+            Err(e) => {
+                self.report_error(format!("Failed to load note: {}", e), cx);
+                return;
+            }
+        };
+
+        if let Err(e) = decrypt_content(&passphrase, &note.content) {
+            self.report_error(format!("Could not unlock note: {}", e), cx);
+            return;
+        }
+
+        self.unlocked_passphrases.insert(id, passphrase);
+        self.load_note_into_editor(&note, cx);
+    }
+
+    fn encrypt_note(&mut self, id: Uuid, passphrase: String, cx: &mut Context<Self>) {
+        if self.auto_title_note_id == Some(id) {
+            self.auto_title_note_id = None;
+        }
+        let content = self.editor.read(cx).content.to_string();
+        self.unlocked_passphrases.insert(id, passphrase.clone());
+        self.writer.set_encryption(id, content, Some(passphrase));
+        self.bind_editor_to_note(id, cx);
+    }
+
+    pub fn remove_active_note_encryption(&mut self, cx: &mut Context<Self>) {
+        let Some(active_id) = self.active_note_id else {
+            return;
+        };
+        if self.is_note_locked(active_id) {
+            return;
+        }
+
+        let content = self.editor.read(cx).content.to_string();
+        self.writer.set_encryption(active_id, content, None);
+        self.unlocked_passphrases.remove(&active_id);
+        self.bind_editor_to_note(active_id, cx);
+    }
+
+    fn handle_passphrase_key_down(
+        &mut self,
+        event: &KeyDownEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if event.keystroke.key == "enter" {
+            self.submit_passphrase(window, cx);
+            cx.stop_propagation();
+        } else if event.keystroke.key == "escape" && self.show_encrypt_prompt {
+            self.toggle_encrypt_prompt(window, cx);
+            cx.stop_propagation();
+        }
+    }
+
+    pub fn toggle_active_note_font(&mut self, cx: &mut Context<Self>) {
+        let Some(active_id) = self.active_note_id else {
+            return;
+        };
+
+        let Some(note) = self.notes.iter_mut().find(|note| note.id == active_id) else {
+            return;
+        };
+        note.monospace = !note.monospace;
+        self.writer.update(active_id, NoteChange::Monospace(note.monospace));
+        cx.notify();
+    }
+
+    pub fn set_auto_close_brackets(&mut self, enabled: bool, cx: &mut Context<Self>) {
+        self.settings.auto_close_brackets = enabled;
+        self.editor.update(cx, |editor, _cx| {
+            editor.auto_close_brackets = enabled;
+        });
+        self.save_settings(cx);
+        cx.notify();
+    }
+
+    pub fn set_confirm_close_with_unsaved_edits(&mut self, enabled: bool, cx: &mut Context<Self>) {
+        self.settings.confirm_close_with_unsaved_edits = enabled;
+        self.save_settings(cx);
+        cx.notify();
+    }
+
+    pub fn set_paste_url_as_link(&mut self, enabled: bool, cx: &mut Context<Self>) {
+        self.settings.paste_url_as_link = enabled;
+        self.editor.update(cx, |editor, _cx| {
+            editor.paste_url_as_link = enabled;
+        });
+        self.save_settings(cx);
+        cx.notify();
+    }
+
+    pub fn set_indent_style(
+        &mut self,
+        indent_style: IndentStyle,
+        tab_width: usize,
+        cx: &mut Context<Self>,
+    ) {
+        self.settings.indent_style = indent_style;
+        self.settings.tab_width = tab_width.clamp(1, 16);
+        self.save_settings(cx);
+
+        let tab_width = self.settings.tab_width;
+        self.editor.update(cx, |editor, cx| {
+            editor.indent_style = indent_style;
+            editor.tab_width = tab_width;
+            cx.notify();
+        });
+        cx.notify();
+    }
+
+    pub fn set_editor_zoom(&mut self, zoom: f32, cx: &mut Context<Self>) {
+        // Round away float drift so repeated steps land back on 1.0.
+        self.settings.editor_zoom = ((zoom * 10.0).round() / 10.0).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.save_settings(cx);
+
+        // Hit-testing divides by the editor's line height, so it must match
+        // the zoomed height the content area renders with.
+        let line_height = px(self.settings.editor_line_height());
+        self.editor.update(cx, |editor, cx| {
+            editor.line_height = line_height;
+            cx.notify();
+        });
+        cx.notify();
+    }
+
+    pub fn set_line_spacing(
+        &mut self,
+        line_spacing: f32,
+        paragraph_spacing: f32,
+        cx: &mut Context<Self>,
+    ) {
+        self.settings.line_spacing = line_spacing.max(1.0);
+        self.settings.paragraph_spacing = paragraph_spacing.max(0.0);
+        self.save_settings(cx);
+
+        let line_height = px(self.settings.editor_line_height());
+        let paragraph_spacing = px(self.settings.paragraph_spacing);
+        self.editor.update(cx, |editor, cx| {
+            editor.line_height = line_height;
+            editor.paragraph_spacing = paragraph_spacing;
+            cx.notify();
+        });
+        cx.notify();
+    }
+
+    pub fn set_content_padding(&mut self, padding: f32, cx: &mut Context<Self>) {
+        self.settings.content_padding = padding.max(0.0);
+        self.save_settings(cx);
+        cx.notify();
+    }
+
+    pub fn set_focus_width(&mut self, width: Option<f32>, cx: &mut Context<Self>) {
+        self.settings.focus_width = width.map(|width| width.max(240.0));
+        self.save_settings(cx);
+        cx.notify();
+    }
+
+    pub fn set_auto_title_from_content(&mut self, enabled: bool, cx: &mut Context<Self>) {
+        self.settings.auto_title_from_content = enabled;
+        if !enabled {
+            self.auto_title_note_id = None;
+        }
+        self.save_settings(cx);
+        cx.notify();
+    }
+
+    pub fn toggle_save_transform(&mut self, id: &str, cx: &mut Context<Self>) {
+        let enabled = &mut self.settings.save_transforms;
+        if enabled.iter().any(|enabled_id| enabled_id == id) {
+            enabled.retain(|enabled_id| enabled_id != id);
+        } else {
+            enabled.push(id.to_string());
+        }
+        self.save_settings(cx);
+        self.sync_save_transforms();
+        cx.notify();
+    }
+
+    pub fn set_discard_empty_untitled_notes(&mut self, enabled: bool, cx: &mut Context<Self>) {
+        self.settings.discard_empty_untitled_notes = enabled;
+        self.save_settings(cx);
+    }
+
+    pub fn set_new_note_placement(&mut self, placement: NewNotePlacement, cx: &mut Context<Self>) {
+        self.settings.new_note_placement = placement;
+        self.save_settings(cx);
+        cx.notify();
+    }
+
+    pub fn set_sidebar_density(&mut self, density: SidebarDensity, cx: &mut Context<Self>) {
+        self.settings.sidebar_density = density;
+        self.save_settings(cx);
+        cx.notify();
+    }
+
+    fn save_settings(&mut self, cx: &mut Context<Self>) {
+        if let Err(e) = self.settings.save(&get_settings_path()) {
+            self.report_error(format!("Failed to save settings: {}", e), cx);
+        }
+    }
+
+    pub fn toggle_note_info(&mut self, cx: &mut Context<Self>) {
+        self.show_note_info = !self.show_note_info;
+        self.load_note_attachments();
+        cx.notify();
+    }
+
+    fn load_note_attachments(&mut self) {
+        let Some(note_id) = self.active_note_id.filter(|_| self.show_note_info) else {
+            return;
+        };
+        self.note_attachments = match self.db.attachments.list_attachments(note_id) {
+            Ok(attachments) if attachments.is_empty() => "None".to_string(),
+            Ok(attachments) => attachments
+                .iter()
+                .map(|attachment| {
+                    std::path::Path::new(&attachment.path)
+                        .file_name()
+                        .map_or(attachment.path.clone(), |name| {
+                            name.to_string_lossy().into_owned()
+                        })
+                })
+                .collect::<Vec<_>>()
+                .join(", "),
+            Err(e) => format!("Unavailable ({})", e),
+        };
+    }
+
+    pub fn toggle_split_preview(&mut self, cx: &mut Context<Self>) {
+        self.settings.split_preview = !self.settings.split_preview;
+        self.save_settings(cx);
+        cx.notify();
+    }
+
+    pub fn set_undo_limits(&mut self, limits: UndoLimits, cx: &mut Context<Self>) {
+        self.settings.undo_limits = limits;
+        self.save_settings(cx);
+        self.editor.update(cx, |editor, _cx| editor.history.set_limits(limits));
+        cx.notify();
+    }
+
+    pub fn set_vim_mode(&mut self, enabled: bool, cx: &mut Context<Self>) {
+        self.settings.vim_mode = enabled;
+        self.editor.update(cx, |editor, cx| {
+            editor.vim_mode = enabled;
+            editor.set_edit_mode(EditMode::Normal, cx);
+        });
+        self.save_settings(cx);
+        cx.notify();
+    }
+
+    pub fn toggle_replace_panel(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.show_replace_panel = !self.show_replace_panel;
+        self.replace_status = None;
+
+        if self.show_replace_panel {
+            let find_focus = self.replace_find_editor.read(cx).focus_handle.clone();
+            find_focus.focus(window);
+        } else {
+            let main_editor_focus = self.editor.read(cx).focus_handle.clone();
+            main_editor_focus.focus(window);
+        }
+
+        cx.notify();
+    }
+
+    pub fn replace_in_all_notes(&mut self, cx: &mut Context<Self>) {
+        let find = self.replace_find_editor.read(cx).content.to_string();
+        let replace = self.replace_with_editor.read(cx).content.to_string();
+
+        if find.is_empty() {
+            self.replace_status = Some("Enter text to find".into());
+            cx.notify();
+            return;
+        }
+
+        self.run_busy("Replacing in all notes…", cx, move |app, cx| {
+            match app
+                .db
+                .notes
+                .replace_in_all(&find, &replace, app.replace_case_sensitive)
+            {
+                Ok(replaced) => {
+                    app.replace_status = Some(format!(
+                        "Replaced {} occurrence{} in {} note{}",
+                        replaced.occurrences,
+                        if replaced.occurrences == 1 { "" } else { "s" },
+                        replaced.notes,
+                        if replaced.notes == 1 { "" } else { "s" }
+                    ));
+
+                    match Self::load_notes(&app.db) {
+                        Ok(notes) => app.notes = notes,
+                        Err(e) => app.report_error(
+                            format!("Failed to reload notes after replace: {}", e),
+                            cx,
+                        ),
+                    }
+
+                    if let Some(active_id) = app.active_note_id {
+                        app.set_active_note(active_id, cx);
+                    }
+                }
+                Err(e) => {
+                    app.replace_status = Some("Replace failed".into());
+                    app.report_error(format!("Failed to replace in all notes: {}", e), cx);
+                }
+            }
+        });
+    }
+
+    /// Shows `task` in the busy overlay and runs `operation` once the overlay
+    /// has had a frame to draw. The database calls are still synchronous, so
+    /// the UI blocks while `operation` runs, but no longer without feedback.
+    fn run_busy(
+        &mut self,
+        task: impl Into<String>,
+        cx: &mut Context<Self>,
+        operation: impl FnOnce(&mut Self, &mut Context<Self>) + 'static,
+    ) {
+        if self.busy.is_some() {
+            return;
+        }
+        self.busy = Some(task.into());
+        cx.notify();
+
+        cx.spawn(async move |this, cx| {
+            cx.background_executor().timer(BUSY_OVERLAY_DELAY).await;
+            this.update(cx, |app, cx| {
+                operation(app, cx);
+                app.busy = None;
+                cx.notify();
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    pub fn handle_key_down(
+        &mut self,
+        event: &KeyDownEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if self.show_shortcuts && event.keystroke.key == "escape" {
+            self.toggle_shortcuts(cx);
+            cx.stop_propagation();
+            return;
+        }
+        if self.show_settings && event.keystroke.key == "escape" {
+            self.toggle_settings(cx);
+            cx.stop_propagation();
+            return;
+        }
+        if self.duplicate_groups.is_some() && event.keystroke.key == "escape" {
+            self.close_duplicate_review(cx);
+            cx.stop_propagation();
+            return;
+        }
+        if self.onboarding_step.is_some() && event.keystroke.key == "escape" {
+            self.finish_onboarding(cx);
+            cx.stop_propagation();
+            return;
+        }
+
+        let text_focused = self.editor.read(cx).focus_handle.is_focused(window)
+            || self.title_editor.read(cx).focus_handle.is_focused(window)
+            || self.replace_find_editor.read(cx).focus_handle.is_focused(window)
+            || self.replace_with_editor.read(cx).focus_handle.is_focused(window)
+            || self.search_editor.read(cx).focus_handle.is_focused(window);
+
+        let Some(action) = action_for_keystroke(&event.keystroke, text_focused) else {
+            return;
+        };
+
+        match action {
+            ShortcutAction::NewNote => self.add_note(window, cx),
+            ShortcutAction::RenameNote => self.start_title_edit_from_keyboard(window, cx),
+            ShortcutAction::ToggleReplacePanel => self.toggle_replace_panel(window, cx),
+            ShortcutAction::ToggleShortcuts => self.toggle_shortcuts(cx),
+            ShortcutAction::ZoomIn => {
+                self.set_editor_zoom(self.settings.editor_zoom + ZOOM_STEP, cx)
+            }
+            ShortcutAction::ZoomOut => {
+                self.set_editor_zoom(self.settings.editor_zoom - ZOOM_STEP, cx)
+            }
+            ShortcutAction::ResetZoom => self.set_editor_zoom(1.0, cx),
+            ShortcutAction::CloseTab => {
+                if let Some(active_id) = self.active_note_id {
+                    self.close_tab(active_id, cx);
+                }
+            }
+            ShortcutAction::JumpToNote(number) => self.jump_to_note(number, cx),
+            ShortcutAction::InsertDate => self.insert_current_date(window, cx),
+            ShortcutAction::ToggleSidebarFocus => self.toggle_sidebar_focus(window, cx),
+            ShortcutAction::CopyNote => {
+                if let Some(active_id) = self.active_note_id {
+                    self.copy_note_to_clipboard(active_id, true, cx);
+                }
+            }
+        }
+        cx.stop_propagation();
+    }
+
+    fn insert_current_date(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if !self.editor.read(cx).focus_handle.is_focused(window) {
+            return;
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let text = format_timestamp(now, &self.settings.date_format);
+        self.editor.update(cx, |editor, cx| {
+            editor.replace_text_in_range(None, &text, window, cx);
+        });
+    }
+
+    pub fn set_export_line_ending(&mut self, line_ending: LineEnding, cx: &mut Context<Self>) {
+        self.settings.export_line_ending = line_ending;
+        self.save_settings(cx);
+        cx.notify();
+    }
+
+    pub fn set_date_format(&mut self, format: impl Into<String>, cx: &mut Context<Self>) {
+        self.settings.date_format = format.into();
+        self.save_settings(cx);
+        cx.notify();
+    }
+
+    /// Activates the `number`th note in the sidebar's current order and
+    /// filter, or the last one for 9. Does nothing if there are fewer notes.
+    fn jump_to_note(&mut self, number: usize, cx: &mut Context<Self>) {
+        let visible = self.visible_note_ids();
+        let target = if number == 9 {
+            visible.last()
+        } else {
+            visible.get(number.saturating_sub(1))
+        };
+
+        if let Some(&id) = target {
+            self.selected_ids.clear();
+            self.set_active_note(id, cx);
+        }
+    }
+
+    fn visible_note_ids(&self) -> Vec<Uuid> {
+        self.notes
+            .iter()
+            .filter(|note| self.is_listed(note) && note_matches_query(note, &self.search_query))
+            .map(|note| note.id)
+            .collect()
+    }
+
+    fn toggle_sidebar_focus(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if self.sidebar_focus_handle.is_focused(window) {
+            self.focus_editor(window, cx);
+        } else {
+            self.sidebar_focus_handle.focus(window);
+        }
+        cx.notify();
+    }
+
+    fn handle_sidebar_key_down(
+        &mut self,
+        event: &KeyDownEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let modifiers = &event.keystroke.modifiers;
+        if modifiers.platform || modifiers.control || modifiers.alt {
+            return;
+        }
+
+        match event.keystroke.key.as_str() {
+            "arrowup" => self.step_active_note(-1, cx),
+            "arrowdown" => self.step_active_note(1, cx),
+            "enter" => self.focus_editor(window, cx),
+            _ => return,
+        }
+        cx.stop_propagation();
+    }
+
+    fn reveal_active_note(&self) {
+        let Some(id) = self.active_note_id else {
+            return;
+        };
+        if let Some(index) = self.visible_note_ids().iter().position(|visible| *visible == id) {
+            self.sidebar_scroll.scroll_to_item(index);
+        }
+    }
+
+    /// Activates the note `step` places away from the active one in the
+    /// sidebar, stopping at either end.
+    fn step_active_note(&mut self, step: isize, cx: &mut Context<Self>) {
+        let visible = self.visible_note_ids();
+        let current = self
+            .active_note_id
+            .and_then(|id| visible.iter().position(|visible_id| *visible_id == id));
+        let target = match current {
+            Some(index) => index.saturating_add_signed(step).min(visible.len().saturating_sub(1)),
+            None => 0,
+        };
+
+        if let Some(&id) = visible.get(target) {
+            if Some(id) != self.active_note_id {
+                self.selected_ids.clear();
+                self.set_active_note(id, cx);
+            }
+        }
+    }
+
+    pub fn toggle_shortcuts(&mut self, cx: &mut Context<Self>) {
+        self.show_shortcuts = !self.show_shortcuts;
+        cx.notify();
+    }
+
+    fn advance_onboarding(&mut self, cx: &mut Context<Self>) {
+        match self.onboarding_step {
+            Some(step) if step + 1 < ONBOARDING_STEPS.len() => {
+                self.onboarding_step = Some(step + 1);
+                cx.notify();
+            }
+            Some(_) => self.finish_onboarding(cx),
+            None => {}
+        }
+    }
+
+    fn finish_onboarding(&mut self, cx: &mut Context<Self>) {
+        self.onboarding_step = None;
+        self.settings.onboarding_completed = true;
+        self.save_settings(cx);
+        cx.notify();
+    }
+
+    pub fn toggle_settings(&mut self, cx: &mut Context<Self>) {
+        self.show_settings = !self.show_settings;
+        if self.show_settings {
+            self.database_size = self.db.notes.database_size_bytes().ok();
+        }
+        cx.notify();
+    }
+
+    fn start_title_edit_from_keyboard(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if self.get_active_note().is_none() || self.editor.read(cx).marked_range.is_some() {
+            return;
+        }
+
+        self.toggle_title_edit_mode(window, cx);
+        if self.title_edit_mode {
+            let title_focus = self.title_editor.read(cx).focus_handle.clone();
+            title_focus.focus(window);
+        }
+    }
+
+    /// Esc in the search field clears it and hands focus back to the note
+    /// list, or to the editor when the list is empty.
+    fn handle_search_key_down(
+        &mut self,
+        event: &KeyDownEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if event.keystroke.key != "escape" {
+            return;
+        }
+
+        self.search_editor
+            .update(cx, |editor, cx| editor.set_content("", cx));
+        self.search_query.clear();
+        if self.visible_note_ids().is_empty() {
+            self.focus_editor(window, cx);
+        } else {
+            self.sidebar_focus_handle.focus(window);
+        }
+        cx.stop_propagation();
+        cx.notify();
+    }
+
+    fn handle_replace_key_down(
+        &mut self,
+        event: &KeyDownEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if event.keystroke.key == "enter" {
+            self.replace_in_all_notes(cx);
+        } else if event.keystroke.key == "escape" {
+            self.toggle_replace_panel(window, cx);
+        }
+    }
+}
+
+impl Focusable for NoteApp {
+    fn focus_handle(&self, cx: &App) -> FocusHandle {
+        self.content_focus_handle.clone()
+    }
+}
+
+impl Render for NoteApp {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        if std::mem::take(&mut self.pending_editor_focus) {
+            self.focus_editor(window, cx);
+        }
+        without_writes(|| self.render_root(cx))
     }
 }
 
-impl NoteApp {
-    fn render_sidebar(&self, cx: &mut Context<Self>) -> impl IntoElement {
-        let notes = self.notes.clone();
-        let active_note_id = self.active_note_id;
+impl NoteApp {
+    fn render_root(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        div()
+            .flex()
+            .bg(rgb(0xf5f5f5))
+            .size_full()
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|view, event, window, cx| {
+                    if view.title_edit_mode {
+                        let title_focused =
+                            view.title_editor.read(cx).focus_handle.is_focused(window);
+                        if !title_focused {
+                            view.on_title_blur(window, cx);
+                        }
+                    }
+                }),
+            )
+            .on_key_down(cx.listener(Self::handle_key_down))
+            .on_action(cx.listener(|view, _: &ExportToPdf, _window, cx| {
+                view.export_active_note_to_pdf(cx);
+            }))
+            .on_action(cx.listener(|view, _: &ExportSelectedAsMarkdown, _window, cx| {
+                view.export_selected_notes_as_markdown(cx);
+            }))
+            .on_action(cx.listener(|view, _: &ExportSelectedAsJson, _window, cx| {
+                view.export_selected_notes_as_json(cx);
+            }))
+            .on_action(cx.listener(|view, _: &ExportAllAsJsonLines, _window, cx| {
+                view.export_all_notes_as_jsonl(cx);
+            }))
+            .on_action(cx.listener(|view, _: &ImportJsonLines, _window, cx| {
+                view.import_notes_from_jsonl(cx);
+            }))
+            .child(self.render_sidebar(cx))
+            .child(self.render_content(cx))
+            .children(self.render_status_banner(cx))
+            .children(self.render_error_banner(cx))
+            .when(self.show_shortcuts, |this| {
+                this.child(self.render_shortcuts_overlay(cx))
+            })
+            .when(self.show_settings, |this| {
+                this.child(self.render_settings_overlay(cx))
+            })
+            .children(self.render_duplicates_overlay(cx))
+            .children(self.render_onboarding_overlay(cx))
+            .children(self.render_busy_overlay())
+    }
+
+    fn render_sidebar(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let query = self.search_query.clone();
+        let notes: Vec<Note> = self
+            .notes
+            .iter()
+            .filter(|note| self.is_listed(note) && note_matches_query(note, &query))
+            .cloned()
+            .collect();
+        let showing_favorites = self.favorite_ids.is_some();
+        let active_note_id = self.active_note_id;
+        let compact = self.settings.sidebar_density == SidebarDensity::Compact;
+        let note_count = notes.len();
+
+        div()
+            .flex()
+            .flex_col()
+            .w(px(200.0))
+            .h_full()
+            .bg(rgb(0xf0f0f0))
+            .px_2()
+            .border_r_1()
+            .rounded_lg()
+            .border_color(rgb(0xE0E0E0))
+            .child(
+                div()
+                    .flex()
+                    .justify_between()
+                    .items_center()
+                    .p_2()
+                    .child(
+                        div()
+                            .text_sm()
+                            .text_color(if showing_favorites {
+                                rgb(0xd99a00)
+                            } else {
+                                rgb(0x666666)
+                            })
+                            .cursor_pointer()
+                            .hover(|s| s.text_color(rgb(0x4287f5)))
+                            .on_mouse_down(
+                                MouseButton::Left,
+                                cx.listener(|view, _: &MouseDownEvent, _window, cx| {
+                                    view.toggle_favorites(cx);
+                                }),
+                            )
+                            .child(if showing_favorites {
+                                "★ Favorites"
+                            } else {
+                                "☆ Favorites"
+                            }),
+                    )
+                    .child(
+                        div()
+                            .relative()
+                            .child(
+                                div()
+                                    .size(px(28.0))
+                                    .flex()
+                                    .justify_center()
+                                    .items_center()
+                                    .bg(rgb(0x4287f5))
+                                    .text_color(rgb(0xffffff))
+                                    .text_lg()
+                                    .font_weight(FontWeight::BOLD)
+                                    .rounded_full()
+                                    .cursor_pointer()
+                                    .hover(|s| s.bg(rgb(0x3276e4)))
+                                    .on_mouse_down(
+                                        MouseButton::Left,
+                                        cx.listener(|view, event: &MouseDownEvent, window, cx| {
+                                            view.click_new_note_button(
+                                                event.modifiers.alt,
+                                                window,
+                                                cx,
+                                            );
+                                        }),
+                                    )
+                                    .child("+"),
+                            )
+                            .when(self.show_template_menu, |this| {
+                                this.child(deferred(self.render_template_menu(cx)))
+                            }),
+                    ),
+            )
+            .child(self.render_search_field(notes.len(), cx))
+            .when(notes.is_empty() && !query.is_empty(), |this| {
+                this.child(self.render_no_search_results(&query, cx))
+            })
+            .child(
+                div()
+                    .id("sidebar-notes")
+                    .track_focus(&self.sidebar_focus_handle)
+                    .on_key_down(cx.listener(Self::handle_sidebar_key_down))
+                    .flex()
+                    .flex_col()
+                    .flex_grow()
+                    .overflow_y_scroll()
+                    .track_scroll(&self.sidebar_scroll)
+                    .p_2()
+                    .rounded_md()
+                    .border_1()
+                    .border_color(rgb(0xf0f0f0))
+                    .focus(|style| style.border_color(rgb(0x4287f5)))
+                    .children(
+                        notes
+                            .iter()
+                            .map(|note| {
+                                let is_active = active_note_id == Some(note.id);
+                                let is_selected = self.selected_ids.contains(&note.id);
+                                let note_id = note.id;
+                                let locked = note.locked;
+                                let starred = note.starred;
+                                let archived = note.archived;
+                                let title = display_title(note);
+                                let (background, hover_background) = if is_selected {
+                                    (rgb(0xd6e4fb), rgb(0xc9dbf9))
+                                } else if is_active {
+                                    (rgb(0xdddddd), rgb(0xd4d4d4))
+                                } else {
+                                    (rgb(0xf0f0f0), rgb(0xe6e6e6))
+                                };
+
+                                div()
+                                    .id(SharedString::from(format!("note-row-{}", note_id)))
+                                    .flex()
+                                    .justify_between()
+                                    .items_center()
+                                    .px_2()
+                                    .when(compact, |row| row.py_0p5().text_sm())
+                                    .when(!compact, |row| row.py_1p5())
+                                    .border_b_1()
+                                    .border_color(rgb(0xE0E0E0))
+                                    .bg(background)
+                                    .hover(move |s| s.bg(hover_background))
+                                    .child(
+                                        div()
+                                            .flex_grow()
+                                            .overflow_hidden()
+                                            .font_weight(if is_active {
+                                                FontWeight::BOLD
+                                            } else {
+                                                FontWeight::NORMAL
+                                            })
+                                            .cursor_pointer()
+                                            .on_mouse_down(
+                                                MouseButton::Left,
+                                                cx.listener(
+                                                    move |view, event: &MouseDownEvent, window, cx| {
+                                                        if event.modifiers.platform {
+                                                            view.toggle_note_selection(note_id, cx);
+                                                            return;
+                                                        }
+                                                        view.selected_ids.clear();
+                                                        view.set_active_note(note_id, cx);
+                                                    },
+                                                ),
+                                            )
+                                            .on_mouse_down(
+                                                MouseButton::Right,
+                                                cx.listener(
+                                                    move |view, event: &MouseDownEvent, window, cx| {
+                                                        let mut menu = ContextMenu::new();
+                                                        menu.add_move_to_top_item("Move to Top", note_id);
+                                                        menu.add_move_to_bottom_item("Move to Bottom", note_id);
+                                                        menu.add_copy_note_item("Copy Note", note_id);
+                                                        menu.add_copy_content_item("Copy Content Only", note_id);
+                                                        menu.add_copy_link_item("Copy Link", note_id);
+                                                        menu.add_toggle_locked_item(if locked { "Unlock" } else { "Lock" }, note_id);
+                                                        menu.add_toggle_starred_item(if starred { "Unstar" } else { "Star" }, note_id);
+                                                        menu.add_toggle_archived_item(if archived { "Unarchive" } else { "Archive" }, note_id);
+                                                        menu.add_delete_item("Delete", note_id);
+
+                                                        let callback = Box::new(|action| {
+                                                            send_app_request(AppRequest::Menu(action))
+                                                        });
+
+                                                        menu.show_at_position(
+                                                            event.position.x.0 as f64,
+                                                            event.position.y.0 as f64,
+                                                            callback,
+                                                        );
+                                                    },
+                                                ),
+                                            )
+                                            .child(render_highlighted_title(&title, &query)),
+                                    )
+                                    .child(
+                                        div()
+                                            .pl_1()
+                                            .cursor_pointer()
+                                            .text_color(if starred {
+                                                rgb(0xd99a00)
+                                            } else {
+                                                rgb(0xbbbbbb)
+                                            })
+                                            .hover(|s| s.text_color(rgb(0xd99a00)))
+                                            .on_mouse_down(
+                                                MouseButton::Left,
+                                                cx.listener(
+                                                    move |view, _: &MouseDownEvent, _window, cx| {
+                                                        view.toggle_note_starred(note_id, cx);
+                                                        cx.stop_propagation();
+                                                    },
+                                                ),
+                                            )
+                                            .child(if starred { "★" } else { "☆" }),
+                                    )
+                            })
+                            .collect::<Vec<_>>(),
+                    ),
+            )
+            .child(self.render_trash_section(cx))
+            .child(self.render_sidebar_footer(note_count, cx))
+    }
+
+    fn render_sidebar_footer(&self, note_count: usize, cx: &mut Context<Self>) -> impl IntoElement {
+        div()
+            .flex()
+            .justify_between()
+            .items_center()
+            .p_2()
+            .border_t_1()
+            .border_color(rgb(0xE0E0E0))
+            .text_xs()
+            .text_color(rgb(0x888888))
+            .child(if note_count == 1 {
+                "1 note".to_string()
+            } else {
+                format!("{} notes", note_count)
+            })
+            .child(
+                div()
+                    .text_sm()
+                    .cursor_pointer()
+                    .hover(|s| s.text_color(rgb(0x444444)))
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|view, _: &MouseDownEvent, _window, cx| {
+                            view.toggle_settings(cx);
+                        }),
+                    )
+                    .child("⚙"),
+            )
+    }
+
+    fn render_search_field(&self, match_count: usize, cx: &mut Context<Self>) -> impl IntoElement {
+        let focus_handle = self.search_editor.read(cx).focus_handle.clone();
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_1()
+            .px_2()
+            .child(
+                div()
+                    .px_2()
+                    .py_1()
+                    .text_sm()
+                    .bg(rgb(0xffffff))
+                    .border_1()
+                    .border_color(rgb(0xE0E0E0))
+                    .rounded_md()
+                    .on_mouse_down(MouseButton::Left, move |_, window, _cx| {
+                        focus_handle.focus(window);
+                    })
+                    .on_key_down(cx.listener(Self::handle_search_key_down))
+                    .child(self.search_editor.clone()),
+            )
+            .when(!self.search_query.is_empty() && match_count > 0, |this| {
+                this.child(
+                    div()
+                        .text_xs()
+                        .text_color(rgb(0x888888))
+                        .child(if match_count == 1 {
+                            "1 note".to_string()
+                        } else {
+                            format!("{} notes", match_count)
+                        }),
+                )
+            })
+    }
+
+    fn render_no_search_results(&self, query: &str, cx: &mut Context<Self>) -> impl IntoElement {
+        let title = query.to_string();
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_2()
+            .p_2()
+            .text_sm()
+            .child(
+                div()
+                    .text_color(rgb(0x666666))
+                    .child(format!("No notes match '{}'", query)),
+            )
+            .child(
+                div()
+                    .px_2()
+                    .py_1()
+                    .rounded_md()
+                    .bg(rgb(0x4287f5))
+                    .text_color(rgb(0xffffff))
+                    .cursor_pointer()
+                    .hover(|s| s.bg(rgb(0x3276e4)))
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |view, _: &MouseDownEvent, window, cx| {
+                            if view.create_note_titled(title.clone(), false, cx) {
+                                view.focus_editor(window, cx);
+                            }
+                        }),
+                    )
+                    .child(format!("Create note titled '{}'", query)),
+            )
+    }
+
+    fn render_trash_section(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let archived_count = self.notes.iter().filter(|note| note.archived).count();
+
+        div()
+            .flex()
+            .flex_col()
+            .mt_auto()
+            .p_2()
+            .border_t_1()
+            .border_color(rgb(0xE0E0E0))
+            .child(
+                div()
+                    .flex()
+                    .justify_between()
+                    .text_sm()
+                    .text_color(if self.show_archived {
+                        rgb(0x4287f5)
+                    } else {
+                        rgb(0x666666)
+                    })
+                    .cursor_pointer()
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|view, _: &MouseDownEvent, _window, cx| {
+                            view.toggle_archived_view(cx);
+                        }),
+                    )
+                    .child(format!("Archived ({})", archived_count))
+                    .child(if self.show_archived { "✓" } else { "" }),
+            )
+            .child(
+                div()
+                    .flex()
+                    .justify_between()
+                    .text_sm()
+                    .text_color(rgb(0x666666))
+                    .cursor_pointer()
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|view, _: &MouseDownEvent, _window, cx| {
+                            view.toggle_trash(cx);
+                        }),
+                    )
+                    .child(format!("Trash ({})", self.trashed_count))
+                    .child(if self.show_trash { "▾" } else { "▸" }),
+            )
+            .children(self.trashed_notes.iter().map(|note| {
+                let note_id = note.id;
+
+                div()
+                    .flex()
+                    .justify_between()
+                    .items_center()
+                    .gap_1()
+                    .text_sm()
+                    .text_color(rgb(0x888888))
+                    .child(div().flex_grow().truncate().child(display_title(note)))
+                    .child(
+                        div()
+                            .cursor_pointer()
+                            .hover(|s| s.text_color(rgb(0x4287f5)))
+                            .on_mouse_down(
+                                MouseButton::Left,
+                                cx.listener(move |view, _: &MouseDownEvent, _window, cx| {
+                                    view.restore_note(note_id, cx);
+                                }),
+                            )
+                            .child("Restore"),
+                    )
+                    .child(
+                        div()
+                            .cursor_pointer()
+                            .hover(|s| s.text_color(rgb(0xd93025)))
+                            .on_mouse_down(
+                                MouseButton::Left,
+                                cx.listener(move |view, _: &MouseDownEvent, _window, cx| {
+                                    view.delete_note_permanently(note_id, cx);
+                                }),
+                            )
+                            .child("×"),
+                    )
+            }))
+    }
+
+    fn render_shortcuts_overlay(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        div()
+            .absolute()
+            .top_0()
+            .left_0()
+            .size_full()
+            .flex()
+            .justify_center()
+            .items_center()
+            .bg(rgba(0x00000055))
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|view, _: &MouseDownEvent, _window, cx| {
+                    view.toggle_shortcuts(cx);
+                }),
+            )
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .gap_3()
+                    .p_4()
+                    .w(px(420.0))
+                    .bg(rgb(0xffffff))
+                    .rounded_lg()
+                    .border_1()
+                    .border_color(rgb(0xE0E0E0))
+                    .on_mouse_down(MouseButton::Left, |_, _, cx| cx.stop_propagation())
+                    .child(
+                        div()
+                            .flex()
+                            .justify_between()
+                            .items_center()
+                            .child(div().font_weight(FontWeight::BOLD).child("Keyboard Shortcuts"))
+                            .child(
+                                div()
+                                    .text_xs()
+                                    .text_color(rgb(0x888888))
+                                    .child("Esc to close"),
+                            ),
+                    )
+                    .children(shortcuts_by_category().into_iter().map(|(category, shortcuts)| {
+                        div()
+                            .flex()
+                            .flex_col()
+                            .gap_1()
+                            .child(
+                                div()
+                                    .text_xs()
+                                    .font_weight(FontWeight::BOLD)
+                                    .text_color(rgb(0x666666))
+                                    .child(category),
+                            )
+                            .children(shortcuts.into_iter().map(|shortcut| {
+                                div()
+                                    .flex()
+                                    .justify_between()
+                                    .text_sm()
+                                    .child(shortcut.description)
+                                    .child(
+                                        div()
+                                            .font_family("monospace")
+                                            .text_color(rgb(0x444444))
+                                            .child(shortcut.label),
+                                    )
+                            }))
+                    })),
+            )
+    }
+
+    fn render_settings_overlay(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let settings = &self.settings;
+        let compact = settings.sidebar_density == SidebarDensity::Compact;
+
+        div()
+            .absolute()
+            .top_0()
+            .left_0()
+            .size_full()
+            .flex()
+            .justify_center()
+            .items_center()
+            .bg(rgba(0x00000055))
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|view, _: &MouseDownEvent, _window, cx| {
+                    view.toggle_settings(cx);
+                }),
+            )
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .gap_2()
+                    .p_4()
+                    .w(px(360.0))
+                    .bg(rgb(0xffffff))
+                    .rounded_lg()
+                    .border_1()
+                    .border_color(rgb(0xE0E0E0))
+                    .on_mouse_down(MouseButton::Left, |_, _, cx| cx.stop_propagation())
+                    .child(
+                        div()
+                            .flex()
+                            .justify_between()
+                            .items_center()
+                            .child(div().font_weight(FontWeight::BOLD).child("Settings"))
+                            .child(
+                                div()
+                                    .text_xs()
+                                    .text_color(rgb(0x888888))
+                                    .child("Esc to close"),
+                            ),
+                    )
+                    .child(self.render_settings_row(
+                        "Text zoom (click to reset)",
+                        format!("{:.0}%", settings.editor_zoom * 100.0),
+                        |view, cx| view.set_editor_zoom(1.0, cx),
+                        cx,
+                    ))
+                    .child(self.render_settings_row(
+                        "Sidebar density",
+                        if compact { "Compact" } else { "Comfortable" }.to_string(),
+                        move |view, cx| {
+                            view.set_sidebar_density(
+                                if compact {
+                                    SidebarDensity::Comfortable
+                                } else {
+                                    SidebarDensity::Compact
+                                },
+                                cx,
+                            )
+                        },
+                        cx,
+                    ))
+                    .child(self.render_settings_row(
+                        "New notes",
+                        match settings.new_note_placement {
+                            NewNotePlacement::Top => "At the top",
+                            NewNotePlacement::Bottom => "At the bottom",
+                        }
+                        .to_string(),
+                        |view, cx| {
+                            let next = match view.settings.new_note_placement {
+                                NewNotePlacement::Top => NewNotePlacement::Bottom,
+                                NewNotePlacement::Bottom => NewNotePlacement::Top,
+                            };
+                            view.set_new_note_placement(next, cx)
+                        },
+                        cx,
+                    ))
+                    .child(self.render_settings_row(
+                        "\"+\" asks for a template",
+                        if settings.note_templates.is_empty() {
+                            "No templates".to_string()
+                        } else {
+                            on_off(settings.choose_template_for_new_notes)
+                        },
+                        |view, cx| {
+                            view.set_choose_template_for_new_notes(
+                                !view.settings.choose_template_for_new_notes,
+                                cx,
+                            )
+                        },
+                        cx,
+                    ))
+                    .child(self.render_settings_row(
+                        "Line spacing",
+                        format!("{}×", settings.line_spacing),
+                        |view, cx| {
+                            let next =
+                                next_preset(LINE_SPACING_PRESETS, view.settings.line_spacing);
+                            view.set_line_spacing(next, view.settings.paragraph_spacing, cx)
+                        },
+                        cx,
+                    ))
+                    .child(self.render_settings_row(
+                        "Paragraph spacing",
+                        format!("{} px", settings.paragraph_spacing),
+                        |view, cx| {
+                            let next = next_preset(
+                                PARAGRAPH_SPACING_PRESETS,
+                                view.settings.paragraph_spacing,
+                            );
+                            view.set_line_spacing(view.settings.line_spacing, next, cx)
+                        },
+                        cx,
+                    ))
+                    .child(self.render_settings_row(
+                        "Content padding",
+                        format!("{} px", settings.content_padding),
+                        |view, cx| {
+                            let next = next_preset(
+                                CONTENT_PADDING_PRESETS,
+                                view.settings.content_padding,
+                            );
+                            view.set_content_padding(next, cx)
+                        },
+                        cx,
+                    ))
+                    .child(self.render_settings_row(
+                        "Focus width",
+                        on_off(settings.focus_width.is_some()),
+                        |view, cx| {
+                            let width = match view.settings.focus_width {
+                                Some(_) => None,
+                                None => Settings::default().focus_width,
+                            };
+                            view.set_focus_width(width, cx)
+                        },
+                        cx,
+                    ))
+                    .child(self.render_settings_row(
+                        "Indentation",
+                        match settings.indent_style {
+                            IndentStyle::Spaces(width) => format!("{} spaces", width),
+                            IndentStyle::Tab => format!("Tabs ({} wide)", settings.tab_width),
+                            IndentStyle::Auto => "Match each note".to_string(),
+                        },
+                        |view, cx| {
+                            let next = match view.settings.indent_style {
+                                IndentStyle::Spaces(2) => IndentStyle::Spaces(4),
+                                IndentStyle::Spaces(_) => IndentStyle::Tab,
+                                IndentStyle::Tab => IndentStyle::Auto,
+                                IndentStyle::Auto => IndentStyle::Spaces(2),
+                            };
+                            view.set_indent_style(next, view.settings.tab_width, cx)
+                        },
+                        cx,
+                    ))
+                    .child(self.render_settings_row(
+                        "Auto-close brackets",
+                        on_off(settings.auto_close_brackets),
+                        |view, cx| {
+                            view.set_auto_close_brackets(!view.settings.auto_close_brackets, cx)
+                        },
+                        cx,
+                    ))
+                    .child(self.render_settings_row(
+                        "Paste URL over selection as link",
+                        on_off(settings.paste_url_as_link),
+                        |view, cx| view.set_paste_url_as_link(!view.settings.paste_url_as_link, cx),
+                        cx,
+                    ))
+                    .child(self.render_settings_row(
+                        "Ask before closing with unsaved edits",
+                        on_off(settings.confirm_close_with_unsaved_edits),
+                        |view, cx| {
+                            view.set_confirm_close_with_unsaved_edits(
+                                !view.settings.confirm_close_with_unsaved_edits,
+                                cx,
+                            )
+                        },
+                        cx,
+                    ))
+                    .child(self.render_settings_row(
+                        "Undo steps kept",
+                        settings.undo_limits.max_entries.to_string(),
+                        |view, cx| {
+                            let limits = view.settings.undo_limits;
+                            let next = UNDO_STEP_PRESETS
+                                .iter()
+                                .position(|preset| *preset == limits.max_entries)
+                                .map_or(0, |index| (index + 1) % UNDO_STEP_PRESETS.len());
+                            view.set_undo_limits(
+                                UndoLimits {
+                                    max_entries: UNDO_STEP_PRESETS[next],
+                                    ..limits
+                                },
+                                cx,
+                            )
+                        },
+                        cx,
+                    ))
+                    .child(self.render_settings_row(
+                        "Vim mode",
+                        on_off(settings.vim_mode),
+                        |view, cx| view.set_vim_mode(!view.settings.vim_mode, cx),
+                        cx,
+                    ))
+                    .child(self.render_settings_row(
+                        "Title from first line",
+                        on_off(settings.auto_title_from_content),
+                        |view, cx| {
+                            view.set_auto_title_from_content(
+                                !view.settings.auto_title_from_content,
+                                cx,
+                            )
+                        },
+                        cx,
+                    ))
+                    .child(self.render_settings_row(
+                        "Date format",
+                        format_timestamp(1_700_000_000, &settings.date_format),
+                        |view, cx| {
+                            let next = DATE_FORMAT_PRESETS
+                                .iter()
+                                .position(|preset| *preset == view.settings.date_format)
+                                .map_or(0, |index| (index + 1) % DATE_FORMAT_PRESETS.len());
+                            view.set_date_format(DATE_FORMAT_PRESETS[next], cx)
+                        },
+                        cx,
+                    ))
+                    .child(self.render_settings_row(
+                        "Discard empty untitled notes",
+                        on_off(settings.discard_empty_untitled_notes),
+                        |view, cx| {
+                            view.set_discard_empty_untitled_notes(
+                                !view.settings.discard_empty_untitled_notes,
+                                cx,
+                            );
+                            cx.notify();
+                        },
+                        cx,
+                    ))
+                    .children(
+                        self.save_transforms
+                            .iter()
+                            .map(|transform| {
+                                let id = transform.id;
+                                self.render_settings_row(
+                                    transform.label,
+                                    on_off(settings.save_transforms.iter().any(|on| on == id)),
+                                    move |view, cx| view.toggle_save_transform(id, cx),
+                                    cx,
+                                )
+                            })
+                            .collect::<Vec<_>>(),
+                    )
+                    .child(self.render_settings_row(
+                        "Export line endings",
+                        match settings.export_line_ending {
+                            LineEnding::Lf => "LF",
+                            LineEnding::CrLf => "CRLF",
+                            LineEnding::Platform => "Platform",
+                        }
+                        .to_string(),
+                        |view, cx| {
+                            let next = match view.settings.export_line_ending {
+                                LineEnding::Lf => LineEnding::CrLf,
+                                LineEnding::CrLf => LineEnding::Platform,
+                                LineEnding::Platform => LineEnding::Lf,
+                            };
+                            view.set_export_line_ending(next, cx);
+                        },
+                        cx,
+                    ))
+                    .child(self.render_settings_row(
+                        "Database size",
+                        self.database_size
+                            .map_or("Compact…".to_string(), |size| {
+                                format!("{} · Compact…", format_size(size))
+                            }),
+                        |view, cx| view.compact_database(cx),
+                        cx,
+                    ))
+                    .child(self.render_settings_row(
+                        "Duplicate notes",
+                        "Find…".to_string(),
+                        |view, cx| view.review_duplicate_notes(cx),
+                        cx,
+                    )),
+            )
+    }
+
+    fn render_duplicates_overlay(&self, cx: &mut Context<Self>) -> Option<impl IntoElement> {
+        let groups = self.duplicate_groups.as_ref()?;
+
+        Some(
+            div()
+                .absolute()
+                .top_0()
+                .left_0()
+                .size_full()
+                .flex()
+                .justify_center()
+                .items_center()
+                .bg(rgba(0x00000055))
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(|view, _: &MouseDownEvent, _window, cx| {
+                        view.close_duplicate_review(cx);
+                    }),
+                )
+                .child(
+                    div()
+                        .flex()
+                        .flex_col()
+                        .gap_2()
+                        .p_4()
+                        .w(px(400.0))
+                        .bg(rgb(0xffffff))
+                        .rounded_lg()
+                        .border_1()
+                        .border_color(rgb(0xE0E0E0))
+                        .on_mouse_down(MouseButton::Left, |_, _, cx| cx.stop_propagation())
+                        .child(
+                            div()
+                                .flex()
+                                .justify_between()
+                                .items_center()
+                                .child(div().font_weight(FontWeight::BOLD).child("Duplicate Notes"))
+                                .child(
+                                    div()
+                                        .text_xs()
+                                        .text_color(rgb(0x888888))
+                                        .child("Esc to close"),
+                                ),
+                        )
+                        .when(groups.is_empty(), |this| {
+                            this.child(
+                                div()
+                                    .text_sm()
+                                    .text_color(rgb(0x666666))
+                                    .child("No duplicate notes found"),
+                            )
+                        })
+                        .children(groups.iter().enumerate().map(|(index, group)| {
+                            let title = self
+                                .notes
+                                .iter()
+                                .find(|note| note.id == group[0])
+                                .map(display_title)
+                                .unwrap_or_default();
+
+                            div()
+                                .flex()
+                                .justify_between()
+                                .items_center()
+                                .gap_2()
+                                .text_sm()
+                                .child(
+                                    div()
+                                        .flex_grow()
+                                        .overflow_hidden()
+                                        .child(format!("{} ({} copies)", title, group.len())),
+                                )
+                                .child(
+                                    div()
+                                        .cursor_pointer()
+                                        .text_color(rgb(0x666666))
+                                        .hover(|s| s.text_color(rgb(0xd93025)))
+                                        .on_mouse_down(
+                                            MouseButton::Left,
+                                            cx.listener(
+                                                move |view, _: &MouseDownEvent, _window, cx| {
+                                                    view.delete_duplicate_extras(index, cx);
+                                                },
+                                            ),
+                                        )
+                                        .child("Delete extras"),
+                                )
+                        })),
+                ),
+        )
+    }
+
+    fn render_settings_row(
+        &self,
+        label: &'static str,
+        value: String,
+        on_click: impl Fn(&mut Self, &mut Context<Self>) + 'static,
+        cx: &mut Context<Self>,
+    ) -> impl IntoElement {
+        div()
+            .flex()
+            .justify_between()
+            .items_center()
+            .text_sm()
+            .child(label)
+            .child(
+                div()
+                    .px_2()
+                    .rounded_md()
+                    .text_color(rgb(0x4287f5))
+                    .cursor_pointer()
+                    .hover(|s| s.bg(rgb(0xf0f0f0)))
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |view, _: &MouseDownEvent, _window, cx| {
+                            on_click(view, cx);
+                        }),
+                    )
+                    .child(value),
+            )
+    }
+
+    fn render_onboarding_overlay(&self, cx: &mut Context<Self>) -> Option<impl IntoElement> {
+        let step = self.onboarding_step?;
+        let (heading, body) = ONBOARDING_STEPS[step];
+        let last_step = step + 1 == ONBOARDING_STEPS.len();
+        let progress = format!("{} of {}", step + 1, ONBOARDING_STEPS.len());
+
+        Some(
+            div()
+                .absolute()
+                .top_0()
+                .left_0()
+                .size_full()
+                .flex()
+                .justify_center()
+                .items_center()
+                .bg(rgba(0x00000055))
+                .on_mouse_down(MouseButton::Left, |_, _, cx| cx.stop_propagation())
+                .child(
+                    div()
+                        .flex()
+                        .flex_col()
+                        .gap_3()
+                        .p_4()
+                        .w(px(360.0))
+                        .bg(rgb(0xffffff))
+                        .rounded_lg()
+                        .border_1()
+                        .border_color(rgb(0xE0E0E0))
+                        .child(
+                            div()
+                                .flex()
+                                .justify_between()
+                                .items_center()
+                                .child(div().font_weight(FontWeight::BOLD).child("Welcome to Tap"))
+                                .child(
+                                    div()
+                                        .text_xs()
+                                        .text_color(rgb(0x888888))
+                                        .child(progress),
+                                ),
+                        )
+                        .child(div().text_sm().font_weight(FontWeight::BOLD).child(heading))
+                        .child(div().text_sm().text_color(rgb(0x444444)).child(body))
+                        .child(
+                            div()
+                                .flex()
+                                .justify_between()
+                                .items_center()
+                                .child(
+                                    div()
+                                        .text_sm()
+                                        .text_color(rgb(0x888888))
+                                        .cursor_pointer()
+                                        .hover(|s| s.text_color(rgb(0x444444)))
+                                        .on_mouse_down(
+                                            MouseButton::Left,
+                                            cx.listener(|view, _: &MouseDownEvent, _window, cx| {
+                                                view.finish_onboarding(cx);
+                                            }),
+                                        )
+                                        .child("Skip"),
+                                )
+                                .child(
+                                    div()
+                                        .px_3()
+                                        .py_1()
+                                        .bg(rgb(0x4287f5))
+                                        .text_color(rgb(0xffffff))
+                                        .rounded_md()
+                                        .cursor_pointer()
+                                        .hover(|s| s.bg(rgb(0x3276e4)))
+                                        .on_mouse_down(
+                                            MouseButton::Left,
+                                            cx.listener(|view, _: &MouseDownEvent, _window, cx| {
+                                                view.advance_onboarding(cx);
+                                            }),
+                                        )
+                                        .child(if last_step { "Got it" } else { "Next" }),
+                                ),
+                        ),
+                ),
+        )
+    }
+
+    fn render_busy_overlay(&self) -> Option<impl IntoElement> {
+        let task = self.busy.clone()?;
+
+        Some(
+            div()
+                .absolute()
+                .top_0()
+                .left_0()
+                .size_full()
+                .flex()
+                .justify_center()
+                .items_center()
+                .bg(rgba(0xffffff99))
+                .on_mouse_down(MouseButton::Left, |_, _, cx| cx.stop_propagation())
+                .child(
+                    div()
+                        .flex()
+                        .items_center()
+                        .gap_2()
+                        .px_4()
+                        .py_3()
+                        .bg(rgb(0xffffff))
+                        .border_1()
+                        .border_color(rgb(0xE0E0E0))
+                        .rounded_lg()
+                        .text_sm()
+                        .child(div().text_color(rgb(0x888888)).child("⏳"))
+                        .child(task),
+                ),
+        )
+    }
+
+    fn render_status_banner(&self, cx: &mut Context<Self>) -> Option<impl IntoElement> {
+        let message = self.status_message.clone()?;
+
+        Some(
+            div()
+                .absolute()
+                .bottom_4()
+                .left(px(216.0))
+                .flex()
+                .items_center()
+                .gap_2()
+                .px_3()
+                .py_2()
+                .max_w(px(400.0))
+                .bg(rgb(0xe8f0fe))
+                .text_color(rgb(0x1a3d7c))
+                .border_1()
+                .border_color(rgb(0xc2d5f5))
+                .rounded_md()
+                .text_sm()
+                .child(div().flex_grow().child(message))
+                .child(
+                    div()
+                        .cursor_pointer()
+                        .font_weight(FontWeight::BOLD)
+                        .on_mouse_down(
+                            MouseButton::Left,
+                            cx.listener(|view, _: &MouseDownEvent, _window, cx| {
+                                view.dismiss_status(cx);
+                            }),
+                        )
+                        .child("×"),
+                ),
+        )
+    }
+
+    fn render_error_banner(&self, cx: &mut Context<Self>) -> Option<impl IntoElement> {
+        let message = self.error_message.clone()?;
+
+        Some(
+            div()
+                .absolute()
+                .bottom_4()
+                .right_4()
+                .flex()
+                .items_center()
+                .gap_2()
+                .px_3()
+                .py_2()
+                .max_w(px(400.0))
+                .bg(rgb(0xfdecea))
+                .text_color(rgb(0x8a1c1c))
+                .border_1()
+                .border_color(rgb(0xf5c2c0))
+                .rounded_md()
+                .text_sm()
+                .child(div().flex_grow().child(message))
+                .child(
+                    div()
+                        .cursor_pointer()
+                        .font_weight(FontWeight::BOLD)
+                        .on_mouse_down(
+                            MouseButton::Left,
+                            cx.listener(|view, _: &MouseDownEvent, _window, cx| {
+                                view.dismiss_error(cx);
+                            }),
+                        )
+                        .child("×"),
+                ),
+        )
+    }
+
+    fn render_replace_input(
+        &self,
+        label: &'static str,
+        editor: &Entity<TitleEditor>,
+        cx: &mut Context<Self>,
+    ) -> impl IntoElement {
+        let focus_handle = editor.read(cx).focus_handle.clone();
+
+        div()
+            .flex()
+            .items_center()
+            .gap_2()
+            .child(div().w(px(60.0)).text_sm().child(label))
+            .child(
+                div()
+                    .flex_grow()
+                    .px_2()
+                    .py_1()
+                    .bg(rgb(0xffffff))
+                    .border_1()
+                    .border_color(rgb(0xE0E0E0))
+                    .rounded_md()
+                    .on_mouse_down(MouseButton::Left, move |_, window, _cx| {
+                        focus_handle.focus(window);
+                    })
+                    .child(editor.clone()),
+            )
+    }
+
+    fn render_replace_panel(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let case_sensitive = self.replace_case_sensitive;
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_2()
+            .p_4()
+            .w_full()
+            .bg(rgb(0xf5f5f5))
+            .border_b_1()
+            .border_color(rgb(0xE0E0E0))
+            .on_key_down(cx.listener(Self::handle_replace_key_down))
+            .child(self.render_replace_input("Find", &self.replace_find_editor, cx))
+            .child(self.render_replace_input("Replace", &self.replace_with_editor, cx))
+            .child(
+                div()
+                    .flex()
+                    .items_center()
+                    .gap_2()
+                    .child(
+                        div()
+                            .px_2()
+                            .py_1()
+                            .text_sm()
+                            .rounded_md()
+                            .cursor_pointer()
+                            .bg(if case_sensitive {
+                                rgb(0xdddddd)
+                            } else {
+                                rgb(0xf0f0f0)
+                            })
+                            .on_mouse_down(
+                                MouseButton::Left,
+                                cx.listener(|view, _: &MouseDownEvent, _window, cx| {
+                                    view.replace_case_sensitive = !view.replace_case_sensitive;
+                                    cx.notify();
+                                }),
+                            )
+                            .child("Match case"),
+                    )
+                    .child(
+                        div()
+                            .px_2()
+                            .py_1()
+                            .text_sm()
+                            .rounded_md()
+                            .cursor_pointer()
+                            .bg(rgb(0x4287f5))
+                            .text_color(rgb(0xffffff))
+                            .hover(|s| s.bg(rgb(0x3276e4)))
+                            .on_mouse_down(
+                                MouseButton::Left,
+                                cx.listener(|view, _: &MouseDownEvent, _window, cx| {
+                                    view.replace_in_all_notes(cx);
+                                }),
+                            )
+                            .child("Replace All"),
+                    )
+                    .children(
+                        self.replace_status
+                            .clone()
+                            .map(|status| div().text_sm().child(status)),
+                    ),
+            )
+    }
+
+    fn render_status_bar(&self) -> impl IntoElement {
+        let ((line, column), selected) = self.caret_status;
+        let mut label = format!("Ln {}, Col {}", line, column);
+        if selected > 0 {
+            label.push_str(&format!(" ({} selected)", selected));
+        }
+        let dates = self.get_active_note().map(|note| {
+            let format = &self.settings.date_format;
+            format!(
+                "Created {} · Edited {}",
+                format_timestamp(note.created_at, format),
+                format_timestamp(note.updated_at, format)
+            )
+        });
+
+        // Takes the status bar's own color, and fades out over the second
+        // half of its time unless motion is reduced.
+        let saved = self.saved_flash.map(|flash| {
+            let mark = div().child("✓ Saved");
+            if self.reduce_motion {
+                return mark.into_any_element();
+            }
+            mark.with_animation(
+                ElementId::NamedInteger("saved-flash".into(), flash as u64),
+                Animation::new(SAVED_FLASH_DURATION),
+                |mark, delta| mark.opacity((2.0 * (1.0 - delta)).min(1.0)),
+            )
+            .into_any_element()
+        });
+
+        div()
+            .flex()
+            .justify_between()
+            .gap_2()
+            .pt_1()
+            .border_t_1()
+            .border_color(rgb(0xE0E0E0))
+            .text_xs()
+            .font_family("monospace")
+            .text_color(rgb(0x888888))
+            .child(div().children(dates))
+            .child(div().flex().gap_3().children(saved).child(label))
+    }
+
+    fn render_tab_strip(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        div()
+            .flex()
+            .w_full()
+            .bg(rgb(0xf0f0f0))
+            .border_b_1()
+            .border_color(rgb(0xE0E0E0))
+            .children(self.open_note_ids.iter().filter_map(|&id| {
+                let note = self.notes.iter().find(|note| note.id == id)?;
+                let is_active = self.active_note_id == Some(id);
+
+                Some(
+                    div()
+                        .flex()
+                        .items_center()
+                        .gap_2()
+                        .px_3()
+                        .py_1()
+                        .max_w(px(180.0))
+                        .text_sm()
+                        .border_r_1()
+                        .border_color(rgb(0xE0E0E0))
+                        .bg(if is_active {
+                            rgb(0xffffff)
+                        } else {
+                            rgb(0xf0f0f0)
+                        })
+                        .cursor_pointer()
+                        .on_mouse_down(
+                            MouseButton::Left,
+                            cx.listener(move |view, _: &MouseDownEvent, _window, cx| {
+                                view.set_active_note(id, cx);
+                            }),
+                        )
+                        .on_mouse_down(
+                            MouseButton::Middle,
+                            cx.listener(move |view, _: &MouseDownEvent, _window, cx| {
+                                view.close_tab(id, cx);
+                            }),
+                        )
+                        .child(div().flex_grow().overflow_hidden().child(display_title(note)))
+                        .child(
+                            div()
+                                .text_color(rgb(0x888888))
+                                .hover(|s| s.text_color(rgb(0x444444)))
+                                .on_mouse_down(
+                                    MouseButton::Left,
+                                    cx.listener(move |view, _: &MouseDownEvent, _window, cx| {
+                                        view.close_tab(id, cx);
+                                        cx.stop_propagation();
+                                    }),
+                                )
+                                .child("×"),
+                        ),
+                )
+            }))
+    }
+
+    fn render_note_toolbar(
+        &self,
+        note: &Note,
+        locked: bool,
+        cx: &mut Context<Self>,
+    ) -> impl IntoElement {
+        let toolbar_link = |label: &'static str| {
+            div()
+                .text_xs()
+                .text_color(rgb(0x888888))
+                .cursor_pointer()
+                .hover(|s| s.text_color(rgb(0x444444)))
+                .child(label)
+        };
+
+        div()
+            .flex()
+            .justify_end()
+            .gap_3()
+            .when(!locked, |this| {
+                this.child(if note.encrypted {
+                    toolbar_link("🔒 Remove encryption").on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|view, _: &MouseDownEvent, _window, cx| {
+                            view.remove_active_note_encryption(cx);
+                        }),
+                    )
+                } else {
+                    toolbar_link("Encrypt…").on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|view, _: &MouseDownEvent, window, cx| {
+                            view.toggle_encrypt_prompt(window, cx);
+                        }),
+                    )
+                })
+            })
+            .when(!locked, |this| {
+                this.child(
+                    toolbar_link(if self.settings.split_preview {
+                        "Hide preview"
+                    } else {
+                        "Preview"
+                    })
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|view, _: &MouseDownEvent, _window, cx| {
+                            view.toggle_split_preview(cx);
+                        }),
+                    ),
+                )
+            })
+            .child(
+                toolbar_link(if note.monospace {
+                    "Monospace"
+                } else {
+                    "Proportional"
+                })
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(|view, _: &MouseDownEvent, _window, cx| {
+                        view.toggle_active_note_font(cx);
+                    }),
+                ),
+            )
+            .child(
+                toolbar_link(if self.show_note_info {
+                    "Hide info"
+                } else {
+                    "Info"
+                })
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(|view, _: &MouseDownEvent, _window, cx| {
+                        view.toggle_note_info(cx);
+                    }),
+                ),
+            )
+    }
+
+    /// Counts are left out while the note is locked, as its text isn't readable
+    /// then.
+    fn render_note_info(&self, note: &Note, locked: bool, cx: &mut Context<Self>) -> gpui::Div {
+        let format = &self.settings.date_format;
+        let stats = (!locked).then(|| text_stats(&self.editor.read(cx).content));
+        let count = |count: fn(&TextStats) -> usize| {
+            stats
+                .as_ref()
+                .map_or("—".to_string(), |stats| count(stats).to_string())
+        };
+        let reading_time = if locked {
+            0
+        } else {
+            reading_time_minutes(
+                &self.editor.read(cx).content,
+                self.settings.reading_words_per_minute,
+            )
+        };
+        let reading_time = match reading_time {
+            0 => "—".to_string(),
+            minutes => format!("~{} min read", minutes),
+        };
+        let rows = [
+            ("Id", note.id.to_string()),
+            ("Created", format_timestamp(note.created_at, format)),
+            ("Modified", format_timestamp(note.updated_at, format)),
+            ("Words", count(|stats| stats.words)),
+            ("Reading time", reading_time),
+            ("Characters", count(|stats| stats.characters)),
+            ("Lines", count(|stats| stats.lines)),
+            ("Attachments", self.note_attachments.clone()),
+        ];
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_1()
+            .self_end()
+            .w(px(340.0))
+            .px_3()
+            .py_2()
+            .bg(rgb(0xfafafa))
+            .border_1()
+            .border_color(rgb(0xE0E0E0))
+            .rounded_md()
+            .text_xs()
+            .children(rows.into_iter().map(|(label, value)| {
+                div()
+                    .flex()
+                    .gap_2()
+                    .child(
+                        div()
+                            .w(px(80.0))
+                            .flex_none()
+                            .text_color(rgb(0x888888))
+                            .child(label),
+                    )
+                    .child(div().flex_grow().overflow_hidden().child(value))
+            }))
+    }
+
+    fn render_missing_note_prompt(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        div()
+            .flex()
+            .items_center()
+            .gap_3()
+            .px_3()
+            .py_2()
+            .bg(rgb(0xfff4e5))
+            .border_1()
+            .border_color(rgb(0xf5c26b))
+            .rounded_md()
+            .text_sm()
+            .child(
+                div()
+                    .flex_grow()
+                    .child("This note was deleted outside Tap. Edits are not being saved."),
+            )
+            .child(
+                div()
+                    .text_color(rgb(0x4287f5))
+                    .cursor_pointer()
+                    .hover(|s| s.text_color(rgb(0x2a6fd6)))
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|view, _: &MouseDownEvent, _window, cx| {
+                            view.recreate_missing_note(cx);
+                        }),
+                    )
+                    .child("Recreate"),
+            )
+            .child(
+                div()
+                    .text_color(rgb(0xcc3333))
+                    .cursor_pointer()
+                    .hover(|s| s.text_color(rgb(0x2a6fd6)))
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|view, _: &MouseDownEvent, _window, cx| {
+                            view.discard_missing_note(cx);
+                        }),
+                    )
+                    .child("Discard"),
+            )
+    }
 
+    fn render_unlock_prompt(&self, cx: &mut Context<Self>) -> impl IntoElement {
         div()
             .flex()
             .flex_col()
-            .w(px(200.0))
-            .h_full()
-            .bg(rgb(0xf0f0f0))
-            .px_2()
-            .border_r_1()
-            .rounded_lg()
-            .border_color(rgb(0xE0E0E0))
+            .gap_2()
+            .py_4()
+            .max_w(px(360.0))
+            .child(div().font_weight(FontWeight::BOLD).child("🔒 This note is encrypted"))
+            .child(
+                div()
+                    .on_key_down(cx.listener(Self::handle_passphrase_key_down))
+                    .child(self.render_replace_input("Passphrase", &self.passphrase_editor, cx)),
+            )
             .child(
-                div().flex().justify_end().items_center().p_2().child(
+                div()
+                    .px_3()
+                    .py_1()
+                    .bg(rgb(0x4287f5))
+                    .text_color(rgb(0xffffff))
+                    .rounded_md()
+                    .cursor_pointer()
+                    .hover(|s| s.bg(rgb(0x3276e4)))
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|view, _: &MouseDownEvent, window, cx| {
+                            view.submit_passphrase(window, cx);
+                        }),
+                    )
+                    .child("Unlock"),
+            )
+    }
+
+    /// Viewport height, scrollable distance and current scroll position of
+    /// the content area as of the last layout.
+    fn content_scroll_metrics(&self) -> (f32, f32, f32) {
+        (
+            self.content_scroll.bounds().size.height.0,
+            self.content_scroll.max_offset().height.0,
+            -self.content_scroll.offset().y.0,
+        )
+    }
+
+    /// Scrolls so the thumb's top lands `grab` above the pointer at `y`.
+    fn drag_scrollbar_to(&mut self, y: Pixels, grab: f32, cx: &mut Context<Self>) {
+        let (viewport, max_scroll, scroll_top) = self.content_scroll_metrics();
+        let Some((_, thumb_height)) = scrollbar_thumb(viewport, max_scroll, scroll_top) else {
+            return;
+        };
+
+        let thumb_top = (y - self.content_scroll.bounds().origin.y).0 - grab;
+        let top = scroll_top_for_thumb(thumb_top, thumb_height, viewport, max_scroll);
+        let offset = self.content_scroll.offset();
+        self.content_scroll.set_offset(point(offset.x, px(-top)));
+        cx.notify();
+    }
+
+    fn render_scrollbar(&self, cx: &mut Context<Self>) -> Option<impl IntoElement> {
+        let (viewport, max_scroll, scroll_top) = self.content_scroll_metrics();
+        let (thumb_top, thumb_height) = scrollbar_thumb(viewport, max_scroll, scroll_top)?;
+        let dragging = self.scrollbar_drag.is_some();
+
+        Some(
+            div()
+                .absolute()
+                .top_0()
+                .right_0()
+                .bottom_0()
+                .w(px(SCROLLBAR_WIDTH))
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |view, event: &MouseDownEvent, _window, cx| {
+                        let y = (event.position.y - view.content_scroll.bounds().origin.y).0;
+                        // Grabbing the thumb keeps it under the pointer;
+                        // clicking the track centers it there.
+                        let grab = if (thumb_top..thumb_top + thumb_height).contains(&y) {
+                            y - thumb_top
+                        } else {
+                            thumb_height / 2.
+                        };
+                        view.scrollbar_drag = Some(grab);
+                        view.drag_scrollbar_to(event.position.y, grab, cx);
+                        cx.stop_propagation();
+                    }),
+                )
+                .child(
                     div()
-                        .size(px(28.0))
-                        .flex()
-                        .justify_center()
-                        .items_center()
-                        .bg(rgb(0x4287f5))
-                        .text_color(rgb(0xffffff))
-                        .text_lg()
-                        .font_weight(FontWeight::BOLD)
+                        .absolute()
+                        .top(px(thumb_top))
+                        .h(px(thumb_height))
+                        .w_full()
                         .rounded_full()
-                        .cursor_pointer()
-                        .hover(|s| s.bg(rgb(0x3276e4)))
-                        .on_mouse_down(
-                            MouseButton::Left,
-                            cx.listener(|view, _: &MouseDownEvent, window, cx| {
-                                view.add_note(window, cx);
-                            }),
-                        )
-                        .child("+"),
+                        .bg(if dragging { rgba(0x00000066) } else { rgba(0x00000033) })
+                        .hover(|style| style.bg(rgba(0x00000066))),
                 ),
+        )
+    }
+
+    fn render_content(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        div()
+            .flex()
+            .flex_grow()
+            .h_full()
+            .child(self.render_editor_pane(cx))
+            .children(self.render_markdown_preview(cx))
+    }
+
+    fn render_editor_pane(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        div()
+            .relative()
+            .flex()
+            .flex_grow()
+            .h_full()
+            .on_mouse_move(cx.listener(|view, event: &MouseMoveEvent, _window, cx| {
+                if let Some(grab) = view.scrollbar_drag {
+                    view.drag_scrollbar_to(event.position.y, grab, cx);
+                }
+            }))
+            .on_mouse_up(
+                MouseButton::Left,
+                cx.listener(|view, _event: &MouseUpEvent, _window, cx| {
+                    if view.scrollbar_drag.take().is_some() {
+                        cx.notify();
+                    }
+                }),
             )
-            .child(
-                div().flex().flex_col().p_2().children(
-                    notes
-                        .iter()
-                        .map(|note| {
-                            let is_active = active_note_id == Some(note.id);
-                            let note_id = note.id;
+            .on_mouse_up_out(
+                MouseButton::Left,
+                cx.listener(|view, _event: &MouseUpEvent, _window, cx| {
+                    if view.scrollbar_drag.take().is_some() {
+                        cx.notify();
+                    }
+                }),
+            )
+            .child(self.render_scroll_content(cx))
+            .children(self.render_scrollbar(cx))
+    }
 
-                            div()
-                                .flex()
-                                .justify_between()
-                                .items_center()
-                                .bg(if is_active {
-                                    rgb(0xdddddd)
-                                } else {
-                                    rgb(0xf0f0f0)
-                                })
-                                .child(
-                                    div()
-                                        .flex_grow()
-                                        .font_weight(if is_active {
-                                            FontWeight::BOLD
-                                        } else {
-                                            FontWeight::NORMAL
-                                        })
-                                        .cursor_pointer()
-                                        .on_mouse_down(
-                                            MouseButton::Left,
-                                            cx.listener(
-                                                move |view, event: &MouseDownEvent, window, cx| {
-                                                    view.set_active_note(note_id, cx);
-                                                },
-                                            ),
-                                        )
-                                        .on_mouse_down(
-                                            MouseButton::Right,
-                                            cx.listener(
-                                                move |view, event: &MouseDownEvent, window, cx| {
-                                                    let mut menu = ContextMenu::new();
-                                                    menu.add_delete_item("Delete", note_id);
-
-                                                    
-                                                    let db_clone = view.db.clone();
-                                                    menu.set_direct_delete_callback(move |uuid| {
-                                                        println!("Executing direct delete for note: {}", uuid);
-                                                        if let Err(e) = db_clone.notes.delete_note(&uuid.to_string()) {
-                                                            println!("Direct delete failed: {}", e);
-                                                            return false;
-                                                        }
-                                                        println!("Note {} was deleted directly!", uuid);
-                                                        return true;
-                                                    });
-
-                                                    
-                                                    let callback = Box::new(move |action| {
-                                                        match action {
-                                                            MenuAction::Delete(delete_note_id) => {
-                                                                println!(
-                                                                    "Menu action: Delete note {}",
-                                                                    delete_note_id
-                                                                );
-                                                                
-                                                                
-                                                                if let Ok(mut guard) = NOTE_TO_DELETE.lock() {
-                                                                    *guard = Some(delete_note_id);
-                                                                    println!("Set note {} for deletion in the global mutex", delete_note_id);
-                                                                }
-                                                                
-                                                                
-                                                                unsafe {
-                                                                    let dispatch_queue = objc::class!(NSOperationQueue);
-                                                                    let main_queue: cocoa::base::id = msg_send![dispatch_queue, mainQueue];
-                                                                    let block = ConcreteBlock::new(|| {
-                                                                        println!("Attempting to force UI refresh after deletion signal");
-                                                                        
-                                                                        
-                                                                        let app: cocoa::base::id = msg_send![objc::class!(NSApplication), sharedApplication];
-                                                                        let _: () = msg_send![app, updateWindows];
-                                                                        
-                                                                    }).copy();
-                                                                    let _: () = msg_send![main_queue, addOperationWithBlock:block];
-                                                                }
-                                                            }
-                                                        }
-                                                    });
+    fn scroll_preview_to_match(&self, cx: &App) {
+        let Some(note_id) = self.active_note_id else {
+            return;
+        };
+        if !self.settings.split_preview || self.is_note_locked(note_id) {
+            return;
+        }
 
-                                                    menu.show_at_position(
-                                                        event.position.x.0 as f64,
-                                                        event.position.y.0 as f64,
-                                                        callback,
-                                                    );
-                                                },
-                                            ),
-                                        )
-                                        .child(note.title.clone()),
-                                )
-                        })
-                        .collect::<Vec<_>>(),
+        let content = self.editor.read(cx).content.clone();
+        let first_match = parse_markdown(&content).iter().position(|block| {
+            !query_match_ranges(&block.rendered_text(), &self.search_query).is_empty()
+        });
+        if let Some(index) = first_match {
+            self.preview_scroll.scroll_to_item(index);
+        }
+    }
+
+    fn render_markdown_preview(&self, cx: &mut Context<Self>) -> Option<impl IntoElement> {
+        let note_id = self.active_note_id?;
+        if !self.settings.split_preview || self.is_note_locked(note_id) {
+            return None;
+        }
+        let content = self.editor.read(cx).content.to_string();
+        let query = self.search_query.clone();
+
+        Some(
+            div()
+                .id("markdown-preview")
+                .flex()
+                .flex_col()
+                .gap_2()
+                .w_1_2()
+                .h_full()
+                .overflow_y_scroll()
+                .track_scroll(&self.preview_scroll)
+                .p(px(self.settings.content_padding))
+                .bg(rgb(0xffffff))
+                .border_l_1()
+                .border_color(rgb(0xE0E0E0))
+                .font_family(PROPORTIONAL_FONT_FAMILY)
+                .text_size(px(self.settings.editor_text_size()))
+                .children(
+                    parse_markdown(&content)
+                        .into_iter()
+                        .map(|block| render_markdown_block(block, &query)),
                 ),
-            )
+        )
     }
 
-    fn render_content(&self, cx: &mut Context<Self>) -> impl IntoElement {
+    fn render_scroll_content(&self, cx: &mut Context<Self>) -> impl IntoElement {
         let active_note = self.get_active_note().cloned();
+        let locked = active_note
+            .as_ref()
+            .is_some_and(|note| self.is_note_locked(note.id));
 
         div()
             .id("content-area")
@@ -1860,14 +6501,24 @@ impl NoteApp {
             .flex_grow()
             .h_full()
             .overflow_y_scroll()
+            .track_scroll(&self.content_scroll)
             .bg(rgb(0xffffff))
+            .when(self.show_replace_panel, |this| {
+                this.child(self.render_replace_panel(cx))
+            })
+            .when(!self.open_note_ids.is_empty(), |this| {
+                this.child(self.render_tab_strip(cx))
+            })
             .child(if let Some(note) = active_note {
                 div()
                     .flex()
                     .flex_col()
                     .gap_2()
-                    .p_4()
+                    .p(px(self.settings.content_padding))
                     .w_full()
+                    .when_some(self.settings.focus_width, |this, width| {
+                        this.max_w(px(width)).mx_auto()
+                    })
                     .child(if self.title_edit_mode {
                         div()
                             .flex()
@@ -1890,20 +6541,670 @@ impl NoteApp {
                                     editor_handle.focus(window);
                                 }),
                             )
-                            .child(note.title)
+                            .child(display_title(&note))
                     })
-                    .child(
-                        div()
-                            .id("editor-area")
-                            .w_full()
-                            .py_2()
-                            .font_family("monospace")
-                            .text_size(px(16.0))
-                            .line_height(px(LINE_HEIGHT))
-                            .child(self.editor.clone()),
+                    .when(
+                        self.missing_note.as_ref().map(|missing| missing.id) == Some(note.id),
+                        |this| this.child(self.render_missing_note_prompt(cx)),
                     )
+                    .child(self.render_note_toolbar(&note, locked, cx))
+                    .when(self.show_note_info, |this| {
+                        this.child(self.render_note_info(&note, locked, cx))
+                    })
+                    .when(self.show_encrypt_prompt && !locked, |this| {
+                        this.child(
+                            div()
+                                .on_key_down(cx.listener(Self::handle_passphrase_key_down))
+                                .child(self.render_replace_input(
+                                    "Passphrase",
+                                    &self.passphrase_editor,
+                                    cx,
+                                )),
+                        )
+                    })
+                    .when(locked, |this| this.child(self.render_unlock_prompt(cx)))
+                    .when(!locked, |this| {
+                        this.child(
+                            div()
+                                .id("editor-area")
+                                .w_full()
+                                .py_2()
+                                .font_family(if note.monospace {
+                                    "monospace"
+                                } else {
+                                    PROPORTIONAL_FONT_FAMILY
+                                })
+                                .text_size(px(self.settings.editor_text_size()))
+                                .line_height(px(self.settings.editor_line_height()))
+                                .child(self.editor.clone()),
+                        )
+                    })
+                    .when(self.settings.vim_mode && !locked, |this| {
+                        let mode_label = match self.editor.read(cx).edit_mode {
+                            EditMode::Normal => "NORMAL",
+                            EditMode::Insert => "INSERT",
+                        };
+                        this.child(
+                            div()
+                                .text_xs()
+                                .font_family("monospace")
+                                .text_color(rgb(0x888888))
+                                .child(mode_label),
+                        )
+                    })
+                    .when(!locked, |this| this.child(self.render_status_bar()))
             } else {
-                div().p_4().child("Select a note or create a new one")
+                self.render_no_active_note(cx)
+            })
+    }
+
+    fn render_template_menu(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let entries = std::iter::once((None, "Blank".to_string())).chain(
+            self.settings
+                .note_templates
+                .iter()
+                .enumerate()
+                .map(|(index, template)| (Some(index), template.name.clone())),
+        );
+
+        div()
+            .absolute()
+            .top(px(32.0))
+            .right_0()
+            .flex()
+            .flex_col()
+            .min_w(px(160.0))
+            .py_1()
+            .bg(rgb(0xffffff))
+            .border_1()
+            .border_color(rgb(0xE0E0E0))
+            .rounded_md()
+            .text_sm()
+            .on_mouse_down_out(cx.listener(|view, _: &MouseDownEvent, _window, cx| {
+                view.show_template_menu = false;
+                cx.notify();
+            }))
+            .children(entries.map(|(index, name)| {
+                div()
+                    .px_3()
+                    .py_1()
+                    .cursor_pointer()
+                    .hover(|s| s.bg(rgb(0xf0f0f0)))
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |view, _: &MouseDownEvent, window, cx| {
+                            view.add_note_from_template(index, window, cx);
+                            cx.stop_propagation();
+                        }),
+                    )
+                    .child(name)
+            }))
+    }
+
+    /// Shown when no note is open, e.g. after closing every tab: the most
+    /// recently edited notes, so there's a way forward besides the sidebar.
+    fn render_no_active_note(&self, cx: &mut Context<Self>) -> gpui::Div {
+        let mut recent: Vec<&Note> =
+            self.notes.iter().filter(|note| self.is_listed(note)).collect();
+        if recent.is_empty() {
+            return div().p_4().child("Create a note to get started");
+        }
+        recent.sort_by_key(|note| std::cmp::Reverse(note.updated_at));
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_2()
+            .p_4()
+            .child(div().text_color(rgb(0x666666)).child("No note is open. Recent notes:"))
+            .children(recent.into_iter().take(RECENT_NOTES_SHOWN).map(|note| {
+                let note_id = note.id;
+                div()
+                    .text_color(rgb(0x4287f5))
+                    .cursor_pointer()
+                    .hover(|s| s.text_color(rgb(0x2a6fd6)))
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |view, _: &MouseDownEvent, _window, cx| {
+                            view.set_active_note(note_id, cx);
+                        }),
+                    )
+                    .child(display_title(note))
+            }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grapheme_column_counts_clusters_from_line_start() {
+        let text = "ab\ne\u{301}x";
+        assert_eq!(grapheme_column(text, 0, 2), 2);
+        assert_eq!(grapheme_column(text, 3, 3), 0);
+        assert_eq!(grapheme_column(text, 3, 6), 1);
+        assert_eq!(grapheme_column(text, 3, 7), 2);
+    }
+
+    #[test]
+    fn search_matches_title_and_plaintext_content() {
+        let mut note = Note::new("Groceries".to_string());
+        note.content = "Milk and EGGS".to_string();
+
+        assert!(note_matches_query(&note, ""));
+        assert!(note_matches_query(&note, "grocer"));
+        assert!(note_matches_query(&note, "eggs"));
+        assert!(!note_matches_query(&note, "bread"));
+
+        note.encrypted = true;
+        assert!(!note_matches_query(&note, "eggs"));
+        assert!(note_matches_query(&note, "GROCERIES"));
+    }
+
+    #[test]
+    fn sizes_use_the_largest_fitting_unit() {
+        assert_eq!(format_size(900), "900 bytes");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(5 * 1024 * 1024), "5.0 MB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024 * 1024), "3072.0 GB");
+    }
+
+    #[test]
+    fn announcements_name_the_note_then_read_it() {
+        assert_eq!(
+            note_announcement("Groceries", Some((1, 4)), Some("  Milk\nEggs ")),
+            "Groceries, note 2 of 4. Milk\nEggs"
+        );
+        assert_eq!(note_announcement("Diary", None, None), "Diary. Locked");
+        assert_eq!(note_announcement("Blank", None, Some("\n")), "Blank. Empty note");
+
+        let long = "word ".repeat(1000);
+        let message = note_announcement("Long", None, Some(&long));
+        assert_eq!(message.chars().count(), "Long. ".len() + ANNOUNCED_CONTENT_CHARS);
+    }
+
+    #[test]
+    fn deleting_the_last_note_then_the_others_keeps_one_active() {
+        let ids: Vec<Uuid> = (0..3).map(|_| Uuid::new_v4()).collect();
+        let (a, b, c) = (ids[0], ids[1], ids[2]);
+
+        assert_eq!(active_after_removal(&[a, b, c], Some(c), c), Some(b));
+        assert_eq!(active_after_removal(&[a, b], Some(b), a), Some(b));
+        assert_eq!(active_after_removal(&[b], Some(b), b), None);
+
+        // With nothing open, removing a note still leaves one selected.
+        assert_eq!(active_after_removal(&[a, b, c], None, a), Some(b));
+        assert_eq!(active_after_removal(&[a, b], None, c), Some(a));
+    }
+
+    #[test]
+    fn list_items_continue_with_the_next_marker() {
+        assert_eq!(list_item_marker("- milk"), Some((2, "- ".to_string())));
+        assert_eq!(list_item_marker("  * eggs"), Some((4, "  * ".to_string())));
+        assert_eq!(list_item_marker("9. step"), Some((3, "10. ".to_string())));
+        assert_eq!(list_item_marker("- "), Some((2, "- ".to_string())));
+        assert_eq!(list_item_marker("-milk"), None);
+        assert_eq!(list_item_marker("2024.05 notes"), None);
+        assert_eq!(list_item_marker("plain text"), None);
+    }
+
+    #[test]
+    fn presets_cycle_and_unknown_values_restart() {
+        assert_eq!(next_preset(LINE_SPACING_PRESETS, 1.25), 1.5);
+        assert_eq!(next_preset(LINE_SPACING_PRESETS, 2.0), 1.25);
+        assert_eq!(next_preset(PARAGRAPH_SPACING_PRESETS, 0.0), 4.0);
+        assert_eq!(next_preset(PARAGRAPH_SPACING_PRESETS, 3.0), 0.0);
+    }
+
+    #[test]
+    fn pasting_a_url_over_a_selection_makes_a_link() {
+        assert_eq!(
+            markdown_link_for_paste("Tap", " https://example.com/tap\n"),
+            Some("[Tap](https://example.com/tap)".to_string())
+        );
+        assert_eq!(
+            markdown_link_for_paste("mail me", "mailto:me@example.com"),
+            Some("[mail me](mailto:me@example.com)".to_string())
+        );
+        assert_eq!(markdown_link_for_paste("", "https://example.com"), None);
+        assert_eq!(markdown_link_for_paste("Tap", "https://"), None);
+        assert_eq!(markdown_link_for_paste("Tap", "see https://example.com"), None);
+        assert_eq!(markdown_link_for_paste("Tap", "plain text"), None);
+    }
+
+    #[test]
+    fn rapid_switching_keeps_each_save_on_its_own_note() {
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut stored: HashMap<Uuid, String> = HashMap::new();
+        let save = |stored: &mut HashMap<Uuid, String>,
+                    current: &EditorBinding,
+                    captured: EditorBinding,
+                    text: &str| {
+            if current.is_current(&captured) {
+                stored.insert(captured.note_id.unwrap(), text.to_string());
+            }
+        };
+
+        let mut binding = EditorBinding::default();
+        let for_a = binding.rebind(Some(a));
+        save(&mut stored, &binding, for_a, "typed in A");
+
+        // B is selected while a save set up for A is still on its way.
+        binding.rebind(None);
+        save(&mut stored, &binding, for_a, "B's text");
+        let for_b = binding.rebind(Some(b));
+        save(&mut stored, &binding, for_a, "B's text");
+        save(&mut stored, &binding, for_b, "typed in B");
+
+        assert_eq!(stored[&a], "typed in A");
+        assert_eq!(stored[&b], "typed in B");
+
+        // Reopening A is a new load; the first one's save stays stale.
+        let for_a_again = binding.rebind(Some(a));
+        assert!(!binding.is_current(&for_a));
+        assert!(binding.is_current(&for_a_again));
+    }
+
+    #[test]
+    fn encrypted_notes_never_take_a_title_from_their_content() {
+        let mut note = Note::new("Untitled".to_string());
+        assert_eq!(
+            auto_title(&note, "  Bank details\nPIN 1234"),
+            Some("Bank details".to_string())
+        );
+        assert_eq!(auto_title(&note, "\nsecond line"), None);
+
+        note.encrypted = true;
+        assert_eq!(auto_title(&note, "Bank details\nPIN 1234"), None);
+
+        note.encrypted = false;
+        note.locked = true;
+        assert_eq!(auto_title(&note, "Bank details"), None);
+    }
+
+    #[test]
+    fn pasting_multibyte_text_is_left_alone() {
+        assert_eq!(markdown_link_for_paste("Tap", "Café ☕ is great"), None);
+        assert_eq!(markdown_link_for_paste("Tap", "日本語のテキスト"), None);
+        assert_eq!(
+            markdown_link_for_paste("Tap", "https://例え.jp"),
+            Some("[Tap](https://例え.jp)".to_string())
+        );
+    }
+
+    #[test]
+    fn two_line_notes_map_points_and_ranges_per_line() {
+        let text = "ab\ncde";
+        let glyphs = |line: &str| -> Vec<(usize, f32)> {
+            line.char_indices()
+                .map(|(index, _)| (index, index as f32 * 10.))
+                .collect()
+        };
+        let lines: Vec<(LineGeometry, usize)> = text
+            .split('\n')
+            .scan(0, |line_start, line| {
+                let start = *line_start;
+                *line_start += line.len() + 1;
+                let width = line.len() as f32 * 10.;
+                Some((LineGeometry::new(line, glyphs(line), width, 200.), start))
             })
+            .collect();
+
+        let index_for_point = |x: f32, y: f32| {
+            let line_index = line_index_for_y(y, 20.);
+            offset_for_line_click(text, line_index, |line| {
+                lines
+                    .get(line_index)
+                    .map_or(line.len(), |(geometry, _)| geometry.index_for_x(x))
+            })
+        };
+        assert_eq!(index_for_point(12., 5.), 1);
+        assert_eq!(index_for_point(12., 25.), 4);
+        assert_eq!(index_for_point(95., 25.), text.len());
+
+        let (geometry, line_start) = &lines[1];
+        assert_eq!(range_extent_on_line(geometry, *line_start, &(4..6)), (10., 30.));
+        assert_eq!(range_extent_on_line(geometry, *line_start, &(6..6)), (30., 30.));
+        let (geometry, line_start) = &lines[0];
+        assert_eq!(range_extent_on_line(geometry, *line_start, &(1..5)), (10., 20.));
+    }
+
+    #[test]
+    fn copied_notes_lead_with_a_title_heading() {
+        assert_eq!(note_as_markdown("Groceries", "milk\neggs"), "# Groceries\n\nmilk\neggs");
+        assert_eq!(note_as_markdown("Empty", ""), "# Empty\n\n");
+    }
+
+    #[test]
+    fn reloading_identical_content_keeps_the_selection() {
+        let text = "first line\nsecond line";
+        assert_eq!(selection_for_new_content(text, &(3..8), text), 3..8);
+        assert_eq!(selection_for_new_content(text, &(0..0), text), 0..0);
+        assert_eq!(selection_for_new_content(text, &(3..8), "other"), 5..5);
+        assert_eq!(selection_for_new_content("", &(0..0), "new"), 3..3);
+    }
+
+    #[test]
+    fn marked_text_underlines_its_selected_clause_more_heavily() {
+        // "ab" then marked "にほん" (bytes 2..11) with "ほ" (5..8) selected.
+        assert_eq!(
+            marked_text_spans(13, 2..11, 5..8, 1.),
+            vec![(2, None), (3, Some(1.)), (3, Some(2.)), (3, Some(1.)), (2, None)]
+        );
+        // A caret inside the marked text leaves one even underline.
+        assert_eq!(
+            marked_text_spans(11, 2..11, 11..11, 1.5),
+            vec![(2, None), (9, Some(1.5))]
+        );
+    }
+
+    #[test]
+    fn selection_fill_skips_marked_text() {
+        assert_eq!(selection_outside_marked(&(2..8), None), vec![2..8]);
+        assert_eq!(selection_outside_marked(&(5..8), Some(&(2..11))), Vec::<Range<usize>>::new());
+        assert_eq!(selection_outside_marked(&(0..14), Some(&(2..11))), vec![0..2, 11..14]);
+        assert_eq!(selection_outside_marked(&(0..4), Some(&(6..9))), vec![0..4]);
+    }
+
+    #[test]
+    fn title_matches_are_found_case_insensitively() {
+        assert_eq!(query_match_ranges("Banana bread", "an"), vec![1..3, 3..5]);
+        assert_eq!(query_match_ranges("Notes on NOTES", "notes"), vec![0..5, 9..14]);
+        assert_eq!(query_match_ranges("aaa", "aa"), vec![0..2]);
+        assert_eq!(query_match_ranges("Crème brûlée", "BRÛ"), vec![7..11]);
+        assert!(query_match_ranges("Groceries", "").is_empty());
+        assert!(query_match_ranges("Groceries", "xyz").is_empty());
+    }
+
+    #[test]
+    fn enter_during_composition_is_left_to_the_input_method() {
+        // "にほ" marked after "ab": Enter should commit it, not add a line.
+        let content = "abにほ";
+        let marked = 2..content.len();
+        assert!(is_composing(Some(&marked)));
+
+        // Committing clears the marked range, and an input method that
+        // cancels leaves an empty one; Enter is the editor's again.
+        assert!(!is_composing(None));
+        assert!(!is_composing(Some(&(2..2))));
+    }
+
+    #[test]
+    fn scrollbar_thumb_tracks_position_and_length() {
+        assert_eq!(scrollbar_thumb(400., 0., 0.), None);
+        assert_eq!(scrollbar_thumb(400., 400., 0.), Some((0., 200.)));
+        assert_eq!(scrollbar_thumb(400., 400., 200.), Some((100., 200.)));
+        assert_eq!(scrollbar_thumb(400., 400., 400.), Some((200., 200.)));
+        // Very long notes keep a thumb large enough to grab.
+        assert_eq!(scrollbar_thumb(400., 100_000., 0.), Some((0., MIN_SCROLLBAR_THUMB)));
+
+        assert_eq!(scroll_top_for_thumb(100., 200., 400., 400.), 200.);
+        assert_eq!(scroll_top_for_thumb(-50., 200., 400., 400.), 0.);
+        assert_eq!(scroll_top_for_thumb(500., 200., 400., 400.), 400.);
+    }
+
+    #[test]
+    fn external_reloads_clamp_the_selection_to_shorter_content() {
+        assert_eq!(clamp_selection(&(3..8), "first line"), 3..8);
+        assert_eq!(clamp_selection(&(3..20), "first"), 3..5);
+        assert_eq!(clamp_selection(&(12..20), "first"), 5..5);
+        assert_eq!(clamp_selection(&(4..4), ""), 0..0);
+        // "né" is three bytes; offset 2 falls inside the é.
+        assert_eq!(clamp_selection(&(2..9), "né"), 1..3);
+    }
+
+    #[test]
+    fn line_and_column_map_to_offsets_and_clamp() {
+        let text = "ab\n\ne\u{301}x";
+        assert_eq!(offset_for_line_column(text, 0, 0), 0);
+        assert_eq!(offset_for_line_column(text, 0, 2), 2);
+        assert_eq!(offset_for_line_column(text, 0, 9), 2);
+        assert_eq!(offset_for_line_column(text, 1, 0), 3);
+        assert_eq!(offset_for_line_column(text, 1, 4), 3);
+        assert_eq!(offset_for_line_column(text, 2, 1), 7);
+        assert_eq!(offset_for_line_column(text, 2, 2), text.len());
+        assert_eq!(offset_for_line_column(text, 99, 0), 4);
+        assert_eq!(offset_for_line_column("", 3, 3), 0);
+
+        for line in 0..3 {
+            for column in 0..3 {
+                let offset = offset_for_line_column(text, line, column);
+                let line_start = offset_for_line_column(text, line, 0);
+                assert_eq!(line_index_at(text, offset), line);
+                assert!(grapheme_column(text, line_start, offset) <= column);
+            }
+        }
+    }
+
+    #[test]
+    fn line_index_at_respects_newline_boundaries() {
+        let text = "ab\n\ncd";
+        assert_eq!(line_index_at(text, 0), 0);
+        assert_eq!(line_index_at(text, 2), 0);
+        assert_eq!(line_index_at(text, 3), 1);
+        assert_eq!(line_index_at(text, 4), 2);
+        assert_eq!(line_index_at(text, 6), 2);
+        assert_eq!(line_index_at(text, 99), 2);
+    }
+
+    #[test]
+    fn floor_char_boundary_backs_off_multibyte_characters() {
+        let text = "aé";
+        assert_eq!(floor_char_boundary(text, 2), 1);
+        assert_eq!(floor_char_boundary(text, 3), 3);
+        assert_eq!(floor_char_boundary(text, 10), 3);
+    }
+
+    #[test]
+    fn moving_up_keeps_the_column_and_stops_on_the_first_line() {
+        let text = "first\n\nlonger line";
+        assert_eq!(vertical_move_offset(text, 10, true), Some(6));
+        assert_eq!(vertical_move_offset(text, 6, true), Some(0));
+        assert_eq!(vertical_move_offset(text, 5, true), None);
+        assert_eq!(vertical_move_offset(text, 0, true), None);
+        assert_eq!(vertical_move_offset("abcdef\nab", 9, true), Some(2));
+    }
+
+    #[test]
+    fn moving_down_keeps_the_column_and_stops_on_the_last_line() {
+        let text = "first\n\nlonger line";
+        assert_eq!(vertical_move_offset(text, 3, false), Some(6));
+        assert_eq!(vertical_move_offset(text, 6, false), Some(7));
+        assert_eq!(vertical_move_offset(text, 5, false), Some(6));
+        assert_eq!(vertical_move_offset(text, 7, false), None);
+        assert_eq!(vertical_move_offset(text, text.len(), false), None);
+        assert_eq!(vertical_move_offset("longer\né", 1, false), Some(7));
+    }
+
+    #[test]
+    fn failed_create_leaves_the_list_and_selection_unchanged() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("notes.db");
+        let db = Database::new(&path).unwrap();
+        let mut notes = Vec::new();
+        let mut active_note_id = None;
+
+        let existing = Note::with_content("Existing".to_string(), "kept");
+        let saved = save_new_note(&db.notes, &notes, NewNotePlacement::Top, existing).unwrap();
+        list_new_note(&mut notes, &mut active_note_id, SIDEBAR_ORDER, saved);
+        assert_eq!(notes.len(), 1);
+        assert_eq!(active_note_id, Some(notes[0].id));
+
+        rusqlite::Connection::open(&path)
+            .unwrap()
+            .execute_batch(
+                "CREATE TRIGGER reject_inserts BEFORE INSERT ON notes
+                 BEGIN SELECT RAISE(ABORT, 'disk is full'); END",
+            )
+            .unwrap();
+
+        let before: Vec<Uuid> = notes.iter().map(|note| note.id).collect();
+        let selected = active_note_id;
+        let rejected = Note::with_content("Rejected".to_string(), "");
+        assert!(save_new_note(&db.notes, &notes, NewNotePlacement::Top, rejected).is_err());
+        assert_eq!(notes.iter().map(|note| note.id).collect::<Vec<_>>(), before);
+        assert_eq!(active_note_id, selected);
+    }
+
+    #[test]
+    fn home_toggles_between_the_indent_and_the_line_start() {
+        let text = "top\n    indented\n\tlast";
+        // From inside the text, then from the indent, then from the start.
+        assert_eq!(home_offset(text, 12), 8);
+        assert_eq!(home_offset(text, 8), 4);
+        assert_eq!(home_offset(text, 4), 8);
+        assert_eq!(home_offset(text, 6), 8);
+        assert_eq!(home_offset(text, text.len()), 18);
+        assert_eq!(home_offset(text, 18), 17);
+        // Unindented lines only have one stop.
+        assert_eq!(home_offset(text, 2), 0);
+        assert_eq!(home_offset(text, 0), 0);
+    }
+
+    #[test]
+    fn home_on_a_whitespace_only_line_stops_after_the_whitespace() {
+        let text = "a\n   \nb";
+        assert_eq!(home_offset(text, 3), 5);
+        assert_eq!(home_offset(text, 5), 2);
+        assert_eq!(home_offset(text, 2), 5);
+        assert_eq!(home_offset("", 0), 0);
+    }
+
+    #[test]
+    fn clicks_on_blank_lines_land_on_the_line_start() {
+        let text = "first\n\n\n\nlast";
+        let far_right = |line: &str| line.len() + 40;
+
+        assert_eq!(offset_for_line_click(text, 1, far_right), 6);
+        assert_eq!(offset_for_line_click(text, 2, far_right), 7);
+        assert_eq!(offset_for_line_click(text, 3, far_right), 8);
+        assert_eq!(offset_for_line_click(text, 3, |_| 0), 8);
+    }
+
+    #[test]
+    fn line_selection_takes_the_trailing_newline_except_on_the_last_line() {
+        let text = "first\n\nlast";
+        assert_eq!(&text[line_selection_range(text, 2)], "first\n");
+        assert_eq!(&text[line_selection_range(text, 5)], "first\n");
+        assert_eq!(&text[line_selection_range(text, 6)], "\n");
+        assert_eq!(&text[line_selection_range(text, 9)], "last");
+        assert_eq!(&text[line_selection_range(text, 99)], "last");
+        assert_eq!(line_selection_range("", 0), 0..0);
+
+        let mut moved = text.to_string();
+        let cut: String = moved.drain(line_selection_range(text, 0)).collect();
+        moved.insert_str(line_selection_range(&moved, 0).end, &cut);
+        assert_eq!(moved, "\nfirst\nlast");
+    }
+
+    #[test]
+    fn blank_titles_display_a_fallback() {
+        let mut note = Note::new(String::new());
+        assert_eq!(display_title(&note), FALLBACK_TITLE);
+        note.title = " \t ".to_string();
+        assert_eq!(display_title(&note), FALLBACK_TITLE);
+        assert_eq!(note.title, " \t ");
+        note.title = "Groceries".to_string();
+        assert_eq!(display_title(&note), "Groceries");
+    }
+
+    #[test]
+    fn status_item_lists_starred_notes_outside_the_archive() {
+        let mut notes: Vec<Note> = ["Plain", "Starred", "Archived", "Also starred"]
+            .into_iter()
+            .map(|title| Note::new(title.to_string()))
+            .collect();
+        notes[1].starred = true;
+        notes[2].starred = true;
+        notes[2].archived = true;
+        notes[3].starred = true;
+
+        let titles: Vec<String> = status_item_notes(&notes)
+            .into_iter()
+            .map(|(_, title)| title)
+            .collect();
+        assert_eq!(titles, ["Starred", "Also starred"]);
+    }
+
+    #[test]
+    fn text_stats_count_words_graphemes_and_lines() {
+        assert_eq!(
+            text_stats(""),
+            TextStats {
+                words: 0,
+                characters: 0,
+                lines: 1,
+            }
+        );
+        assert_eq!(
+            text_stats("Café au lait, don't stop\n\n- e\u{301}clair 👍🏽"),
+            TextStats {
+                words: 6,
+                characters: 36,
+                lines: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn reading_time_rounds_up_to_whole_minutes() {
+        let words = |count: usize| vec!["word"; count].join(" ");
+        assert_eq!(reading_time_minutes("", 200), 0);
+        assert_eq!(reading_time_minutes(" \n\t", 200), 0);
+        assert_eq!(reading_time_minutes("Hi", 200), 1);
+        assert_eq!(reading_time_minutes(&words(200), 200), 1);
+        assert_eq!(reading_time_minutes(&words(201), 200), 2);
+        assert_eq!(reading_time_minutes(&words(700), 200), 4);
+        assert_eq!(reading_time_minutes(&words(10), 0), 10);
+    }
+
+    #[test]
+    fn deleting_lines_takes_one_newline_with_them() {
+        let text = "first\nmiddle\nlast";
+        let delete = |selection: Range<usize>| {
+            let mut text = text.to_string();
+            text.replace_range(deleted_lines_range(&text, selection), "");
+            text
+        };
+
+        assert_eq!(delete(2..2), "middle\nlast");
+        assert_eq!(delete(8..8), "first\nlast");
+        assert_eq!(delete(15..15), "first\nmiddle");
+        assert_eq!(delete(3..9), "last");
+        assert_eq!(delete(3..text.len()), "");
+
+        // Ending at the start of "last" doesn't touch it.
+        assert_eq!(delete(6..13), "first\nlast");
+        assert_eq!(deleted_lines_range("only", 1..1), 0..4);
+        assert_eq!(deleted_lines_range("", 0..0), 0..0);
+    }
+
+    #[test]
+    fn splice_inserts_deletes_and_replaces() {
+        assert_eq!(splice("ac", 1..1, "b", 2), ("abc".to_string(), 2));
+        assert_eq!(splice("abc", 0..2, "", 0), ("c".to_string(), 0));
+        assert_eq!(splice("- item", 0..2, "1. ", 3), ("1. item".to_string(), 3));
+
+        // A caret past the end or inside a character is pulled back.
+        assert_eq!(splice("ab", 2..2, "é", 99), ("abé".to_string(), 4));
+        assert_eq!(splice("ab", 2..2, "é", 3), ("abé".to_string(), 2));
+    }
+
+    #[test]
+    fn clicks_on_text_lines_use_the_resolved_column() {
+        let text = "first\n\nlast";
+        assert_eq!(offset_for_line_click(text, 0, |_| 2), 2);
+        assert_eq!(offset_for_line_click(text, 2, |_| 3), 10);
+        assert_eq!(offset_for_line_click(text, 9, |_| 0), text.len());
+    }
+
+    #[test]
+    fn line_index_for_y_clamps_above_the_editor() {
+        assert_eq!(line_index_for_y(-5., 20.), 0);
+        assert_eq!(line_index_for_y(0., 20.), 0);
+        assert_eq!(line_index_for_y(19.9, 20.), 0);
+        assert_eq!(line_index_for_y(20., 20.), 1);
+        assert_eq!(line_index_for_y(65., 20.), 3);
     }
 }
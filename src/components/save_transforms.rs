@@ -0,0 +1,231 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+/// A rewrite of a note's text applied each time the note is saved. The
+/// editor keeps the text as typed; only the stored copy is rewritten.
+pub struct SaveTransform {
+    /// Listed in `Settings::save_transforms` to turn the transform on.
+    pub id: &'static str,
+    pub label: &'static str,
+    transform: Box<dyn Fn(&str) -> String + Send + Sync>,
+}
+
+/// Every transform the app knows about, in the order they run.
+pub struct SaveTransforms {
+    transforms: Vec<SaveTransform>,
+}
+
+impl SaveTransforms {
+    pub fn with_builtins() -> Self {
+        let mut transforms = Self {
+            transforms: Vec::new(),
+        };
+        transforms.register(
+            "trim-trailing-whitespace",
+            "Trim trailing whitespace",
+            trim_trailing_whitespace,
+        );
+        transforms.register(
+            "align-tables",
+            "Align markdown tables",
+            align_markdown_tables,
+        );
+        transforms
+    }
+
+    /// Adds a transform that runs after those registered before it.
+    /// Registering an id again replaces the earlier transform in place.
+    pub fn register(
+        &mut self,
+        id: &'static str,
+        label: &'static str,
+        transform: impl Fn(&str) -> String + Send + Sync + 'static,
+    ) {
+        let transform = SaveTransform {
+            id,
+            label,
+            transform: Box::new(transform),
+        };
+        match self
+            .transforms
+            .iter_mut()
+            .find(|existing| existing.id == id)
+        {
+            Some(existing) => *existing = transform,
+            None => self.transforms.push(transform),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SaveTransform> {
+        self.transforms.iter()
+    }
+
+    /// `content` passed through each transform whose id is in `enabled`.
+    pub fn apply(&self, content: &str, enabled: &[String]) -> String {
+        self.transforms
+            .iter()
+            .filter(|transform| enabled.iter().any(|id| id == transform.id))
+            .fold(content.to_string(), |text, transform| {
+                (transform.transform)(&text)
+            })
+    }
+}
+
+/// Drops spaces and tabs from the end of every line.
+pub fn trim_trailing_whitespace(text: &str) -> String {
+    text.split('\n')
+        .map(|line| line.trim_end_matches([' ', '\t']))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Pads the cells of every markdown table so its pipes line up. Only runs
+/// of `|` lines whose second line is a `---` delimiter row count as tables.
+pub fn align_markdown_tables(text: &str) -> String {
+    let lines: Vec<&str> = text.split('\n').collect();
+    let mut output: Vec<String> = Vec::with_capacity(lines.len());
+    let mut index = 0;
+
+    while index < lines.len() {
+        let rows = lines[index..]
+            .iter()
+            .take_while(|line| line.trim_start().starts_with('|'))
+            .count();
+        let delimiter = lines.get(index + 1).map(|line| table_cells(line));
+        if rows >= 2 && delimiter.as_deref().is_some_and(is_delimiter_row) {
+            output.extend(align_table(&lines[index..index + rows]));
+            index += rows;
+        } else {
+            output.push(lines[index].to_string());
+            index += 1;
+        }
+    }
+
+    output.join("\n")
+}
+
+fn align_table(lines: &[&str]) -> Vec<String> {
+    let indent = &lines[0][..lines[0].len() - lines[0].trim_start().len()];
+    let rows: Vec<Vec<String>> = lines.iter().map(|line| table_cells(line)).collect();
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+
+    let mut widths = vec![3; columns];
+    for (row_index, row) in rows.iter().enumerate() {
+        if row_index == 1 {
+            continue;
+        }
+        for (column, cell) in row.iter().enumerate() {
+            widths[column] = widths[column].max(cell.graphemes(true).count());
+        }
+    }
+
+    rows.iter()
+        .enumerate()
+        .map(|(row_index, row)| {
+            let cells: Vec<String> = (0..columns)
+                .map(|column| {
+                    let cell = row.get(column).map_or("", String::as_str);
+                    if row_index == 1 {
+                        delimiter_cell(cell, widths[column])
+                    } else {
+                        let padding = widths[column] - cell.graphemes(true).count();
+                        format!("{}{}", cell, " ".repeat(padding))
+                    }
+                })
+                .collect();
+            format!("{}| {} |", indent, cells.join(" | "))
+        })
+        .collect()
+}
+
+/// The trimmed cells of a `| a | b |` row. Pipes escaped as `\|` stay in
+/// their cell.
+fn table_cells(line: &str) -> Vec<String> {
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut escaped = false;
+    for c in line.trim().chars() {
+        if c == '|' && !escaped {
+            cells.push(cell.trim().to_string());
+            cell.clear();
+        } else {
+            cell.push(c);
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    cells.push(cell.trim().to_string());
+
+    // The split leaves an empty cell before the leading pipe and, when the
+    // row is closed, after the trailing one.
+    cells.remove(0);
+    if cells.last().is_some_and(String::is_empty) {
+        cells.pop();
+    }
+    cells
+}
+
+fn is_delimiter_row(cells: &[String]) -> bool {
+    !cells.is_empty()
+        && cells.iter().all(|cell| {
+            let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+            !dashes.is_empty() && dashes.chars().all(|c| c == '-')
+        })
+}
+
+fn delimiter_cell(cell: &str, width: usize) -> String {
+    let left = cell.starts_with(':');
+    let right = cell.len() > 1 && cell.ends_with(':');
+    let dashes = width - usize::from(left) - usize::from(right);
+    format!(
+        "{}{}{}",
+        if left { ":" } else { "" },
+        "-".repeat(dashes),
+        if right { ":" } else { "" }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_enabled_transforms_run_in_registration_order() {
+        let mut transforms = SaveTransforms::with_builtins();
+        transforms.register("shout", "Shout", |text| text.to_uppercase());
+        transforms.register("exclaim", "Exclaim", |text| format!("{}!", text));
+
+        let enabled = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+        assert_eq!(transforms.apply("hi  \nyou", &[]), "hi  \nyou");
+        assert_eq!(
+            transforms.apply(
+                "hi  \nyou",
+                &enabled(&["exclaim", "trim-trailing-whitespace"])
+            ),
+            "hi\nyou!"
+        );
+
+        transforms.register("shout", "Shout", |text| text.to_lowercase());
+        assert_eq!(
+            transforms.apply("HI", &enabled(&["shout", "exclaim"])),
+            "hi!"
+        );
+        assert_eq!(transforms.iter().count(), 4);
+    }
+
+    #[test]
+    fn tables_are_aligned_and_other_lines_left_alone() {
+        let text = "Prices:\n| Item | Cost |\n|:-|-:|\n| Bread, brown | 2 |\n|a \\| b|\n\n| not | a table |";
+        assert_eq!(
+            align_markdown_tables(text),
+            "Prices:\n\
+             | Item         | Cost |\n\
+             | :----------- | ---: |\n\
+             | Bread, brown | 2    |\n\
+             | a \\| b       |      |\n\
+             \n\
+             | not | a table |"
+        );
+
+        let aligned = align_markdown_tables(text);
+        assert_eq!(align_markdown_tables(&aligned), aligned);
+    }
+}
@@ -0,0 +1,181 @@
+use gpui::Keystroke;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShortcutAction {
+    NewNote,
+    RenameNote,
+    ToggleReplacePanel,
+    ToggleShortcuts,
+    CloseTab,
+    ZoomIn,
+    ZoomOut,
+    ResetZoom,
+    /// Selects the Nth note in the sidebar; 9 selects the last one.
+    JumpToNote(usize),
+    CopyNote,
+    InsertDate,
+    ToggleSidebarFocus,
+}
+
+pub struct Shortcut {
+    pub category: &'static str,
+    pub label: &'static str,
+    pub description: &'static str,
+    pub binding: Option<Binding>,
+}
+
+pub struct Binding {
+    pub action: ShortcutAction,
+    pub key: &'static str,
+    pub platform: bool,
+    pub shift: bool,
+    /// Only fires when no text field has focus, for keys that also type text.
+    pub outside_text: bool,
+}
+
+impl Binding {
+    fn matches(&self, keystroke: &Keystroke, text_focused: bool) -> bool {
+        keystroke.key == self.key
+            && keystroke.modifiers.platform == self.platform
+            && keystroke.modifiers.shift == self.shift
+            && !(self.outside_text && text_focused)
+    }
+}
+
+const fn app(
+    category: &'static str,
+    label: &'static str,
+    description: &'static str,
+    action: ShortcutAction,
+    key: &'static str,
+    platform: bool,
+    shift: bool,
+) -> Shortcut {
+    Shortcut {
+        category,
+        label,
+        description,
+        binding: Some(Binding {
+            action,
+            key,
+            platform,
+            shift,
+            outside_text: false,
+        }),
+    }
+}
+
+const fn listed(category: &'static str, label: &'static str, description: &'static str) -> Shortcut {
+    Shortcut {
+        category,
+        label,
+        description,
+        binding: None,
+    }
+}
+
+pub const SHORTCUTS: &[Shortcut] = &[
+    app("Notes", "⌘N", "New note", ShortcutAction::NewNote, "n", true, false),
+    app("Notes", "F2", "Rename note", ShortcutAction::RenameNote, "f2", false, false),
+    app("Notes", "⌘R", "Rename note", ShortcutAction::RenameNote, "r", true, false),
+    app("Notes", "⌘⇧C", "Copy note with its title", ShortcutAction::CopyNote, "c", true, true),
+    listed("Notes", "⌘⇧Space", "Quick capture a note from any app"),
+    listed("Notes", "⌘-click", "Select multiple notes"),
+    listed("Notes", "⌘1 … ⌘9", "Jump to a note in the sidebar (⌘9 = last)"),
+    app(
+        "Navigation",
+        "⌘⇧E",
+        "Switch focus between editor and sidebar",
+        ShortcutAction::ToggleSidebarFocus,
+        "e",
+        true,
+        true,
+    ),
+    listed("Navigation", "↑ ↓ / Enter", "In the sidebar: choose a note / edit it"),
+    app("Tabs", "⌘W", "Close tab", ShortcutAction::CloseTab, "w", true, false),
+    listed("Tabs", "Middle-click", "Close tab"),
+    app(
+        "Search",
+        "⌘⇧H",
+        "Find and replace in all notes",
+        ShortcutAction::ToggleReplacePanel,
+        "h",
+        true,
+        true,
+    ),
+    listed("Search", "Esc", "In the search field: clear it and return to the list"),
+    app("View", "⌘=", "Zoom in", ShortcutAction::ZoomIn, "=", true, false),
+    app("View", "⌘-", "Zoom out", ShortcutAction::ZoomOut, "-", true, false),
+    app("View", "⌘0", "Reset zoom", ShortcutAction::ResetZoom, "0", true, false),
+    listed("Navigation", "← → ↑ ↓", "Move the cursor"),
+    listed("Navigation", "Home", "Line start / first character"),
+    listed("Navigation", "End", "Line end"),
+    listed("Navigation", "⇧ + movement", "Extend the selection"),
+    listed("Editing", "⌘Z / ⌘⇧Z", "Undo / redo"),
+    listed("Editing", "⌘A", "Select all"),
+    listed("Editing", "Triple-click", "Select the whole line"),
+    listed("Editing", "⌘⇧K", "Delete the selected lines"),
+    listed("Editing", "⌘C", "Copy"),
+    listed("Editing", "⌘X", "Cut"),
+    listed("Editing", "⌘V", "Paste text or image"),
+    listed("Editing", "⇧Enter", "New line without continuing a list"),
+    listed("Editing", "Tab / ⇧Tab", "Indent / outdent"),
+    app("Editing", "⌘⇧D", "Insert date and time", ShortcutAction::InsertDate, "d", true, true),
+    listed("Vim (Normal mode)", "h j k l", "Move the cursor"),
+    listed("Vim (Normal mode)", "0 / $", "Line start / end"),
+    listed("Vim (Normal mode)", "i / a / o", "Insert before / after / below"),
+    listed("Vim (Normal mode)", "x / dd", "Delete character / line"),
+    listed("Vim (Normal mode)", "Esc", "Back to Normal mode"),
+    app(
+        "Help",
+        "⌘/",
+        "Show keyboard shortcuts",
+        ShortcutAction::ToggleShortcuts,
+        "/",
+        true,
+        false,
+    ),
+    Shortcut {
+        category: "Help",
+        label: "?",
+        description: "Show keyboard shortcuts",
+        binding: Some(Binding {
+            action: ShortcutAction::ToggleShortcuts,
+            key: "/",
+            platform: false,
+            shift: true,
+            outside_text: true,
+        }),
+    },
+];
+
+pub fn action_for_keystroke(keystroke: &Keystroke, text_focused: bool) -> Option<ShortcutAction> {
+    SHORTCUTS
+        .iter()
+        .filter_map(|shortcut| shortcut.binding.as_ref())
+        .find(|binding| binding.matches(keystroke, text_focused))
+        .map(|binding| binding.action)
+        .or_else(|| jump_to_note_action(keystroke, text_focused))
+}
+
+/// ⌘1 through ⌘9, listed as a single row in `SHORTCUTS`. Ignored while
+/// typing so they never reach a text field's own handling.
+fn jump_to_note_action(keystroke: &Keystroke, text_focused: bool) -> Option<ShortcutAction> {
+    if text_focused || !keystroke.modifiers.platform || keystroke.modifiers.shift {
+        return None;
+    }
+
+    let number: usize = keystroke.key.parse().ok()?;
+    (1..=9).contains(&number).then_some(ShortcutAction::JumpToNote(number))
+}
+
+pub fn shortcuts_by_category() -> Vec<(&'static str, Vec<&'static Shortcut>)> {
+    let mut groups: Vec<(&'static str, Vec<&'static Shortcut>)> = Vec::new();
+    for shortcut in SHORTCUTS {
+        match groups.iter_mut().find(|(category, _)| *category == shortcut.category) {
+            Some((_, shortcuts)) => shortcuts.push(shortcut),
+            None => groups.push((shortcut.category, vec![shortcut])),
+        }
+    }
+    groups
+}
@@ -0,0 +1,297 @@
+use crate::models::UndoLimits;
+use std::collections::VecDeque;
+use std::ops::Range;
+
+/// Edits at most this large can be folded into the entry before them, so
+/// typing or deleting a run of text is undone in one step.
+const COALESCE_MAX_BYTES: usize = 32;
+
+/// One change to the text: `removed` was replaced by `inserted` at `start`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Edit {
+    pub start: usize,
+    pub removed: String,
+    pub inserted: String,
+    /// Restored when the edit is undone.
+    pub selection_before: Range<usize>,
+}
+
+impl Edit {
+    fn bytes(&self) -> usize {
+        self.removed.len() + self.inserted.len()
+    }
+
+    fn inserted_range(&self) -> Range<usize> {
+        self.start..self.start + self.inserted.len()
+    }
+
+    /// Folds `next`, made right after this edit, into it when the two read
+    /// as one run of typing or deleting. Runs end after a newline.
+    fn coalesce(&mut self, next: &Edit) -> bool {
+        if next.bytes() > COALESCE_MAX_BYTES || self.inserted.ends_with('\n') {
+            return false;
+        }
+
+        let region = self.inserted_range();
+        if next.start >= region.start && next.start + next.removed.len() <= region.end {
+            let offset = next.start - region.start;
+            self.inserted
+                .replace_range(offset..offset + next.removed.len(), &next.inserted);
+            return true;
+        }
+
+        if self.inserted.is_empty() && next.inserted.is_empty() {
+            if next.start + next.removed.len() == self.start {
+                self.start = next.start;
+                self.removed.insert_str(0, &next.removed);
+                return true;
+            }
+            if next.start == self.start {
+                self.removed.push_str(&next.removed);
+                return true;
+            }
+        }
+        false
+    }
+}
+
+/// The edit that turns `old` into `new`, found by trimming their common
+/// prefix and suffix. `None` when the texts are equal.
+pub fn diff_edit(old: &str, new: &str, selection_before: Range<usize>) -> Option<Edit> {
+    if old == new {
+        return None;
+    }
+
+    let prefix: usize = old
+        .chars()
+        .zip(new.chars())
+        .take_while(|(a, b)| a == b)
+        .map(|(c, _)| c.len_utf8())
+        .sum();
+    let suffix: usize = old[prefix..]
+        .chars()
+        .rev()
+        .zip(new[prefix..].chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(c, _)| c.len_utf8())
+        .sum();
+
+    Some(Edit {
+        start: prefix,
+        removed: old[prefix..old.len() - suffix].to_string(),
+        inserted: new[prefix..new.len() - suffix].to_string(),
+        selection_before,
+    })
+}
+
+/// Undo and redo stacks for one text, bounded by `UndoLimits`.
+pub struct UndoHistory {
+    undo: VecDeque<Edit>,
+    redo: Vec<Edit>,
+    undo_bytes: usize,
+    limits: UndoLimits,
+}
+
+impl UndoHistory {
+    pub fn new(limits: UndoLimits) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            undo_bytes: 0,
+            limits,
+        }
+    }
+
+    pub fn set_limits(&mut self, limits: UndoLimits) {
+        self.limits = limits;
+        self.enforce_limits();
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.undo_bytes = 0;
+    }
+
+    pub fn undo_len(&self) -> usize {
+        self.undo.len()
+    }
+
+    pub fn undo_bytes(&self) -> usize {
+        self.undo_bytes
+    }
+
+    /// Records a new edit, which discards anything that could be redone.
+    /// An edit larger than `max_bytes` on its own can't be undone.
+    pub fn record(&mut self, edit: Edit) {
+        self.redo.clear();
+
+        if let Some(last) = self.undo.back_mut() {
+            let before = last.bytes();
+            if last.coalesce(&edit) {
+                self.undo_bytes = self.undo_bytes - before + last.bytes();
+                self.enforce_limits();
+                return;
+            }
+        }
+
+        self.undo_bytes += edit.bytes();
+        self.undo.push_back(edit);
+        self.enforce_limits();
+    }
+
+    /// Reverts the latest edit in `content`, returning the text and the
+    /// selection to show.
+    pub fn undo(&mut self, content: &str) -> Option<(String, Range<usize>)> {
+        let edit = self.undo.pop_back()?;
+        self.undo_bytes -= edit.bytes();
+
+        let range = edit.inserted_range();
+        if content.get(range.clone()) != Some(edit.inserted.as_str()) {
+            self.clear();
+            return None;
+        }
+
+        let text = format!(
+            "{}{}{}",
+            &content[..range.start],
+            edit.removed,
+            &content[range.end..]
+        );
+        let selection = edit.selection_before.clone();
+        self.redo.push(edit);
+        Some((text, selection))
+    }
+
+    /// Applies the latest undone edit again, leaving the caret after it.
+    pub fn redo(&mut self, content: &str) -> Option<(String, Range<usize>)> {
+        let edit = self.redo.pop()?;
+
+        let range = edit.start..edit.start + edit.removed.len();
+        if content.get(range.clone()) != Some(edit.removed.as_str()) {
+            self.clear();
+            return None;
+        }
+
+        let text = format!(
+            "{}{}{}",
+            &content[..range.start],
+            edit.inserted,
+            &content[range.end..]
+        );
+        let caret = edit.start + edit.inserted.len();
+        self.undo_bytes += edit.bytes();
+        self.undo.push_back(edit);
+        self.enforce_limits();
+        Some((text, caret..caret))
+    }
+
+    fn enforce_limits(&mut self) {
+        while self.undo.len() > self.limits.max_entries || self.undo_bytes > self.limits.max_bytes {
+            let Some(oldest) = self.undo.pop_front() else {
+                break;
+            };
+            self.undo_bytes -= oldest.bytes();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_text(history: &mut UndoHistory, content: &mut String, text: &str) {
+        for c in text.chars() {
+            let before = content.clone();
+            let caret = content.len();
+            content.push(c);
+            history.record(diff_edit(&before, content, caret..caret).unwrap());
+        }
+    }
+
+    #[test]
+    fn typing_coalesces_until_a_newline() {
+        let mut history = UndoHistory::new(UndoLimits::default());
+        let mut content = String::new();
+        type_text(&mut history, &mut content, "first line\nsecond line");
+        assert_eq!(history.undo_len(), 2);
+
+        let before = content.clone();
+        content.truncate(content.len() - 4);
+        history.record(diff_edit(&before, &content, before.len()..before.len()).unwrap());
+        assert_eq!(history.undo_len(), 2);
+
+        let (text, _) = history.undo(&content).unwrap();
+        assert_eq!(text, "first line\n");
+        let (text, selection) = history.undo(&text).unwrap();
+        assert_eq!(text, "");
+        assert_eq!(selection, 0..0);
+        assert!(history.undo(&text).is_none());
+
+        let (text, selection) = history.redo(&text).unwrap();
+        assert_eq!(text, "first line\n");
+        assert_eq!(selection, 11..11);
+    }
+
+    #[test]
+    fn many_edits_stay_within_the_limits() {
+        let limits = UndoLimits {
+            max_entries: 10,
+            max_bytes: 200,
+        };
+        let mut history = UndoHistory::new(limits);
+        let mut content = String::new();
+        for line in 0..100 {
+            type_text(
+                &mut history,
+                &mut content,
+                &format!("line {} of the note\n", line),
+            );
+            assert!(history.undo_len() <= limits.max_entries);
+            assert!(history.undo_bytes() <= limits.max_bytes);
+        }
+
+        assert_eq!(history.undo_len(), limits.max_entries);
+
+        history.set_limits(UndoLimits {
+            max_entries: 4,
+            max_bytes: 200,
+        });
+        assert_eq!(history.undo_len(), 4);
+
+        let mut text = content.clone();
+        let mut undone = 0;
+        while let Some((previous, _)) = history.undo(&text) {
+            text = previous;
+            undone += 1;
+        }
+        assert_eq!(undone, 4);
+        assert!(text.ends_with("line 95 of the note\n"));
+    }
+
+    #[test]
+    fn pastes_and_edits_elsewhere_start_a_new_entry() {
+        let mut history = UndoHistory::new(UndoLimits::default());
+        let pasted = "x".repeat(COALESCE_MAX_BYTES + 1);
+        history.record(diff_edit("ab", "abc", 2..2).unwrap());
+        history.record(diff_edit("abc", &format!("abc{}", pasted), 3..3).unwrap());
+        history.record(diff_edit("abc", "Zabc", 0..0).unwrap());
+        assert_eq!(history.undo_len(), 3);
+    }
+
+    #[test]
+    fn diff_finds_the_changed_span_on_char_boundaries() {
+        let edit = diff_edit("héllo", "hallo", 1..1).unwrap();
+        assert_eq!(
+            (edit.start, edit.removed.as_str(), edit.inserted.as_str()),
+            (1, "é", "a")
+        );
+        assert!(diff_edit("same", "same", 0..0).is_none());
+
+        let edit = diff_edit("aaa", "aaaa", 3..3).unwrap();
+        assert_eq!(
+            (edit.start, edit.removed.as_str(), edit.inserted.as_str()),
+            (3, "", "a")
+        );
+    }
+}
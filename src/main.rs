@@ -1,12 +1,23 @@
-use gpui::{App, Application, Bounds, WindowBounds, WindowOptions, prelude::*, px};
+use gpui::{
+    App, Application, Bounds, KeyBinding, Menu, MenuItem, SystemMenuType, WindowBounds,
+    WindowOptions, actions, prelude::*, px,
+};
 use std::fs;
 
 mod components;
 mod models;
 mod util;
 
-use components::NoteApp;
-use util::get_db_path;
+use components::{
+    ExportAllAsJsonLines, ExportSelectedAsJson, ExportSelectedAsMarkdown, ExportToPdf,
+    ImportJsonLines, NoteApp,
+};
+use util::{
+    AppRequest, get_db_path, macos_accessibility::observe_reduce_motion_changes,
+    macos_hotkey::register_quick_capture_hotkey, send_app_request,
+};
+
+actions!(tap, [Quit, Hide, HideOthers, ShowAll]);
 
 fn main() {
     // Print database path to help with debugging
@@ -23,14 +34,62 @@ fn main() {
         println!("Database file does not exist yet, will be created when app starts");
     }
 
-    Application::new().run(|cx: &mut App| {
+    let app = Application::new();
+    app.on_open_urls(|urls| {
+        for url in urls {
+            send_app_request(AppRequest::OpenUrl(url));
+        }
+    });
+
+    app.run(|cx: &mut App| {
+        cx.on_action(|_: &Quit, cx| cx.quit());
+        cx.on_action(|_: &Hide, cx| cx.hide());
+        cx.on_action(|_: &HideOthers, cx| cx.hide_other_apps());
+        cx.on_action(|_: &ShowAll, cx| cx.unhide_other_apps());
+        cx.bind_keys([
+            KeyBinding::new("cmd-q", Quit, None),
+            KeyBinding::new("cmd-h", Hide, None),
+            KeyBinding::new("alt-cmd-h", HideOthers, None),
+        ]);
+
+        cx.set_menus(vec![
+            Menu {
+                name: "Tap".into(),
+                items: vec![
+                    MenuItem::os_submenu("Services", SystemMenuType::Services),
+                    MenuItem::separator(),
+                    MenuItem::action("Hide Tap", Hide),
+                    MenuItem::action("Hide Others", HideOthers),
+                    MenuItem::action("Show All", ShowAll),
+                    MenuItem::separator(),
+                    MenuItem::action("Quit Tap", Quit),
+                ],
+            },
+            Menu {
+                name: "File".into(),
+                items: vec![
+                    MenuItem::action("Export to PDF…", ExportToPdf),
+                    MenuItem::action("Export Selected as Markdown…", ExportSelectedAsMarkdown),
+                    MenuItem::action("Export Selected as JSON…", ExportSelectedAsJson),
+                    MenuItem::action("Export All as JSON Lines", ExportAllAsJsonLines),
+                    MenuItem::action("Import JSON Lines", ImportJsonLines),
+                ],
+            },
+        ]);
+        register_quick_capture_hotkey();
+        observe_reduce_motion_changes();
+
         let bounds = Bounds::centered(None, gpui::size(px(1000.0), px(710.0)), cx);
         cx.open_window(
             WindowOptions {
                 window_bounds: Some(WindowBounds::Windowed(bounds)),
                 ..Default::default()
             },
-            |_window, cx| cx.new(|cx| NoteApp::new(cx)),
+            |window, cx| {
+                let app = cx.new(|cx| NoteApp::new(cx));
+                app.update(cx, |app, cx| app.guard_window_close(window, cx));
+                app
+            },
         )
         .unwrap();
 
@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Attachment {
+    pub id: Uuid,
+    pub note_id: Uuid,
+    pub mime: String,
+    pub path: String,
+    pub created_at: u64,
+}
+
+impl Attachment {
+    pub fn new(note_id: Uuid, mime: String, path: String) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        Self {
+            id: Uuid::new_v4(),
+            note_id,
+            mime,
+            path,
+            created_at: timestamp,
+        }
+    }
+}
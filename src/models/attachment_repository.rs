@@ -0,0 +1,60 @@
+use rusqlite::{Connection, Result};
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+use crate::models::Attachment;
+
+pub struct AttachmentRepository {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl AttachmentRepository {
+    pub fn new(connection: Arc<Mutex<Connection>>) -> Self {
+        Self { connection }
+    }
+
+    pub fn create_attachment(&self, attachment: &Attachment) -> Result<()> {
+        let connection = self.connection.lock().unwrap();
+        connection.execute(
+            "INSERT INTO attachments (id, note_id, mime, path, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            [
+                &attachment.id.to_string(),
+                &attachment.note_id.to_string(),
+                &attachment.mime,
+                &attachment.path,
+                &attachment.created_at.to_string(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn list_attachments(&self, note_id: Uuid) -> Result<Vec<Attachment>> {
+        let connection = self.connection.lock().unwrap();
+        let mut stmt = connection.prepare(
+            "SELECT id, mime, path, created_at FROM attachments WHERE note_id = ?1 ORDER BY created_at ASC",
+        )?;
+
+        let attachments_iter = stmt.query_map([note_id.to_string()], |row| {
+            let id: String = row.get(0)?;
+            let Ok(id) = Uuid::parse_str(&id) else {
+                eprintln!("Warning: Skipping attachment with invalid id {:?}", id);
+                return Ok(None);
+            };
+
+            Ok(Some(Attachment {
+                id,
+                note_id,
+                mime: row.get(1)?,
+                path: row.get(2)?,
+                created_at: row.get::<_, i64>(3)? as u64,
+            }))
+        })?;
+
+        let mut attachments = Vec::new();
+        for attachment_result in attachments_iter {
+            attachments.extend(attachment_result?);
+        }
+
+        Ok(attachments)
+    }
+}
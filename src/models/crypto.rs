@@ -0,0 +1,108 @@
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+use argon2::Argon2;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use std::fmt;
+
+const ENCRYPTED_PREFIX: &str = "tapenc:v1:";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+#[derive(Debug)]
+pub enum CryptoError {
+    KeyDerivation,
+    Malformed,
+    WrongPassphrase,
+}
+
+impl fmt::Display for CryptoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CryptoError::KeyDerivation => write!(f, "failed to derive encryption key"),
+            CryptoError::Malformed => write!(f, "encrypted content is malformed"),
+            CryptoError::WrongPassphrase => write!(f, "wrong passphrase"),
+        }
+    }
+}
+
+impl std::error::Error for CryptoError {}
+
+fn derive_cipher(passphrase: &str, salt: &[u8]) -> Result<Aes256Gcm, CryptoError> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|_| CryptoError::KeyDerivation)?;
+    Aes256Gcm::new_from_slice(&key).map_err(|_| CryptoError::KeyDerivation)
+}
+
+/// Encrypts `plaintext` with a key derived from `passphrase`. The salt and
+/// nonce are stored alongside the ciphertext, so each call yields new output.
+pub fn encrypt_content(passphrase: &str, plaintext: &str) -> Result<String, CryptoError> {
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut nonce);
+
+    let cipher = derive_cipher(passphrase, &salt)?;
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext.as_bytes())
+        .map_err(|_| CryptoError::KeyDerivation)?;
+
+    let mut payload = Vec::with_capacity(SALT_LEN + NONCE_LEN + ciphertext.len());
+    payload.extend_from_slice(&salt);
+    payload.extend_from_slice(&nonce);
+    payload.extend_from_slice(&ciphertext);
+
+    Ok(format!("{}{}", ENCRYPTED_PREFIX, STANDARD.encode(payload)))
+}
+
+pub fn decrypt_content(passphrase: &str, stored: &str) -> Result<String, CryptoError> {
+    let encoded = stored
+        .strip_prefix(ENCRYPTED_PREFIX)
+        .ok_or(CryptoError::Malformed)?;
+    let payload = STANDARD.decode(encoded).map_err(|_| CryptoError::Malformed)?;
+    if payload.len() < SALT_LEN + NONCE_LEN {
+        return Err(CryptoError::Malformed);
+    }
+
+    let (salt, rest) = payload.split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+    let cipher = derive_cipher(passphrase, salt)?;
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| CryptoError::WrongPassphrase)?;
+
+    String::from_utf8(plaintext).map_err(|_| CryptoError::Malformed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_with_the_right_passphrase() {
+        let stored = encrypt_content("hunter2", "secret plans").unwrap();
+        assert!(!stored.contains("secret plans"));
+        assert_eq!(decrypt_content("hunter2", &stored).unwrap(), "secret plans");
+    }
+
+    #[test]
+    fn rejects_the_wrong_passphrase() {
+        let stored = encrypt_content("hunter2", "secret plans").unwrap();
+        assert!(matches!(
+            decrypt_content("hunter3", &stored),
+            Err(CryptoError::WrongPassphrase)
+        ));
+    }
+
+    #[test]
+    fn rejects_plaintext_input() {
+        assert!(matches!(
+            decrypt_content("hunter2", "not encrypted"),
+            Err(CryptoError::Malformed)
+        ));
+    }
+}
@@ -1,12 +1,16 @@
 use rusqlite::{Connection, Result};
 use std::path::Path;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
 use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
 
-use crate::models::NoteRepository;
+use crate::models::{AttachmentRepository, NoteRepository};
 
 pub struct Database {
     connection: Arc<Mutex<Connection>>,
     pub notes: NoteRepository,
+    pub attachments: AttachmentRepository,
 }
 
 impl Database {
@@ -19,10 +23,19 @@ impl Database {
             println!("Absolute database path: {:?}", abs_path);
         }
 
-        let connection = Connection::open(path)?;
+        let mut connection = Connection::open(path)?;
 
+        // Only takes effect before the first table exists; older databases
+        // are converted when maintenance starts.
+        let _ = connection.execute_batch("PRAGMA auto_vacuum = INCREMENTAL");
         let _ = connection.execute("PRAGMA synchronous = FULL", []);
-        let _ = connection.execute("PRAGMA journal_mode = DELETE", []);
+        // Writes append to the write-ahead log instead of rewriting pages in
+        // place; `run_maintenance` folds the log back in periodically.
+        let journal_mode =
+            connection.query_row("PRAGMA journal_mode = WAL", [], |row| row.get::<_, String>(0));
+        if let Err(e) = journal_mode {
+            eprintln!("Failed to enable the write-ahead log: {}", e);
+        }
         let _ = connection.execute("PRAGMA foreign_keys = ON", []);
         println!("Database configured for reliability");
 
@@ -31,7 +44,15 @@ impl Database {
                 id TEXT PRIMARY KEY,
                 title TEXT NOT NULL,
                 content TEXT NOT NULL,
-                created_at INTEGER NOT NULL
+                created_at INTEGER NOT NULL,
+                deleted_at INTEGER,
+                monospace INTEGER NOT NULL DEFAULT 1,
+                updated_at INTEGER,
+                encrypted INTEGER NOT NULL DEFAULT 0,
+                sort_index INTEGER,
+                locked INTEGER NOT NULL DEFAULT 0,
+                starred INTEGER NOT NULL DEFAULT 0,
+                archived INTEGER NOT NULL DEFAULT 0
             )",
             [],
         ) {
@@ -39,10 +60,24 @@ impl Database {
             Err(e) => eprintln!("Error creating notes table: {}", e),
         }
 
-        match connection.execute("PRAGMA user_version = 1", []) {
-            Ok(_) => println!("Database is writable"),
+        match connection.execute(
+            "CREATE TABLE IF NOT EXISTS attachments (
+                id TEXT PRIMARY KEY,
+                note_id TEXT NOT NULL,
+                mime TEXT NOT NULL,
+                path TEXT NOT NULL,
+                created_at INTEGER NOT NULL
+            )",
+            [],
+        ) {
+            Ok(_) => println!("Attachments table created or already exists"),
+            Err(e) => eprintln!("Error creating attachments table: {}", e),
+        }
+
+        match run_migrations(&mut connection, MIGRATIONS) {
+            Ok(()) => println!("Database is writable and up to date"),
             Err(e) => {
-                println!("Warning: Database might not be writable: {}", e);
+                println!("Warning: Database migration failed: {}", e);
                 println!("Checking file permissions...");
 
                 if let Some(parent) = path_for_logging.parent() {
@@ -58,78 +93,602 @@ impl Database {
 
         let connection = Arc::new(Mutex::new(connection));
 
-        let db = Self {
+        Ok(Self {
             notes: NoteRepository::new(Arc::clone(&connection)),
+            attachments: AttachmentRepository::new(Arc::clone(&connection)),
             connection,
-        };
+        })
+    }
 
-        if let Err(e) = db.migrate_database() {
-            eprintln!("Warning: Database migration failed: {}", e);
+    /// Changes whenever another connection commits to the database. Writes
+    /// made through this connection leave it as it is.
+    pub fn data_version(&self) -> Result<i64> {
+        let connection = self.connection.lock().unwrap();
+        connection.query_row("PRAGMA data_version", [], |row| row.get(0))
+    }
+
+    /// Moves the write-ahead log back into the database file and hands
+    /// free pages back to the file system.
+    pub fn run_maintenance(&self) -> Result<()> {
+        let connection = self.connection.lock().unwrap();
+        if !connection.is_autocommit() {
+            println!("Skipping database maintenance inside a transaction");
+            return Ok(());
         }
+        // Each step of `incremental_vacuum` frees one page, so it has to be
+        // run to completion. The checkpoint after it moves the freed space
+        // out of the log as well.
+        {
+            let mut vacuum = connection.prepare("PRAGMA incremental_vacuum")?;
+            let mut steps = vacuum.query([])?;
+            while steps.next()?.is_some() {}
+        }
+        connection.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        Ok(())
+    }
 
-        Ok(db)
-    }
-
-    fn migrate_database(&self) -> Result<()> {
-        println!("Checking if database migration is needed...");
-
-        let needs_migration = {
-            let connection = self.connection.lock().unwrap();
-            let mut pragma_stmt = connection.prepare("PRAGMA table_info(notes)")?;
-            let columns = pragma_stmt.query_map([], |row| {
-                let name: String = row.get(1)?;
-                let type_name: String = row.get(2)?;
-                Ok((name, type_name))
-            })?;
-
-            let mut migration_needed = false;
-            for column_result in columns {
-                if let Ok((name, type_name)) = column_result {
-                    if name == "created_at" && type_name != "INTEGER" {
-                        println!(
-                            "Column 'created_at' is of type '{}', needs migration to INTEGER",
-                            type_name
-                        );
-                        migration_needed = true;
-                        break;
+    /// Runs `run_maintenance` every `interval` on its own thread until the
+    /// returned handle is dropped. It takes the connection lock like any
+    /// write, so it never runs in the middle of one. An older database is
+    /// switched to incremental vacuum first, as that rewrites the whole file.
+    pub fn start_maintenance(self: &Arc<Self>, interval: Duration) -> Maintenance {
+        let (stop, stopped) = mpsc::channel::<()>();
+        let db = Arc::clone(self);
+        thread::Builder::new()
+            .name("tap-db-maintenance".into())
+            .spawn(move || {
+                if let Err(e) = enable_incremental_vacuum(&db.connection.lock().unwrap()) {
+                    eprintln!("Failed to enable incremental vacuum: {}", e);
+                }
+                while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                    if let Err(e) = db.run_maintenance() {
+                        eprintln!("Database maintenance failed: {}", e);
                     }
                 }
+                println!("Database maintenance stopped");
+            })
+            .expect("Failed to spawn database maintenance thread");
+
+        Maintenance { _stop: stop }
+    }
+}
+
+/// Keeps the maintenance thread from `Database::start_maintenance` running;
+/// dropping it stops the thread.
+pub struct Maintenance {
+    _stop: Sender<()>,
+}
+
+/// Switches a database created without incremental auto-vacuum over to it.
+/// That takes a full `VACUUM`, so it only happens once per database.
+fn enable_incremental_vacuum(connection: &Connection) -> Result<()> {
+    const INCREMENTAL: i64 = 2;
+    let mode: i64 = connection.query_row("PRAGMA auto_vacuum", [], |row| row.get(0))?;
+    if mode == INCREMENTAL {
+        return Ok(());
+    }
+    println!("Rebuilding database to enable incremental vacuum");
+    connection.execute_batch("PRAGMA auto_vacuum = INCREMENTAL; VACUUM")
+}
+
+type Migration = fn(&Connection) -> Result<()>;
+
+/// Schema migrations in order: running `MIGRATIONS[n]` takes a database from
+/// `user_version` n to n + 1. Only ever append to this list.
+///
+/// Builds before this list existed stamped every database as version 1 and
+/// re-applied their repairs on each launch, so migrations check the current
+/// schema instead of assuming it.
+const MIGRATIONS: &[Migration] = &[
+    migrate_created_at_to_integer,
+    |connection| {
+        add_column_if_missing(connection, "deleted_at", "INTEGER")?;
+        Ok(())
+    },
+    |connection| {
+        if add_column_if_missing(connection, "updated_at", "INTEGER")? {
+            connection.execute(
+                "UPDATE notes SET updated_at = created_at WHERE updated_at IS NULL",
+                [],
+            )?;
+        }
+        Ok(())
+    },
+    |connection| {
+        add_column_if_missing(connection, "encrypted", "INTEGER NOT NULL DEFAULT 0")?;
+        Ok(())
+    },
+    |connection| {
+        if add_column_if_missing(connection, "sort_index", "INTEGER")? {
+            connection.execute(
+                "UPDATE notes SET sort_index = -created_at WHERE sort_index IS NULL",
+                [],
+            )?;
+        }
+        Ok(())
+    },
+    |connection| {
+        add_column_if_missing(connection, "monospace", "INTEGER NOT NULL DEFAULT 1")?;
+        Ok(())
+    },
+    |connection| {
+        add_column_if_missing(connection, "locked", "INTEGER NOT NULL DEFAULT 0")?;
+        Ok(())
+    },
+    |connection| {
+        add_column_if_missing(connection, "starred", "INTEGER NOT NULL DEFAULT 0")?;
+        Ok(())
+    },
+    |connection| {
+        add_column_if_missing(connection, "archived", "INTEGER NOT NULL DEFAULT 0")?;
+        Ok(())
+    },
+];
+
+/// Applies every migration past the database's `user_version`, each in its
+/// own transaction together with the version bump, so a failure leaves the
+/// database at the last version that fully applied.
+fn run_migrations(connection: &mut Connection, migrations: &[Migration]) -> Result<()> {
+    let version: i64 = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    let version = usize::try_from(version).unwrap_or(0);
+
+    if version > migrations.len() {
+        println!(
+            "Database is at version {}, newer than this build knows ({})",
+            version,
+            migrations.len()
+        );
+        return Ok(());
+    }
+    if version == migrations.len() {
+        println!("No database migration needed");
+        return Ok(());
+    }
+
+    for (index, migration) in migrations.iter().enumerate().skip(version) {
+        let target = index + 1;
+        println!("Migrating database to version {}", target);
+
+        let tx = connection.transaction()?;
+        migration(&tx)?;
+        tx.pragma_update(None, "user_version", target as i64)?;
+        tx.commit()?;
+    }
+
+    println!("Database migration completed successfully");
+    Ok(())
+}
+
+/// Adds `name` to the notes table unless it is already there. Returns
+/// whether the column was added.
+fn add_column_if_missing(connection: &Connection, name: &str, definition: &str) -> Result<bool> {
+    if notes_columns(connection)?
+        .iter()
+        .any(|column| column.name == name)
+    {
+        return Ok(false);
+    }
+
+    println!("Adding '{}' column to notes table", name);
+    connection.execute(
+        &format!(
+            "ALTER TABLE notes ADD COLUMN {} {}",
+            quote_identifier(name),
+            definition
+        ),
+        [],
+    )?;
+    Ok(true)
+}
+
+/// Rebuilds the notes table when `created_at` was stored as text, keeping
+/// every other column as it is.
+fn migrate_created_at_to_integer(connection: &Connection) -> Result<()> {
+    let columns = notes_columns(connection)?;
+    let Some(created_at) = columns.iter().find(|column| column.name == "created_at") else {
+        return Ok(());
+    };
+    if created_at.type_name == "INTEGER" {
+        return Ok(());
+    }
+    println!(
+        "Column 'created_at' is of type '{}', migrating to INTEGER",
+        created_at.type_name
+    );
+
+    // Rebuild with every existing column, so nothing added by a newer
+    // feature is lost; only created_at changes type.
+    let mut definitions: Vec<String> = columns
+        .iter()
+        .map(|column| {
+            let type_name = if column.name == "created_at" {
+                "INTEGER"
+            } else {
+                &column.type_name
+            };
+            let mut definition = format!("{} {}", quote_identifier(&column.name), type_name);
+            if column.not_null {
+                definition.push_str(" NOT NULL");
+            }
+            if let Some(default) = &column.default {
+                definition.push_str(&format!(" DEFAULT {}", default));
+            }
+            definition
+        })
+        .collect();
+
+    let mut primary_key: Vec<&ColumnInfo> = columns
+        .iter()
+        .filter(|column| column.primary_key_position > 0)
+        .collect();
+    primary_key.sort_by_key(|column| column.primary_key_position);
+    if !primary_key.is_empty() {
+        let key_columns: Vec<String> = primary_key
+            .iter()
+            .map(|column| quote_identifier(&column.name))
+            .collect();
+        definitions.push(format!("PRIMARY KEY ({})", key_columns.join(", ")));
+    }
+
+    let column_list: Vec<String> = columns
+        .iter()
+        .map(|column| quote_identifier(&column.name))
+        .collect();
+    let selected: Vec<String> = columns
+        .iter()
+        .map(|column| {
+            if column.name == "created_at" {
+                "CAST(created_at AS INTEGER)".to_string()
+            } else {
+                quote_identifier(&column.name)
             }
-            migration_needed
+        })
+        .collect();
+
+    connection.execute(
+        &format!("CREATE TABLE notes_new ({})", definitions.join(", ")),
+        [],
+    )?;
+    connection.execute(
+        &format!(
+            "INSERT INTO notes_new ({}) SELECT {} FROM notes",
+            column_list.join(", "),
+            selected.join(", ")
+        ),
+        [],
+    )?;
+    connection.execute("DROP TABLE notes", [])?;
+    connection.execute("ALTER TABLE notes_new RENAME TO notes", [])?;
+    Ok(())
+}
+
+struct ColumnInfo {
+    name: String,
+    type_name: String,
+    not_null: bool,
+    default: Option<String>,
+    /// 1-based position within the primary key, or 0 if not part of it.
+    primary_key_position: i64,
+}
+
+fn notes_columns(connection: &Connection) -> Result<Vec<ColumnInfo>> {
+    let mut stmt = connection.prepare("PRAGMA table_info(notes)")?;
+    let columns = stmt.query_map([], |row| {
+        Ok(ColumnInfo {
+            name: row.get(1)?,
+            type_name: row.get(2)?,
+            not_null: row.get::<_, i64>(3)? != 0,
+            default: row.get(4)?,
+            primary_key_position: row.get(5)?,
+        })
+    })?;
+    columns.collect()
+}
+
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::SortOrder;
+    use tempfile::TempDir;
+
+    fn column_type(db: &Database, column: &str) -> Option<String> {
+        let connection = db.connection.lock().unwrap();
+        let mut stmt = connection.prepare("PRAGMA table_info(notes)").unwrap();
+        stmt.query_map([], |row| Ok((row.get::<_, String>(1)?, row.get::<_, String>(2)?)))
+            .unwrap()
+            .filter_map(|column| column.ok())
+            .find(|(name, _)| name == column)
+            .map(|(_, type_name)| type_name)
+    }
+
+    fn user_version(connection: &Connection) -> usize {
+        connection
+            .query_row("PRAGMA user_version", [], |row| row.get::<_, i64>(0))
+            .unwrap() as usize
+    }
+
+    #[test]
+    fn migrates_text_created_at_to_integer() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("notes.db");
+
+        {
+            let connection = Connection::open(&path).unwrap();
+            connection
+                .execute(
+                    "CREATE TABLE notes (
+                        id TEXT PRIMARY KEY,
+                        title TEXT NOT NULL,
+                        content TEXT NOT NULL,
+                        created_at TEXT NOT NULL
+                    )",
+                    [],
+                )
+                .unwrap();
+            connection
+                .execute(
+                    "INSERT INTO notes VALUES ('4b1b3c3e-6b7a-4f0e-9a53-2f3c1d8e9a01', 'Old', 'body', '1700000000')",
+                    [],
+                )
+                .unwrap();
+        }
+
+        let db = Database::new(&path).unwrap();
+        assert_eq!(user_version(&db.connection.lock().unwrap()), MIGRATIONS.len());
+        assert_eq!(column_type(&db, "created_at").as_deref(), Some("INTEGER"));
+        assert!(column_type(&db, "deleted_at").is_some());
+        assert!(column_type(&db, "updated_at").is_some());
+        assert!(column_type(&db, "monospace").is_some());
+        assert!(column_type(&db, "encrypted").is_some());
+        assert!(column_type(&db, "sort_index").is_some());
+        assert!(column_type(&db, "locked").is_some());
+        assert!(column_type(&db, "starred").is_some());
+        assert!(column_type(&db, "archived").is_some());
+
+        let notes = db.notes.list_notes(SortOrder::Manual).unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].title, "Old");
+        assert_eq!(notes[0].created_at, 1_700_000_000);
+        assert_eq!(notes[0].updated_at, 1_700_000_000);
+        assert!(notes[0].monospace);
+        assert_eq!(notes[0].sort_index, -1_700_000_000);
+    }
+
+    #[test]
+    fn migration_keeps_columns_it_does_not_know_about() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("notes.db");
+
+        {
+            let connection = Connection::open(&path).unwrap();
+            connection
+                .execute(
+                    "CREATE TABLE notes (
+                        id TEXT PRIMARY KEY,
+                        title TEXT NOT NULL,
+                        content TEXT NOT NULL,
+                        created_at TEXT NOT NULL,
+                        deleted_at INTEGER,
+                        updated_at INTEGER,
+                        pinned INTEGER NOT NULL DEFAULT 0,
+                        color TEXT
+                    )",
+                    [],
+                )
+                .unwrap();
+            connection
+                .execute(
+                    "INSERT INTO notes VALUES ('4b1b3c3e-6b7a-4f0e-9a53-2f3c1d8e9a01', 'Kept', 'body', '1700000000', NULL, 1700000500, 1, 'red')",
+                    [],
+                )
+                .unwrap();
+        }
+
+        let db = Database::new(&path).unwrap();
+        assert_eq!(column_type(&db, "created_at").as_deref(), Some("INTEGER"));
+        assert_eq!(column_type(&db, "pinned").as_deref(), Some("INTEGER"));
+        assert_eq!(column_type(&db, "color").as_deref(), Some("TEXT"));
+
+        let (pinned, color): (i64, String) = db
+            .connection
+            .lock()
+            .unwrap()
+            .query_row("SELECT pinned, color FROM notes", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!(pinned, 1);
+        assert_eq!(color, "red");
+
+        let notes = db.notes.list_notes(SortOrder::Manual).unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].created_at, 1_700_000_000);
+        assert_eq!(notes[0].updated_at, 1_700_000_500);
+    }
+
+    #[test]
+    fn databases_stamped_by_older_builds_still_gain_missing_columns() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("notes.db");
+
+        {
+            let connection = Connection::open(&path).unwrap();
+            connection
+                .execute(
+                    "CREATE TABLE notes (
+                        id TEXT PRIMARY KEY,
+                        title TEXT NOT NULL,
+                        content TEXT NOT NULL,
+                        created_at INTEGER NOT NULL,
+                        deleted_at INTEGER
+                    )",
+                    [],
+                )
+                .unwrap();
+            connection.execute("PRAGMA user_version = 1", []).unwrap();
+        }
+
+        let db = Database::new(&path).unwrap();
+        assert_eq!(user_version(&db.connection.lock().unwrap()), MIGRATIONS.len());
+        assert!(column_type(&db, "updated_at").is_some());
+        assert!(column_type(&db, "encrypted").is_some());
+        assert!(column_type(&db, "sort_index").is_some());
+        assert!(column_type(&db, "monospace").is_some());
+    }
+
+    fn log_steps(connection: &Connection) -> Vec<i64> {
+        let mut stmt = connection.prepare("SELECT step FROM log ORDER BY step").unwrap();
+        stmt.query_map([], |row| row.get(0))
+            .unwrap()
+            .map(|step| step.unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn runner_applies_pending_migrations_in_order() {
+        let mut connection = Connection::open_in_memory().unwrap();
+        let migrations: &[Migration] = &[
+            |connection| connection.execute_batch("CREATE TABLE log (step INTEGER)"),
+            |connection| connection.execute_batch("INSERT INTO log VALUES (2)"),
+            |connection| connection.execute_batch("INSERT INTO log VALUES (3)"),
+        ];
+
+        run_migrations(&mut connection, &migrations[..1]).unwrap();
+        assert_eq!(user_version(&connection), 1);
+        assert!(log_steps(&connection).is_empty());
+
+        run_migrations(&mut connection, migrations).unwrap();
+        assert_eq!(user_version(&connection), 3);
+        assert_eq!(log_steps(&connection), [2, 3]);
+
+        run_migrations(&mut connection, migrations).unwrap();
+        assert_eq!(log_steps(&connection), [2, 3]);
+    }
+
+    #[test]
+    fn failed_migration_rolls_back_and_keeps_the_version() {
+        let mut connection = Connection::open_in_memory().unwrap();
+        let migrations: &[Migration] = &[
+            |connection| connection.execute_batch("CREATE TABLE log (step INTEGER)"),
+            |connection| {
+                connection.execute_batch("INSERT INTO log VALUES (2)")?;
+                connection.execute_batch("INSERT INTO missing_table VALUES (2)")
+            },
+        ];
+
+        assert!(run_migrations(&mut connection, migrations).is_err());
+        assert_eq!(user_version(&connection), 1);
+        assert!(log_steps(&connection).is_empty());
+    }
+
+    #[test]
+    fn maintenance_empties_the_write_ahead_log() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("notes.db");
+        let db = Database::new(&path).unwrap();
+
+        for index in 0..20 {
+            let note = crate::models::Note::with_content(format!("Note {}", index), "body");
+            db.notes.create_note(&note).unwrap();
+        }
+        let wal = dir.path().join("notes.db-wal");
+        assert!(std::fs::metadata(&wal).unwrap().len() > 0);
+
+        db.run_maintenance().unwrap();
+        assert_eq!(std::fs::metadata(&wal).unwrap().len(), 0);
+        assert_eq!(db.notes.list_notes(SortOrder::Manual).unwrap().len(), 20);
+    }
+
+    #[test]
+    fn maintenance_releases_pages_freed_by_deletes() {
+        let dir = TempDir::new().unwrap();
+        let db = Database::new(dir.path().join("notes.db")).unwrap();
+        let pragma = |name: &str| -> i64 {
+            db.connection
+                .lock()
+                .unwrap()
+                .query_row(&format!("PRAGMA {}", name), [], |row| row.get(0))
+                .unwrap()
         };
 
-        if needs_migration {
-            println!("Starting database migration...");
+        let body = "x".repeat(64 * 1024);
+        let notes: Vec<_> = (0..10)
+            .map(|index| crate::models::Note::with_content(format!("Note {}", index), &body))
+            .collect();
+        for note in &notes {
+            db.notes.create_note(note).unwrap();
+        }
+        for note in &notes {
+            db.notes.delete_note(note.id).unwrap();
+        }
+        assert!(pragma("freelist_count") > 0);
 
-            let mut connection = self.connection.lock().unwrap();
-            let tx = connection.transaction()?;
+        db.run_maintenance().unwrap();
+        assert_eq!(pragma("freelist_count"), 0);
+    }
 
-            tx.execute(
-                "CREATE TABLE notes_new (
+    #[test]
+    fn older_databases_are_switched_to_incremental_vacuum() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("notes.db");
+        Connection::open(&path)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE notes (
                     id TEXT PRIMARY KEY,
                     title TEXT NOT NULL,
                     content TEXT NOT NULL,
                     created_at INTEGER NOT NULL
                 )",
-                [],
-            )?;
+            )
+            .unwrap();
 
-            tx.execute(
-                "INSERT INTO notes_new SELECT id, title, content, CAST(created_at AS INTEGER) FROM notes",
-                [],
-            )?;
+        let db = Database::new(&path).unwrap();
+        assert_eq!(column_type(&db, "archived").as_deref(), Some("INTEGER"));
+        let connection = db.connection.lock().unwrap();
+        assert_eq!(user_version(&connection), MIGRATIONS.len());
+        let auto_vacuum = || -> i64 {
+            connection
+                .query_row("PRAGMA auto_vacuum", [], |row| row.get(0))
+                .unwrap()
+        };
+        assert_eq!(auto_vacuum(), 0, "opening leaves the rebuild to maintenance");
 
-            tx.execute("DROP TABLE notes", [])?;
+        enable_incremental_vacuum(&connection).unwrap();
+        let mode = auto_vacuum();
+        let journal: String = connection
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(mode, 2);
+        assert_eq!(journal, "wal");
+    }
 
-            tx.execute("ALTER TABLE notes_new RENAME TO notes", [])?;
+    #[test]
+    fn data_version_only_moves_for_other_connections() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("notes.db");
+        let db = Database::new(&path).unwrap();
+        let before = db.data_version().unwrap();
 
-            tx.commit()?;
+        let note = crate::models::Note::with_content("Own".to_string(), "body");
+        db.notes.create_note(&note).unwrap();
+        assert_eq!(db.data_version().unwrap(), before);
 
-            println!("Database migration completed successfully");
-        } else {
-            println!("No database migration needed");
-        }
+        let other = Database::new(&path).unwrap();
+        other.notes.delete_note(note.id).unwrap();
+        assert_ne!(db.data_version().unwrap(), before);
+    }
 
-        Ok(())
+    #[test]
+    fn fresh_database_needs_no_migration() {
+        let dir = TempDir::new().unwrap();
+        let db = Database::new(dir.path().join("notes.db")).unwrap();
+        assert_eq!(user_version(&db.connection.lock().unwrap()), MIGRATIONS.len());
+        assert_eq!(column_type(&db, "created_at").as_deref(), Some("INTEGER"));
+        assert!(db.notes.list_notes(SortOrder::Manual).unwrap().is_empty());
     }
 }
@@ -1,7 +1,22 @@
+mod attachment;
+mod attachment_repository;
+mod crypto;
 mod db;
 mod note;
 mod note_repository;
+mod note_writer;
+mod session;
+mod settings;
 
-pub use db::Database;
-pub use note::Note;
-pub use note_repository::NoteRepository;
+pub use attachment::Attachment;
+pub use attachment_repository::AttachmentRepository;
+pub use crypto::{CryptoError, decrypt_content, encrypt_content};
+pub use db::{Database, Maintenance};
+pub use note::{Note, normalize_newlines};
+pub use note_repository::{NoteRepository, Replacements, SortOrder, without_writes};
+pub use note_writer::{ContentTransform, NoteChange, NoteWriter, WriteOutcome};
+pub use session::Session;
+pub use settings::{
+    DEFAULT_DATE_FORMAT, EditorHighlights, IndentStyle, LineEnding, NewNotePlacement, Settings,
+    SidebarDensity, UndoLimits, format_timestamp,
+};
@@ -8,6 +8,28 @@ pub struct Note {
     pub title: String,
     pub content: String,
     pub created_at: u64,
+    #[serde(default)]
+    pub updated_at: u64,
+    #[serde(default = "default_monospace")]
+    pub monospace: bool,
+    #[serde(default)]
+    pub encrypted: bool,
+    #[serde(default)]
+    pub sort_index: i64,
+    /// Read-only: the content and title can't be edited until unlocked.
+    #[serde(default)]
+    pub locked: bool,
+    /// Listed under Favorites; doesn't affect where the note sorts.
+    #[serde(default)]
+    pub starred: bool,
+    /// Kept out of the main list but still editable; unlike trash it never
+    /// expires.
+    #[serde(default)]
+    pub archived: bool,
+}
+
+fn default_monospace() -> bool {
+    true
 }
 
 impl Note {
@@ -22,6 +44,41 @@ impl Note {
             title,
             content: String::new(),
             created_at: timestamp,
+            updated_at: timestamp,
+            monospace: true,
+            encrypted: false,
+            sort_index: -(timestamp as i64),
+            locked: false,
+            starred: false,
+            archived: false,
+        }
+    }
+
+    /// A new note holding `content`, with its line endings normalized.
+    pub fn with_content(title: String, content: &str) -> Self {
+        Self {
+            content: normalize_newlines(content),
+            ..Self::new(title)
         }
     }
 }
+
+/// Rewrites `\r\n` and lone `\r` line endings as `\n`, the only line break the
+/// editor understands.
+pub fn normalize_newlines(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mixed_line_endings_become_newlines() {
+        assert_eq!(normalize_newlines("a\r\nb\rc\nd"), "a\nb\nc\nd");
+        assert_eq!(normalize_newlines("\r\n\r\n"), "\n\n");
+        assert_eq!(normalize_newlines("\n\r"), "\n\n");
+        assert_eq!(normalize_newlines("no breaks"), "no breaks");
+        assert_eq!(Note::with_content("t".into(), "one\r\ntwo").content, "one\ntwo");
+    }
+}
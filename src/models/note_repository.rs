@@ -1,9 +1,114 @@
 use rusqlite::{Connection, OptionalExtension, Result};
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
 use uuid::Uuid;
 
 use crate::models::Note;
 
+thread_local! {
+    static WRITES_FORBIDDEN: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Runs `f` with database writes forbidden on this thread, so code paths
+/// that must stay read-only (such as rendering) trip a debug assertion if
+/// they ever write.
+pub fn without_writes<R>(f: impl FnOnce() -> R) -> R {
+    let previous = WRITES_FORBIDDEN.with(|flag| flag.replace(true));
+    let result = f();
+    WRITES_FORBIDDEN.with(|flag| flag.set(previous));
+    result
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+fn debug_assert_writable() {
+    debug_assert!(
+        !WRITES_FORBIDDEN.with(Cell::get),
+        "Database write attempted while writes are forbidden"
+    );
+}
+
+/// How `list_notes` orders its results. Every variant maps to a fixed
+/// `ORDER BY` clause, so no caller-provided text ever reaches the query.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    /// The manual order kept in `sort_index`, newest first among ties.
+    #[default]
+    Manual,
+    ManualReversed,
+    CreatedAscending,
+    CreatedDescending,
+    UpdatedAscending,
+    UpdatedDescending,
+    TitleAscending,
+    TitleDescending,
+}
+
+impl SortOrder {
+    fn order_by_clause(self) -> &'static str {
+        match self {
+            SortOrder::Manual => "sort_index ASC, created_at DESC",
+            SortOrder::ManualReversed => "sort_index DESC, created_at ASC",
+            SortOrder::CreatedAscending => "created_at ASC",
+            SortOrder::CreatedDescending => "created_at DESC",
+            SortOrder::UpdatedAscending => "COALESCE(updated_at, created_at) ASC, created_at ASC",
+            SortOrder::UpdatedDescending => {
+                "COALESCE(updated_at, created_at) DESC, created_at DESC"
+            }
+            SortOrder::TitleAscending => "title COLLATE NOCASE ASC, created_at DESC",
+            SortOrder::TitleDescending => "title COLLATE NOCASE DESC, created_at DESC",
+        }
+    }
+
+    /// Orders two notes the way `order_by_clause` has the database order
+    /// them.
+    pub fn compare(self, a: &Note, b: &Note) -> Ordering {
+        let title = |note: &Note| note.title.to_ascii_lowercase();
+        match self {
+            SortOrder::Manual => a
+                .sort_index
+                .cmp(&b.sort_index)
+                .then(b.created_at.cmp(&a.created_at)),
+            SortOrder::ManualReversed => b
+                .sort_index
+                .cmp(&a.sort_index)
+                .then(a.created_at.cmp(&b.created_at)),
+            SortOrder::CreatedAscending => a.created_at.cmp(&b.created_at),
+            SortOrder::CreatedDescending => b.created_at.cmp(&a.created_at),
+            SortOrder::UpdatedAscending => a
+                .updated_at
+                .cmp(&b.updated_at)
+                .then(a.created_at.cmp(&b.created_at)),
+            SortOrder::UpdatedDescending => b
+                .updated_at
+                .cmp(&a.updated_at)
+                .then(b.created_at.cmp(&a.created_at)),
+            SortOrder::TitleAscending => title(a)
+                .cmp(&title(b))
+                .then(b.created_at.cmp(&a.created_at)),
+            SortOrder::TitleDescending => title(b)
+                .cmp(&title(a))
+                .then(b.created_at.cmp(&a.created_at)),
+        }
+    }
+
+    /// Where `note` belongs in `notes`, a list already in this order, so it
+    /// sits where `list_notes` will put it next time.
+    pub fn insertion_index(self, notes: &[Note], note: &Note) -> usize {
+        notes.partition_point(|existing| self.compare(existing, note) != Ordering::Greater)
+    }
+}
+
 pub struct NoteRepository {
     connection: Arc<Mutex<Connection>>,
 }
@@ -14,6 +119,7 @@ impl NoteRepository {
     }
 
     pub fn create_note(&self, note: &Note) -> Result<()> {
+        debug_assert_writable();
         println!("Saving note to database with ID: {}", note.id);
         let mut connection = match self.connection.lock() {
             Ok(conn) => conn,
@@ -29,12 +135,19 @@ impl NoteRepository {
         println!("Transaction started");
 
         let result = tx.execute(
-            "INSERT INTO notes (id, title, content, created_at) VALUES (?1, ?2, ?3, ?4)",
+            "INSERT INTO notes (id, title, content, created_at, updated_at, monospace, encrypted, sort_index, locked, starred, archived) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
             [
                 &note.id.to_string(),
                 &note.title,
                 &note.content,
                 &note.created_at.to_string(),
+                &note.updated_at.to_string(),
+                &(note.monospace as i64).to_string(),
+                &(note.encrypted as i64).to_string(),
+                &note.sort_index.to_string(),
+                &(note.locked as i64).to_string(),
+                &(note.starred as i64).to_string(),
+                &(note.archived as i64).to_string(),
             ],
         );
 
@@ -54,13 +167,11 @@ impl NoteRepository {
         tx.commit()?;
         println!("Transaction committed");
 
-        let _ = connection.execute("PRAGMA wal_checkpoint(FULL)", []);
-        println!("Checkpoint completed");
-
         Ok(())
     }
 
-    pub fn update_note(&self, note: &Note) -> Result<()> {
+    pub fn update_note(&self, note: &Note) -> Result<bool> {
+        debug_assert_writable();
         let mut connection = match self.connection.lock() {
             Ok(conn) => conn,
             Err(e) => {
@@ -74,22 +185,25 @@ impl NoteRepository {
         let tx = connection.transaction()?;
 
         let result = tx.execute(
-            "UPDATE notes SET title = ?1, content = ?2, created_at = ?3 WHERE id = ?4",
+            "UPDATE notes SET title = ?1, content = ?2, created_at = ?3, monospace = ?4, updated_at = ?5
+             WHERE id = ?6
+               AND (title IS NOT ?1 OR content IS NOT ?2 OR created_at IS NOT ?3 OR monospace IS NOT ?4)",
             [
                 &note.title,
                 &note.content,
                 &note.created_at.to_string(),
+                &(note.monospace as i64).to_string(),
+                &now().to_string(),
                 &note.id.to_string(),
             ],
         );
 
-        match &result {
+        let rows = match &result {
             Ok(rows) => {
-                if *rows == 0 {
-                    eprintln!("Warning: No rows updated for note {}", note.id);
-                } else {
+                if *rows > 0 {
                     println!("Updated note successfully, {} rows affected", rows);
                 }
+                *rows
             }
             Err(e) => {
                 eprintln!("Error updating note: {}", e);
@@ -99,81 +213,1140 @@ impl NoteRepository {
                     Some(format!("Failed to update: {}", e)),
                 ));
             }
-        }
+        };
 
         tx.commit()?;
 
-        let _ = connection.execute("PRAGMA wal_checkpoint(FULL)", []);
+        Ok(rows > 0)
+    }
 
+    pub fn delete_note(&self, id: Uuid) -> Result<()> {
+        debug_assert_writable();
+        let connection = self.connection.lock().unwrap();
+        connection.execute("DELETE FROM notes WHERE id = ?1", [id.to_string()])?;
         Ok(())
     }
 
-    pub fn delete_note(&self, id: &str) -> Result<()> {
+    pub fn update_note_content(&self, id: Uuid, content: &str) -> Result<bool> {
+        debug_assert_writable();
+        let connection = self.connection.lock().unwrap();
+        let rows = connection.execute(
+            "UPDATE notes SET content = ?1, updated_at = ?2 WHERE id = ?3 AND content IS NOT ?1",
+            [content.to_string(), now().to_string(), id.to_string()],
+        )?;
+        Ok(rows > 0)
+    }
+
+    pub fn update_note_title(&self, id: Uuid, title: &str) -> Result<bool> {
+        debug_assert_writable();
+        let connection = self.connection.lock().unwrap();
+        let rows = connection.execute(
+            "UPDATE notes SET title = ?1, updated_at = ?2 WHERE id = ?3 AND title IS NOT ?1",
+            [title.to_string(), now().to_string(), id.to_string()],
+        )?;
+        Ok(rows > 0)
+    }
+
+    pub fn set_note_monospace(&self, id: Uuid, monospace: bool) -> Result<bool> {
+        debug_assert_writable();
+        let connection = self.connection.lock().unwrap();
+        let rows = connection.execute(
+            "UPDATE notes SET monospace = ?1, updated_at = ?2 WHERE id = ?3 AND monospace IS NOT ?1",
+            [(monospace as i64).to_string(), now().to_string(), id.to_string()],
+        )?;
+        Ok(rows > 0)
+    }
+
+    pub fn set_note_locked(&self, id: Uuid, locked: bool) -> Result<bool> {
+        debug_assert_writable();
         let connection = self.connection.lock().unwrap();
-        connection.execute("DELETE FROM notes WHERE id = ?1", [id])?;
+        let rows = connection.execute(
+            "UPDATE notes SET locked = ?1 WHERE id = ?2 AND locked IS NOT ?1",
+            [(locked as i64).to_string(), id.to_string()],
+        )?;
+        Ok(rows > 0)
+    }
+
+    pub fn set_note_starred(&self, id: Uuid, starred: bool) -> Result<bool> {
+        debug_assert_writable();
+        let connection = self.connection.lock().unwrap();
+        let rows = connection.execute(
+            "UPDATE notes SET starred = ?1 WHERE id = ?2 AND starred IS NOT ?1",
+            [(starred as i64).to_string(), id.to_string()],
+        )?;
+        Ok(rows > 0)
+    }
+
+    pub fn set_note_archived(&self, id: Uuid, archived: bool) -> Result<bool> {
+        debug_assert_writable();
+        let connection = self.connection.lock().unwrap();
+        let rows = connection.execute(
+            "UPDATE notes SET archived = ?1 WHERE id = ?2 AND archived IS NOT ?1",
+            [(archived as i64).to_string(), id.to_string()],
+        )?;
+        Ok(rows > 0)
+    }
+
+    pub fn set_note_encryption(&self, id: Uuid, encrypted: bool, content: &str) -> Result<()> {
+        debug_assert_writable();
+        let connection = self.connection.lock().unwrap();
+        connection.execute(
+            "UPDATE notes SET encrypted = ?1, content = ?2, updated_at = ?3 WHERE id = ?4",
+            [
+                (encrypted as i64).to_string(),
+                content.to_string(),
+                now().to_string(),
+                id.to_string(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn move_note_to_top(&self, id: Uuid) -> Result<()> {
+        debug_assert_writable();
+        let connection = self.connection.lock().unwrap();
+        connection.execute(
+            "UPDATE notes SET sort_index = (SELECT COALESCE(MIN(sort_index), 0) - 1 FROM notes) WHERE id = ?1",
+            [id.to_string()],
+        )?;
         Ok(())
     }
 
-    pub fn get_note(&self, id: &str) -> Result<Option<Note>> {
+    pub fn move_note_to_bottom(&self, id: Uuid) -> Result<()> {
+        debug_assert_writable();
+        let connection = self.connection.lock().unwrap();
+        connection.execute(
+            "UPDATE notes SET sort_index = (SELECT COALESCE(MAX(sort_index), 0) + 1 FROM notes) WHERE id = ?1",
+            [id.to_string()],
+        )?;
+        Ok(())
+    }
+
+    pub fn trash_note(&self, id: Uuid) -> Result<()> {
+        debug_assert_writable();
+        let deleted_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        let connection = self.connection.lock().unwrap();
+        connection.execute(
+            "UPDATE notes SET deleted_at = ?1 WHERE id = ?2",
+            [deleted_at.to_string(), id.to_string()],
+        )?;
+        Ok(())
+    }
+
+    pub fn restore_note(&self, id: Uuid) -> Result<()> {
+        debug_assert_writable();
+        let connection = self.connection.lock().unwrap();
+        connection.execute(
+            "UPDATE notes SET deleted_at = NULL WHERE id = ?1",
+            [id.to_string()],
+        )?;
+        Ok(())
+    }
+
+    pub fn count_trashed(&self) -> Result<usize> {
+        let connection = self.connection.lock().unwrap();
+        let count: i64 = connection.query_row(
+            "SELECT COUNT(*) FROM notes WHERE deleted_at IS NOT NULL",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(count as usize)
+    }
+
+    pub fn purge_older_than(&self, cutoff: u64) -> Result<usize> {
+        debug_assert_writable();
+        let connection = self.connection.lock().unwrap();
+        let purged = connection.execute(
+            "DELETE FROM notes WHERE deleted_at IS NOT NULL AND deleted_at < ?1",
+            [cutoff.to_string()],
+        )?;
+        Ok(purged)
+    }
+
+    /// Rebuilds the database file so the space freed by deleted notes goes
+    /// back to the file system.
+    pub fn vacuum(&self) -> Result<()> {
+        debug_assert_writable();
+        let connection = self.connection.lock().unwrap();
+        connection.execute_batch("VACUUM")?;
+        // The rebuilt pages sit in the write-ahead log until a checkpoint.
+        connection.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))
+    }
+
+    /// Size of the database file in bytes, as SQLite lays it out in pages.
+    pub fn database_size_bytes(&self) -> Result<u64> {
+        let connection = self.connection.lock().unwrap();
+        let pages: i64 = connection.query_row("PRAGMA page_count", [], |row| row.get(0))?;
+        let page_size: i64 = connection.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+        Ok((pages * page_size) as u64)
+    }
+
+    pub fn get_note(&self, id: Uuid) -> Result<Option<Note>> {
         let connection = self.connection.lock().unwrap();
         let mut stmt =
-            connection.prepare("SELECT id, title, content, created_at FROM notes WHERE id = ?1")?;
+            connection.prepare("SELECT id, title, content, created_at, monospace, updated_at, encrypted, sort_index, locked, starred, archived FROM notes WHERE id = ?1")?;
 
         let note = stmt
-            .query_row([id], |row| {
-                let id: String = row.get(0)?;
-                let title: String = row.get(1)?;
-                let content: String = row.get(2)?;
-
-                let created_at: u64 = match row.get::<_, rusqlite::types::Value>(3)? {
-                    rusqlite::types::Value::Integer(i) => i as u64,
-                    rusqlite::types::Value::Real(f) => f as u64,
-                    rusqlite::types::Value::Text(s) => s.parse().unwrap_or_default(),
-                    _ => 0,
-                };
-
-                Ok(Note {
-                    id: Uuid::parse_str(&id).unwrap_or_default(),
-                    title,
-                    content,
-                    created_at,
-                })
-            })
+            .query_row([id.to_string()], note_from_row)
             .optional()?;
 
-        Ok(note)
+        Ok(note.flatten())
+    }
+
+    /// Loads the notes with the given ids in the order asked for, leaving out
+    /// ids with no row. Trashed notes are included, as with `get_note`.
+    pub fn get_notes(&self, ids: &[Uuid]) -> Result<Vec<Note>> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let connection = self.connection.lock().unwrap();
+        let mut found = HashMap::with_capacity(ids.len());
+        // Stays under SQLite's default limit on bound parameters.
+        for chunk in ids.chunks(900) {
+            let placeholders = vec!["?"; chunk.len()].join(", ");
+            let mut stmt = connection.prepare(&format!(
+                "SELECT id, title, content, created_at, monospace, updated_at, encrypted, sort_index, locked, starred, archived FROM notes WHERE id IN ({})",
+                placeholders
+            ))?;
+            let notes = stmt.query_map(
+                rusqlite::params_from_iter(chunk.iter().map(|id| id.to_string())),
+                note_from_row,
+            )?;
+            for note in notes {
+                if let Some(note) = note? {
+                    found.insert(note.id, note);
+                }
+            }
+        }
+
+        Ok(ids.iter().filter_map(|id| found.get(id).cloned()).collect())
     }
 
-    pub fn list_notes(&self) -> Result<Vec<Note>> {
+    /// Whether a row for `id` exists, trashed or not, without loading it.
+    pub fn note_exists(&self, id: Uuid) -> Result<bool> {
         let connection = self.connection.lock().unwrap();
-        let mut stmt = connection
-            .prepare("SELECT id, title, content, created_at FROM notes ORDER BY created_at DESC")?;
+        let exists = connection
+            .query_row(
+                "SELECT 1 FROM notes WHERE id = ?1 LIMIT 1",
+                [id.to_string()],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some();
+        Ok(exists)
+    }
 
-        let notes_iter = stmt.query_map([], |row| {
-            let id: String = row.get(0)?;
-            let title: String = row.get(1)?;
-            let content: String = row.get(2)?;
+    /// Notes that are neither trashed nor archived.
+    pub fn list_notes(&self, order: SortOrder) -> Result<Vec<Note>> {
+        let connection = self.connection.lock().unwrap();
+        let mut stmt = connection.prepare(&format!(
+            "SELECT id, title, content, created_at, monospace, updated_at, encrypted, sort_index, locked, starred, archived FROM notes WHERE deleted_at IS NULL AND archived = 0 ORDER BY {}",
+            order.order_by_clause()
+        ))?;
+
+        let notes_iter = stmt.query_map([], note_from_row)?;
 
-            let created_at: u64 = match row.get::<_, rusqlite::types::Value>(3)? {
-                rusqlite::types::Value::Integer(i) => i as u64,
-                rusqlite::types::Value::Real(f) => f as u64,
-                rusqlite::types::Value::Text(s) => s.parse().unwrap_or_default(),
-                _ => 0,
+        let mut notes = Vec::new();
+        for note_result in notes_iter {
+            notes.extend(note_result?);
+        }
+
+        Ok(notes)
+    }
+
+    /// Hands each note to `visit` as its row is read instead of collecting
+    /// them, stopping early if `visit` returns false. Returns how many notes
+    /// were visited.
+    pub fn for_each_note(
+        &self,
+        order: SortOrder,
+        mut visit: impl FnMut(Note) -> bool,
+    ) -> Result<usize> {
+        let connection = self.connection.lock().unwrap();
+        let mut stmt = connection.prepare(&format!(
+            "SELECT id, title, content, created_at, monospace, updated_at, encrypted, sort_index, locked, starred, archived FROM notes WHERE deleted_at IS NULL ORDER BY {}",
+            order.order_by_clause()
+        ))?;
+
+        let mut rows = stmt.query([])?;
+        let mut visited = 0;
+        while let Some(row) = rows.next()? {
+            let Some(note) = note_from_row(row)? else {
+                continue;
             };
+            visited += 1;
+            if !visit(note) {
+                break;
+            }
+        }
 
-            Ok(Note {
-                id: Uuid::parse_str(&id).unwrap_or_default(),
-                title,
-                content,
-                created_at,
-            })
-        })?;
+        Ok(visited)
+    }
+
+    /// Inserts `notes` in one transaction, skipping any whose id is already
+    /// taken. Returns how many were added.
+    pub fn insert_notes(&self, notes: impl IntoIterator<Item = Note>) -> Result<usize> {
+        debug_assert_writable();
+        let mut connection = self.connection.lock().unwrap();
+        let tx = connection.transaction()?;
+
+        let mut inserted = 0;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR IGNORE INTO notes (id, title, content, created_at, updated_at, monospace, encrypted, sort_index, locked, starred, archived) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            )?;
+            for note in notes {
+                inserted += stmt.execute([
+                    &note.id.to_string(),
+                    &note.title,
+                    &note.content,
+                    &note.created_at.to_string(),
+                    &note.updated_at.to_string(),
+                    &(note.monospace as i64).to_string(),
+                    &(note.encrypted as i64).to_string(),
+                    &note.sort_index.to_string(),
+                    &(note.locked as i64).to_string(),
+                    &(note.starred as i64).to_string(),
+                    &(note.archived as i64).to_string(),
+                ])?;
+            }
+        }
+
+        tx.commit()?;
+        Ok(inserted)
+    }
+
+    pub fn list_archived(&self, order: SortOrder) -> Result<Vec<Note>> {
+        let connection = self.connection.lock().unwrap();
+        let mut stmt = connection.prepare(&format!(
+            "SELECT id, title, content, created_at, monospace, updated_at, encrypted, sort_index, locked, starred, archived FROM notes WHERE deleted_at IS NULL AND archived = 1 ORDER BY {}",
+            order.order_by_clause()
+        ))?;
+
+        let notes_iter = stmt.query_map([], note_from_row)?;
 
         let mut notes = Vec::new();
         for note_result in notes_iter {
-            notes.push(note_result?);
+            notes.extend(note_result?);
         }
 
         Ok(notes)
     }
+
+    pub fn list_starred(&self, order: SortOrder) -> Result<Vec<Note>> {
+        let connection = self.connection.lock().unwrap();
+        let mut stmt = connection.prepare(&format!(
+            "SELECT id, title, content, created_at, monospace, updated_at, encrypted, sort_index, locked, starred, archived FROM notes WHERE deleted_at IS NULL AND starred = 1 ORDER BY {}",
+            order.order_by_clause()
+        ))?;
+
+        let notes_iter = stmt.query_map([], note_from_row)?;
+
+        let mut notes = Vec::new();
+        for note_result in notes_iter {
+            notes.extend(note_result?);
+        }
+
+        Ok(notes)
+    }
+
+    /// Groups notes whose title and content are both identical, oldest first
+    /// within each group. Notes without a duplicate are left out.
+    pub fn find_duplicates(&self) -> Result<Vec<Vec<Uuid>>> {
+        let connection = self.connection.lock().unwrap();
+        let mut stmt = connection.prepare(
+            "SELECT id, title, content FROM notes WHERE deleted_at IS NULL ORDER BY created_at, rowid",
+        )?;
+
+        // Notes are bucketed by a hash of their text and only compared in
+        // full against the groups sharing that hash.
+        let mut groups: Vec<(String, String, Vec<Uuid>)> = Vec::new();
+        let mut groups_by_hash: HashMap<u64, Vec<usize>> = HashMap::new();
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let Ok(id) = Uuid::parse_str(&row.get::<_, String>(0)?) else {
+                continue;
+            };
+            let title: String = row.get::<_, Option<String>>(1)?.unwrap_or_default();
+            let content: String = row.get(2)?;
+
+            let mut hasher = DefaultHasher::new();
+            (&title, &content).hash(&mut hasher);
+            let candidates = groups_by_hash.entry(hasher.finish()).or_default();
+            match candidates
+                .iter()
+                .find(|&&index| groups[index].0 == title && groups[index].1 == content)
+            {
+                Some(&index) => groups[index].2.push(id),
+                None => {
+                    candidates.push(groups.len());
+                    groups.push((title, content, vec![id]));
+                }
+            }
+        }
+
+        Ok(groups
+            .into_iter()
+            .map(|(_, _, ids)| ids)
+            .filter(|ids| ids.len() > 1)
+            .collect())
+    }
+
+    pub fn list_trashed(&self) -> Result<Vec<Note>> {
+        let connection = self.connection.lock().unwrap();
+        let mut stmt = connection.prepare(
+            "SELECT id, title, content, created_at, monospace, updated_at, encrypted, sort_index, locked, starred, archived FROM notes WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC",
+        )?;
+
+        let notes_iter = stmt.query_map([], note_from_row)?;
+
+        let mut notes = Vec::new();
+        for note_result in notes_iter {
+            notes.extend(note_result?);
+        }
+
+        Ok(notes)
+    }
+
+    pub fn replace_in_all(
+        &self,
+        find: &str,
+        replace: &str,
+        case_sensitive: bool,
+    ) -> Result<Replacements> {
+        debug_assert_writable();
+        if find.is_empty() {
+            return Ok(Replacements::default());
+        }
+
+        let mut connection = match self.connection.lock() {
+            Ok(conn) => conn,
+            Err(e) => {
+                eprintln!("Failed to lock database connection: {}", e);
+                return Err(rusqlite::Error::InvalidParameterName(
+                    "Failed to lock connection".to_string(),
+                ));
+            }
+        };
+
+        let tx = connection.transaction()?;
+
+        let rows: Vec<(String, String)> = {
+            let mut stmt = tx.prepare(
+                "SELECT id, content FROM notes
+                 WHERE encrypted = 0 AND locked = 0 AND deleted_at IS NULL",
+            )?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<Result<_>>()?
+        };
+
+        let updated_at = now().to_string();
+        let mut notes_changed = 0;
+        let mut occurrences = 0;
+        for (id, content) in rows {
+            let (new_content, count) = replace_occurrences(&content, find, replace, case_sensitive);
+            if count == 0 {
+                continue;
+            }
+
+            tx.execute(
+                "UPDATE notes SET content = ?1, updated_at = ?2 WHERE id = ?3",
+                [&new_content, &updated_at, &id],
+            )?;
+            notes_changed += 1;
+            occurrences += count;
+        }
+
+        tx.commit()?;
+        println!(
+            "Replaced {} occurrences across {} notes",
+            occurrences, notes_changed
+        );
+
+        Ok(Replacements {
+            notes: notes_changed,
+            occurrences,
+        })
+    }
+}
+
+/// What a find-and-replace across every note changed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Replacements {
+    pub notes: usize,
+    pub occurrences: usize,
+}
+
+fn note_from_row(row: &rusqlite::Row) -> Result<Option<Note>> {
+    let id: String = row.get(0)?;
+    let Ok(id) = Uuid::parse_str(&id) else {
+        eprintln!("Warning: Skipping note with invalid id {:?}", id);
+        return Ok(None);
+    };
+
+    // A NULL title, left by an older build or another tool, shows as
+    // untitled instead of failing the whole list.
+    let title: String = row.get::<_, Option<String>>(1)?.unwrap_or_default();
+    let content: String = row.get(2)?;
+
+    let created_at: u64 = match row.get::<_, rusqlite::types::Value>(3)? {
+        rusqlite::types::Value::Integer(i) => i as u64,
+        rusqlite::types::Value::Real(f) => f as u64,
+        rusqlite::types::Value::Text(s) => s.parse().unwrap_or_default(),
+        _ => 0,
+    };
+
+    let monospace = row.get::<_, Option<i64>>(4)?.is_none_or(|value| value != 0);
+    let encrypted = row.get::<_, Option<i64>>(6)?.is_some_and(|value| value != 0);
+    let sort_index = row
+        .get::<_, Option<i64>>(7)?
+        .unwrap_or(-(created_at as i64));
+    let updated_at = row
+        .get::<_, Option<i64>>(5)?
+        .map_or(created_at, |value| value as u64);
+    let locked = row.get::<_, Option<i64>>(8)?.is_some_and(|value| value != 0);
+    let starred = row.get::<_, Option<i64>>(9)?.is_some_and(|value| value != 0);
+    let archived = row.get::<_, Option<i64>>(10)?.is_some_and(|value| value != 0);
+
+    Ok(Some(Note {
+        id,
+        title,
+        content,
+        created_at,
+        updated_at,
+        monospace,
+        encrypted,
+        sort_index,
+        locked,
+        starred,
+        archived,
+    }))
+}
+
+fn replace_occurrences(
+    text: &str,
+    find: &str,
+    replace: &str,
+    case_sensitive: bool,
+) -> (String, usize) {
+    if case_sensitive {
+        return (text.replace(find, replace), text.matches(find).count());
+    }
+
+    let mut result = String::with_capacity(text.len());
+    let mut count = 0;
+    let mut index = 0;
+    while index < text.len() {
+        if let Some(len) = match_len_ignore_case(&text[index..], find) {
+            result.push_str(replace);
+            index += len;
+            count += 1;
+        } else {
+            let ch = text[index..].chars().next().unwrap();
+            result.push(ch);
+            index += ch.len_utf8();
+        }
+    }
+
+    (result, count)
+}
+
+fn match_len_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    let mut haystack_chars = haystack.chars();
+    let mut len = 0;
+    for needle_char in needle.chars() {
+        let haystack_char = haystack_chars.next()?;
+        if !haystack_char.to_lowercase().eq(needle_char.to_lowercase()) {
+            return None;
+        }
+        len += haystack_char.len_utf8();
+    }
+    Some(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Database;
+    use tempfile::TempDir;
+
+    fn temp_database() -> (TempDir, Database) {
+        let dir = TempDir::new().unwrap();
+        let db = Database::new(dir.path().join("notes.db")).unwrap();
+        (dir, db)
+    }
+
+    fn note_with(title: &str, content: &str, created_at: u64) -> Note {
+        let mut note = Note::new(title.to_string());
+        note.content = content.to_string();
+        note.created_at = created_at;
+        note.updated_at = created_at;
+        note
+    }
+
+    #[test]
+    fn create_then_get_round_trips_every_field() {
+        let (_dir, db) = temp_database();
+        let mut note = note_with("Groceries", "milk\neggs", 1_700_000_000);
+        note.monospace = false;
+        note.locked = true;
+        note.starred = true;
+        note.archived = true;
+        db.notes.create_note(&note).unwrap();
+
+        let stored = db.notes.get_note(note.id).unwrap().unwrap();
+        assert_eq!(stored.id, note.id);
+        assert_eq!(stored.title, "Groceries");
+        assert_eq!(stored.content, "milk\neggs");
+        assert_eq!(stored.created_at, 1_700_000_000);
+        assert_eq!(stored.updated_at, 1_700_000_000);
+        assert!(!stored.monospace);
+        assert!(stored.locked);
+        assert!(stored.starred);
+        assert!(stored.archived);
+    }
+
+    #[test]
+    fn failed_create_leaves_the_library_unchanged() {
+        let (_dir, db) = temp_database();
+        let existing = note_with("Existing", "kept", 100);
+        db.notes.create_note(&existing).unwrap();
+        db.notes
+            .connection
+            .lock()
+            .unwrap()
+            .execute_batch(
+                "CREATE TRIGGER reject_inserts BEFORE INSERT ON notes
+                 BEGIN SELECT RAISE(ABORT, 'disk is full'); END",
+            )
+            .unwrap();
+
+        let rejected = note_with("Rejected", "", 200);
+        assert!(db.notes.create_note(&rejected).is_err());
+        assert!(db.notes.get_note(rejected.id).unwrap().is_none());
+
+        let notes = db.notes.list_notes(SortOrder::Manual).unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].id, existing.id);
+        assert_eq!(notes[0].content, "kept");
+    }
+
+    #[test]
+    fn vacuum_after_deleting_notes_shrinks_the_file() {
+        let (dir, db) = temp_database();
+        let path = dir.path().join("notes.db");
+        let notes: Vec<Note> = (0..200)
+            .map(|index| note_with(&format!("Note {}", index), &"x".repeat(4096), index))
+            .collect();
+        db.notes.insert_notes(notes.clone()).unwrap();
+        for note in &notes {
+            db.notes.delete_note(note.id).unwrap();
+        }
+
+        let before = db.notes.database_size_bytes().unwrap();
+
+        db.notes.vacuum().unwrap();
+        let after = db.notes.database_size_bytes().unwrap();
+        assert!(after < before / 10, "{} -> {} bytes", before, after);
+        assert_eq!(after, std::fs::metadata(&path).unwrap().len());
+    }
+
+    #[test]
+    fn locking_a_note_keeps_replace_in_all_away_from_it() {
+        let (_dir, db) = temp_database();
+        let note = note_with("Reference", "cat", 100);
+        db.notes.create_note(&note).unwrap();
+
+        assert!(db.notes.set_note_locked(note.id, true).unwrap());
+        assert!(!db.notes.set_note_locked(note.id, true).unwrap());
+        assert!(db.notes.get_note(note.id).unwrap().unwrap().locked);
+        assert_eq!(db.notes.replace_in_all("a", "o", true).unwrap().notes, 0);
+
+        assert!(db.notes.set_note_locked(note.id, false).unwrap());
+        assert!(!db.notes.get_note(note.id).unwrap().unwrap().locked);
+        assert_eq!(db.notes.replace_in_all("a", "o", true).unwrap().notes, 1);
+    }
+
+    #[test]
+    fn insertion_index_matches_the_order_after_reload() {
+        let (_dir, db) = temp_database();
+        for (title, created_at, updated_at, sort_index) in [
+            ("banana", 100, 900, 30),
+            ("Apple", 300, 400, 10),
+            ("cherry", 200, 100, 20),
+            ("apricot", 400, 700, 40),
+        ] {
+            let mut note = note_with(title, "", created_at);
+            note.updated_at = updated_at;
+            note.sort_index = sort_index;
+            db.notes.create_note(&note).unwrap();
+        }
+
+        let mut added = note_with("Blueberry", "", 250);
+        added.updated_at = 500;
+        added.sort_index = 15;
+        let before: Vec<Vec<Note>> = ALL_ORDERS
+            .iter()
+            .map(|&order| db.notes.list_notes(order).unwrap())
+            .collect();
+        db.notes.create_note(&added).unwrap();
+
+        for (order, mut in_session) in ALL_ORDERS.into_iter().zip(before) {
+            let index = order.insertion_index(&in_session, &added);
+            in_session.insert(index, added.clone());
+
+            let ids = |notes: &[Note]| notes.iter().map(|note| note.id).collect::<Vec<_>>();
+            let reloaded = db.notes.list_notes(order).unwrap();
+            assert_eq!(ids(&in_session), ids(&reloaded), "{:?}", order);
+        }
+    }
+
+    const ALL_ORDERS: [SortOrder; 8] = [
+        SortOrder::Manual,
+        SortOrder::ManualReversed,
+        SortOrder::CreatedAscending,
+        SortOrder::CreatedDescending,
+        SortOrder::UpdatedAscending,
+        SortOrder::UpdatedDescending,
+        SortOrder::TitleAscending,
+        SortOrder::TitleDescending,
+    ];
+
+    #[test]
+    fn starred_notes_are_listed_without_reordering() {
+        let (_dir, db) = temp_database();
+        let first = note_with("first", "", 100);
+        let second = note_with("second", "", 200);
+        let third = note_with("third", "", 300);
+        for note in [&first, &second, &third] {
+            db.notes.create_note(note).unwrap();
+        }
+
+        assert!(db.notes.set_note_starred(first.id, true).unwrap());
+        assert!(db.notes.set_note_starred(third.id, true).unwrap());
+        assert!(!db.notes.set_note_starred(third.id, true).unwrap());
+
+        let titles = |notes: Vec<Note>| -> Vec<String> {
+            notes.into_iter().map(|note| note.title).collect()
+        };
+        assert_eq!(titles(db.notes.list_starred(SortOrder::Manual).unwrap()), ["third", "first"]);
+        assert_eq!(
+            titles(db.notes.list_notes(SortOrder::Manual).unwrap()),
+            ["third", "second", "first"]
+        );
+
+        assert!(db.notes.set_note_starred(third.id, false).unwrap());
+        assert_eq!(titles(db.notes.list_starred(SortOrder::Manual).unwrap()), ["first"]);
+    }
+
+    #[test]
+    fn inserted_notes_stream_back_in_order() {
+        let (_dir, db) = temp_database();
+        let existing = note_with("existing", "keep me", 100);
+        db.notes.create_note(&existing).unwrap();
+
+        let mut clash = existing.clone();
+        clash.content = "overwritten".to_string();
+        let imported = [clash, note_with("second", "", 200), note_with("third", "", 300)];
+        assert_eq!(db.notes.insert_notes(imported).unwrap(), 2);
+        assert_eq!(db.notes.get_note(existing.id).unwrap().unwrap().content, "keep me");
+
+        let mut titles = Vec::new();
+        let visited = db
+            .notes
+            .for_each_note(SortOrder::CreatedAscending, |note| {
+                titles.push(note.title);
+                true
+            })
+            .unwrap();
+        assert_eq!(visited, 3);
+        assert_eq!(titles, ["existing", "second", "third"]);
+
+        let visited = db
+            .notes
+            .for_each_note(SortOrder::CreatedAscending, |_| false)
+            .unwrap();
+        assert_eq!(visited, 1);
+    }
+
+    #[test]
+    fn duplicates_need_matching_title_and_content() {
+        let (_dir, db) = temp_database();
+        let original = note_with("Groceries", "milk", 100);
+        let copy = note_with("Groceries", "milk", 300);
+        let other_content = note_with("Groceries", "eggs", 200);
+        let other_title = note_with("Shopping", "milk", 250);
+        let trashed_copy = note_with("Groceries", "milk", 400);
+        for note in [&copy, &other_content, &original, &other_title, &trashed_copy] {
+            db.notes.create_note(note).unwrap();
+        }
+        db.notes.trash_note(trashed_copy.id).unwrap();
+
+        assert_eq!(db.notes.find_duplicates().unwrap(), vec![vec![original.id, copy.id]]);
+
+        db.notes.trash_note(copy.id).unwrap();
+        assert!(db.notes.find_duplicates().unwrap().is_empty());
+    }
+
+    #[test]
+    fn get_notes_keeps_the_requested_order_and_skips_missing_ids() {
+        let (_dir, db) = temp_database();
+        let first = note_with("first", "", 100);
+        let second = note_with("second", "", 200);
+        db.notes.create_note(&first).unwrap();
+        db.notes.create_note(&second).unwrap();
+
+        let missing = Uuid::new_v4();
+        let notes = db.notes.get_notes(&[second.id, missing, first.id]).unwrap();
+        let titles: Vec<&str> = notes.iter().map(|note| note.title.as_str()).collect();
+        assert_eq!(titles, ["second", "first"]);
+
+        assert!(db.notes.get_notes(&[]).unwrap().is_empty());
+        assert!(db.notes.get_notes(&[missing]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn archived_notes_leave_the_main_list_but_not_the_library() {
+        let (_dir, db) = temp_database();
+        let kept = note_with("kept", "", 100);
+        let archived = note_with("archived", "", 200);
+        for note in [&kept, &archived] {
+            db.notes.create_note(note).unwrap();
+        }
+
+        assert!(db.notes.set_note_archived(archived.id, true).unwrap());
+        assert!(!db.notes.set_note_archived(archived.id, true).unwrap());
+
+        let titles = |notes: Vec<Note>| -> Vec<String> {
+            notes.into_iter().map(|note| note.title).collect()
+        };
+        assert_eq!(titles(db.notes.list_notes(SortOrder::Manual).unwrap()), ["kept"]);
+        assert_eq!(titles(db.notes.list_archived(SortOrder::Manual).unwrap()), ["archived"]);
+        assert!(db.notes.list_trashed().unwrap().is_empty());
+
+        db.notes.trash_note(archived.id).unwrap();
+        assert!(db.notes.list_archived(SortOrder::Manual).unwrap().is_empty());
+        db.notes.restore_note(archived.id).unwrap();
+        assert!(db.notes.get_note(archived.id).unwrap().unwrap().archived);
+    }
+
+    #[test]
+    fn get_note_returns_none_for_unknown_id() {
+        let (_dir, db) = temp_database();
+        assert!(db.notes.get_note(Uuid::new_v4()).unwrap().is_none());
+    }
+
+    #[test]
+    fn update_note_persists_changes() {
+        let (_dir, db) = temp_database();
+        let mut note = note_with("Draft", "", 1_700_000_000);
+        db.notes.create_note(&note).unwrap();
+
+        note.title = "Final".to_string();
+        note.content = "done".to_string();
+        assert!(db.notes.update_note(&note).unwrap());
+
+        let stored = db.notes.get_note(note.id).unwrap().unwrap();
+        assert_eq!(stored.title, "Final");
+        assert_eq!(stored.content, "done");
+        assert!(stored.updated_at >= note.created_at);
+    }
+
+    #[test]
+    fn unchanged_saves_do_not_write() {
+        let (_dir, db) = temp_database();
+        let note = note_with("Same", "text", 1_700_000_000);
+        db.notes.create_note(&note).unwrap();
+
+        assert!(!db.notes.update_note(&note).unwrap());
+        assert!(!db.notes.update_note_content(note.id, "text").unwrap());
+        assert!(!db.notes.update_note_title(note.id, "Same").unwrap());
+
+        let stored = db.notes.get_note(note.id).unwrap().unwrap();
+        assert_eq!(stored.updated_at, 1_700_000_000);
+    }
+
+    #[test]
+    fn delete_note_removes_it() {
+        let (_dir, db) = temp_database();
+        let note = note_with("Gone", "", 1_700_000_000);
+        db.notes.create_note(&note).unwrap();
+
+        db.notes.delete_note(note.id).unwrap();
+        assert!(db.notes.get_note(note.id).unwrap().is_none());
+        assert!(db.notes.list_notes(SortOrder::Manual).unwrap().is_empty());
+    }
+
+    #[test]
+    fn note_exists_tracks_existing_trashed_and_deleted_rows() {
+        let (_dir, db) = temp_database();
+        let note = note_with("Here", "", 1_700_000_000);
+        db.notes.create_note(&note).unwrap();
+        assert!(db.notes.note_exists(note.id).unwrap());
+
+        db.notes.trash_note(note.id).unwrap();
+        assert!(db.notes.note_exists(note.id).unwrap());
+
+        db.notes.delete_note(note.id).unwrap();
+        assert!(!db.notes.note_exists(note.id).unwrap());
+
+        assert!(!db.notes.note_exists(Uuid::new_v4()).unwrap());
+    }
+
+    #[test]
+    fn list_notes_is_newest_first_and_skips_trash() {
+        let (_dir, db) = temp_database();
+        let older = note_with("Older", "", 100);
+        let newer = note_with("Newer", "", 200);
+        let trashed = note_with("Trashed", "", 300);
+        for note in [&older, &newer, &trashed] {
+            db.notes.create_note(note).unwrap();
+        }
+        db.notes.trash_note(trashed.id).unwrap();
+
+        let titles: Vec<String> = db
+            .notes
+            .list_notes(SortOrder::Manual)
+            .unwrap()
+            .into_iter()
+            .map(|note| note.title)
+            .collect();
+        assert_eq!(titles, ["Newer", "Older"]);
+        assert_eq!(db.notes.count_trashed().unwrap(), 1);
+    }
+
+    #[test]
+    fn moving_notes_to_the_edges_reorders_the_list() {
+        let (_dir, db) = temp_database();
+        let first = Note::new("First".to_string());
+        let second = Note::new("Second".to_string());
+        let third = Note::new("Third".to_string());
+        for (index, note) in [&first, &second, &third].into_iter().enumerate() {
+            let mut note = note.clone();
+            note.sort_index = index as i64;
+            db.notes.create_note(&note).unwrap();
+        }
+
+        let titles = |db: &Database| -> Vec<String> {
+            db.notes
+                .list_notes(SortOrder::Manual)
+                .unwrap()
+                .into_iter()
+                .map(|note| note.title)
+                .collect()
+        };
+        assert_eq!(titles(&db), ["First", "Second", "Third"]);
+
+        db.notes.move_note_to_top(third.id).unwrap();
+        assert_eq!(titles(&db), ["Third", "First", "Second"]);
+
+        db.notes.move_note_to_bottom(first.id).unwrap();
+        assert_eq!(titles(&db), ["Third", "Second", "First"]);
+    }
+
+    #[test]
+    fn each_sort_order_returns_its_sequence() {
+        let (_dir, db) = temp_database();
+        let mut banana = note_with("banana", "", 100);
+        banana.updated_at = 600;
+        banana.sort_index = 2;
+        let mut apple = note_with("Apple", "", 200);
+        apple.updated_at = 400;
+        apple.sort_index = 3;
+        let mut cherry = note_with("cherry", "", 300);
+        cherry.updated_at = 500;
+        cherry.sort_index = 1;
+        for note in [&banana, &apple, &cherry] {
+            db.notes.create_note(note).unwrap();
+        }
+
+        let titles = |order: SortOrder| -> Vec<String> {
+            db.notes
+                .list_notes(order)
+                .unwrap()
+                .into_iter()
+                .map(|note| note.title)
+                .collect()
+        };
+        assert_eq!(titles(SortOrder::Manual), ["cherry", "banana", "Apple"]);
+        assert_eq!(titles(SortOrder::ManualReversed), ["Apple", "banana", "cherry"]);
+        assert_eq!(titles(SortOrder::CreatedAscending), ["banana", "Apple", "cherry"]);
+        assert_eq!(titles(SortOrder::CreatedDescending), ["cherry", "Apple", "banana"]);
+        assert_eq!(titles(SortOrder::UpdatedAscending), ["Apple", "cherry", "banana"]);
+        assert_eq!(titles(SortOrder::UpdatedDescending), ["banana", "cherry", "Apple"]);
+        assert_eq!(titles(SortOrder::TitleAscending), ["Apple", "banana", "cherry"]);
+        assert_eq!(titles(SortOrder::TitleDescending), ["cherry", "banana", "Apple"]);
+    }
+
+    #[test]
+    fn text_timestamps_are_coerced_to_integers() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute(
+                "CREATE TABLE notes (
+                    id TEXT PRIMARY KEY,
+                    title TEXT NOT NULL,
+                    content TEXT NOT NULL,
+                    created_at TEXT NOT NULL,
+                    deleted_at INTEGER,
+                    monospace INTEGER NOT NULL DEFAULT 1,
+                    updated_at INTEGER,
+                    encrypted INTEGER NOT NULL DEFAULT 0,
+                    sort_index INTEGER,
+                    locked INTEGER NOT NULL DEFAULT 0,
+                    starred INTEGER NOT NULL DEFAULT 0,
+                    archived INTEGER NOT NULL DEFAULT 0
+                )",
+                [],
+            )
+            .unwrap();
+
+        let id = Uuid::new_v4();
+        connection
+            .execute(
+                "INSERT INTO notes (id, title, content, created_at) VALUES (?1, 'Legacy', '', '1700000000')",
+                [id.to_string()],
+            )
+            .unwrap();
+
+        let notes = NoteRepository::new(Arc::new(Mutex::new(connection)));
+
+        let note = notes.get_note(id).unwrap().unwrap();
+        assert_eq!(note.created_at, 1_700_000_000);
+        assert_eq!(note.updated_at, 1_700_000_000);
+
+        let listed = notes.list_notes(SortOrder::Manual).unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].created_at, 1_700_000_000);
+    }
+
+    #[test]
+    fn replace_in_all_leaves_encrypted_notes_alone() {
+        let (_dir, db) = temp_database();
+        let plain = note_with("Plain", "cat", 100);
+        db.notes.create_note(&plain).unwrap();
+
+        let secret = note_with("Secret", "", 200);
+        db.notes.create_note(&secret).unwrap();
+        let ciphertext = crate::models::encrypt_content("pw", "cat").unwrap();
+        db.notes
+            .set_note_encryption(secret.id, true, &ciphertext)
+            .unwrap();
+
+        let replaced = db.notes.replace_in_all("a", "o", true).unwrap();
+        assert_eq!(
+            replaced,
+            Replacements {
+                notes: 1,
+                occurrences: 1
+            }
+        );
+
+        let stored = db.notes.get_note(secret.id).unwrap().unwrap();
+        assert!(stored.encrypted);
+        assert_eq!(stored.content, ciphertext);
+        assert_eq!(db.notes.get_note(plain.id).unwrap().unwrap().content, "cot");
+    }
+
+    #[test]
+    fn replace_in_all_leaves_trashed_notes_alone() {
+        let (_dir, db) = temp_database();
+        db.notes.create_note(&note_with("Kept", "cat", 100)).unwrap();
+        let trashed = note_with("Trashed", "cat", 200);
+        db.notes.create_note(&trashed).unwrap();
+        db.notes.trash_note(trashed.id).unwrap();
+        let before = db.notes.get_note(trashed.id).unwrap().unwrap();
+
+        let replaced = db.notes.replace_in_all("a", "o", true).unwrap();
+        assert_eq!(replaced.notes, 1);
+
+        let after = db.notes.get_note(trashed.id).unwrap().unwrap();
+        assert_eq!(after.content, "cat");
+        assert_eq!(after.updated_at, before.updated_at);
+    }
+
+    #[test]
+    fn replace_in_all_counts_occurrences_and_bumps_updated_at() {
+        let (_dir, db) = temp_database();
+        let note = note_with("Cats", "cat and cat", 100);
+        db.notes.create_note(&note).unwrap();
+        db.notes.create_note(&note_with("Dogs", "dog", 200)).unwrap();
+
+        let replaced = db.notes.replace_in_all("CAT", "dog", false).unwrap();
+        assert_eq!(
+            replaced,
+            Replacements {
+                notes: 1,
+                occurrences: 2
+            }
+        );
+
+        let stored = db.notes.get_note(note.id).unwrap().unwrap();
+        assert_eq!(stored.content, "dog and dog");
+        assert!(stored.updated_at > note.updated_at);
+    }
+
+    #[test]
+    fn rows_with_invalid_ids_are_skipped() {
+        let (_dir, db) = temp_database();
+        let note = note_with("Valid", "", 200);
+        db.notes.create_note(&note).unwrap();
+
+        {
+            let connection = db.notes.connection.lock().unwrap();
+            for bad_id in ["not-a-uuid", "also-bad"] {
+                connection
+                    .execute(
+                        "INSERT INTO notes (id, title, content, created_at) VALUES (?1, 'Corrupt', '', 100)",
+                        [bad_id],
+                    )
+                    .unwrap();
+            }
+        }
+
+        let notes = db.notes.list_notes(SortOrder::Manual).unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].id, note.id);
+        assert!(notes.iter().all(|note| !note.id.is_nil()));
+        assert!(db.notes.get_note(Uuid::nil()).unwrap().is_none());
+    }
+
+    #[test]
+    fn rows_with_a_null_title_are_listed_untitled() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("notes.db");
+        let id = Uuid::new_v4();
+        {
+            let connection = Connection::open(&path).unwrap();
+            connection
+                .execute_batch(
+                    "CREATE TABLE notes (
+                        id TEXT PRIMARY KEY,
+                        title TEXT,
+                        content TEXT NOT NULL,
+                        created_at INTEGER NOT NULL
+                    )",
+                )
+                .unwrap();
+            connection
+                .execute(
+                    "INSERT INTO notes (id, title, content, created_at) VALUES (?1, NULL, 'body', 100)",
+                    [id.to_string()],
+                )
+                .unwrap();
+        }
+
+        let db = Database::new(&path).unwrap();
+        let notes = db.notes.list_notes(SortOrder::Manual).unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].id, id);
+        assert_eq!(notes[0].title, "");
+        assert_eq!(notes[0].content, "body");
+        assert!(db.notes.find_duplicates().unwrap().is_empty());
+    }
 }
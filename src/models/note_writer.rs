@@ -0,0 +1,434 @@
+use futures::channel::mpsc::UnboundedSender;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::models::{CryptoError, Database, encrypt_content};
+
+pub enum WriteCommand {
+    SaveContent {
+        note_id: Uuid,
+        content: String,
+        passphrase: Option<String>,
+    },
+    SetEncryption {
+        note_id: Uuid,
+        content: String,
+        passphrase: Option<String>,
+    },
+    Update {
+        note_id: Uuid,
+        change: NoteChange,
+    },
+    /// Answered once every write queued before it has been applied.
+    Flush { done: Sender<()> },
+    /// Replaces the rewrite applied to content before it's saved.
+    SetTransform { transform: Option<ContentTransform> },
+}
+
+impl WriteCommand {
+    /// Whether the command changes the database, as counted by
+    /// `NoteWriter::has_pending_writes`.
+    fn is_write(&self) -> bool {
+        matches!(
+            self,
+            Self::SaveContent { .. } | Self::SetEncryption { .. } | Self::Update { .. }
+        )
+    }
+}
+
+/// A change to a note other than its content.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NoteChange {
+    Title(String),
+    Monospace(bool),
+    Locked(bool),
+    Starred(bool),
+    Archived(bool),
+    MoveToTop,
+    MoveToBottom,
+    Trash,
+}
+
+/// Rewrites a note's text on the writer thread just before it's saved.
+pub type ContentTransform = Box<dyn Fn(&str) -> String + Send>;
+
+pub enum WriteOutcome {
+    /// `written` is false when the stored content already matched and the
+    /// write was skipped.
+    Saved {
+        note_id: Uuid,
+        content: String,
+        written: bool,
+    },
+    /// The note was removed from the database behind the app's back, so the
+    /// write had nothing to update. `content` is what was being saved.
+    Missing { note_id: Uuid, content: String },
+    EncryptionChanged { note_id: Uuid, encrypted: bool },
+    Updated { note_id: Uuid, change: NoteChange },
+    /// A `NoteChange` that didn't reach the database, so what the app shows
+    /// for the note is out of date.
+    UpdateFailed { note_id: Uuid, message: String },
+    Failed { note_id: Uuid, message: String },
+}
+
+/// Applies note writes on a dedicated thread so a slow disk never blocks
+/// the UI. Consecutive saves of the same note are coalesced into one write.
+#[derive(Clone)]
+pub struct NoteWriter {
+    sender: Sender<WriteCommand>,
+    pending: Arc<AtomicUsize>,
+}
+
+impl NoteWriter {
+    pub fn spawn(db: Arc<Database>, outcomes: UnboundedSender<WriteOutcome>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let pending = Arc::new(AtomicUsize::new(0));
+
+        let worker_pending = Arc::clone(&pending);
+        thread::Builder::new()
+            .name("tap-db-writer".into())
+            .spawn(move || run_writer(db, receiver, outcomes, worker_pending))
+            .expect("Failed to spawn database writer thread");
+
+        Self { sender, pending }
+    }
+
+    /// Saves `content`, encrypting it first when a passphrase is given.
+    pub fn save_content(&self, note_id: Uuid, content: String, passphrase: Option<String>) {
+        self.send(WriteCommand::SaveContent {
+            note_id,
+            content,
+            passphrase,
+        });
+    }
+
+    pub fn update(&self, note_id: Uuid, change: NoteChange) {
+        self.send(WriteCommand::Update { note_id, change });
+    }
+
+    /// Runs `transform` over the content of every later save, or stops
+    /// rewriting content when it is `None`. Encryption changes store their
+    /// content as given.
+    pub fn set_transform(&self, transform: Option<ContentTransform>) {
+        if self.sender.send(WriteCommand::SetTransform { transform }).is_err() {
+            eprintln!("Database writer has stopped, cannot change its transform");
+        }
+    }
+
+    /// Encrypts the note with `passphrase`, or stores it as plaintext when
+    /// `passphrase` is `None`.
+    pub fn set_encryption(&self, note_id: Uuid, content: String, passphrase: Option<String>) {
+        self.send(WriteCommand::SetEncryption {
+            note_id,
+            content,
+            passphrase,
+        });
+    }
+
+    fn send(&self, command: WriteCommand) {
+        self.pending.fetch_add(1, Ordering::SeqCst);
+        if self.sender.send(command).is_err() {
+            self.pending.fetch_sub(1, Ordering::SeqCst);
+            eprintln!("Database writer has stopped, dropping write");
+        }
+    }
+
+    pub fn has_pending_writes(&self) -> bool {
+        self.pending.load(Ordering::SeqCst) > 0
+    }
+
+    /// Blocks until every write queued before the call has been applied, or
+    /// `timeout` has passed. Returns whether they were. The writer thread
+    /// answers when it gets there, so nothing polls in the meantime.
+    pub fn flush(&self, timeout: Duration) -> bool {
+        if !self.has_pending_writes() {
+            return true;
+        }
+        let (done, applied) = mpsc::channel();
+        // Not counted as pending: it writes nothing.
+        if self.sender.send(WriteCommand::Flush { done }).is_err() {
+            eprintln!("Database writer has stopped, cannot flush");
+            return false;
+        }
+        applied.recv_timeout(timeout).is_ok()
+    }
+}
+
+fn run_writer(
+    db: Arc<Database>,
+    receiver: Receiver<WriteCommand>,
+    outcomes: UnboundedSender<WriteOutcome>,
+    pending: Arc<AtomicUsize>,
+) {
+    let mut transform: Option<ContentTransform> = None;
+    while let Ok(first) = receiver.recv() {
+        let mut batch = vec![first];
+        batch.extend(receiver.try_iter());
+        let received = batch.iter().filter(|command| command.is_write()).count();
+
+        let mut flushes = Vec::new();
+        let mut latest: Vec<(Uuid, String, Option<String>)> = Vec::new();
+        for command in batch {
+            match command {
+                WriteCommand::SaveContent {
+                    note_id,
+                    content,
+                    passphrase,
+                } => match latest.iter_mut().find(|(id, _, _)| *id == note_id) {
+                    Some(entry) => {
+                        entry.1 = content;
+                        entry.2 = passphrase;
+                    }
+                    None => latest.push((note_id, content, passphrase)),
+                },
+                WriteCommand::SetEncryption {
+                    note_id,
+                    content,
+                    passphrase,
+                } => {
+                    latest.retain(|(id, _, _)| *id != note_id);
+                    let outcome = apply_encryption(&db, note_id, &content, passphrase.as_deref());
+                    let _ = outcomes.unbounded_send(outcome);
+                }
+                WriteCommand::Update { note_id, change } => {
+                    let _ = outcomes.unbounded_send(apply_change(&db, note_id, change));
+                }
+                WriteCommand::Flush { done } => flushes.push(done),
+                WriteCommand::SetTransform {
+                    transform: replacement,
+                } => transform = replacement,
+            }
+        }
+
+        for (note_id, content, passphrase) in latest {
+            let content = match &transform {
+                Some(transform) => transform(&content),
+                None => content,
+            };
+            let outcome = save_content(&db, note_id, content, passphrase.as_deref());
+            let _ = outcomes.unbounded_send(outcome);
+        }
+
+        pending.fetch_sub(received, Ordering::SeqCst);
+        for done in flushes {
+            let _ = done.send(());
+        }
+    }
+}
+
+fn stored_content(content: &str, passphrase: Option<&str>) -> Result<String, CryptoError> {
+    match passphrase {
+        Some(passphrase) => encrypt_content(passphrase, content),
+        None => Ok(content.to_string()),
+    }
+}
+
+fn save_content(
+    db: &Database,
+    note_id: Uuid,
+    content: String,
+    passphrase: Option<&str>,
+) -> WriteOutcome {
+    let stored = match stored_content(&content, passphrase) {
+        Ok(stored) => stored,
+        Err(e) => {
+            return WriteOutcome::Failed {
+                note_id,
+                message: format!("Failed to encrypt note: {}", e),
+            };
+        }
+    };
+
+    match db.notes.update_note_content(note_id, &stored) {
+        Ok(true) => WriteOutcome::Saved {
+            note_id,
+            content,
+            written: true,
+        },
+        Ok(false) => match db.notes.note_exists(note_id) {
+            Ok(true) => WriteOutcome::Saved {
+                note_id,
+                content,
+                written: false,
+            },
+            Ok(false) => WriteOutcome::Missing { note_id, content },
+            Err(e) => WriteOutcome::Failed {
+                note_id,
+                message: format!("Failed to save note: {}", e),
+            },
+        },
+        Err(e) => WriteOutcome::Failed {
+            note_id,
+            message: format!("Failed to save note: {}", e),
+        },
+    }
+}
+
+fn apply_encryption(
+    db: &Database,
+    note_id: Uuid,
+    content: &str,
+    passphrase: Option<&str>,
+) -> WriteOutcome {
+    let stored = match stored_content(content, passphrase) {
+        Ok(stored) => stored,
+        Err(e) => {
+            return WriteOutcome::Failed {
+                note_id,
+                message: format!("Failed to encrypt note: {}", e),
+            };
+        }
+    };
+
+    let encrypted = passphrase.is_some();
+    match db.notes.set_note_encryption(note_id, encrypted, &stored) {
+        Ok(()) => WriteOutcome::EncryptionChanged { note_id, encrypted },
+        Err(e) => WriteOutcome::Failed {
+            note_id,
+            message: format!("Failed to change note encryption: {}", e),
+        },
+    }
+}
+
+fn apply_change(db: &Database, note_id: Uuid, change: NoteChange) -> WriteOutcome {
+    let notes = &db.notes;
+    let result = match &change {
+        NoteChange::Title(title) => notes.update_note_title(note_id, title).map(drop),
+        NoteChange::Monospace(monospace) => notes.set_note_monospace(note_id, *monospace).map(drop),
+        NoteChange::Locked(locked) => notes.set_note_locked(note_id, *locked).map(drop),
+        NoteChange::Starred(starred) => notes.set_note_starred(note_id, *starred).map(drop),
+        NoteChange::Archived(archived) => notes.set_note_archived(note_id, *archived).map(drop),
+        NoteChange::MoveToTop => notes.move_note_to_top(note_id),
+        NoteChange::MoveToBottom => notes.move_note_to_bottom(note_id),
+        NoteChange::Trash => notes.trash_note(note_id),
+    };
+
+    match result {
+        Ok(()) => WriteOutcome::Updated { note_id, change },
+        Err(e) => {
+            let action = match change {
+                NoteChange::Title(_) => "update note title",
+                NoteChange::Monospace(_) => "update note font",
+                NoteChange::MoveToTop | NoteChange::MoveToBottom => "move note",
+                NoteChange::Trash => "delete note",
+                _ => "update note",
+            };
+            WriteOutcome::UpdateFailed {
+                note_id,
+                message: format!("Failed to {}: {}", action, e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Note, SortOrder};
+    use futures::channel::mpsc::unbounded;
+    use tempfile::TempDir;
+
+    #[test]
+    fn flush_returns_once_queued_writes_are_on_disk() {
+        let dir = TempDir::new().unwrap();
+        let db = Arc::new(Database::new(dir.path().join("notes.db")).unwrap());
+        let note = Note::new("Draft".to_string());
+        db.notes.create_note(&note).unwrap();
+
+        let (outcomes_sender, _outcomes) = unbounded();
+        let writer = NoteWriter::spawn(Arc::clone(&db), outcomes_sender);
+        assert!(writer.flush(Duration::ZERO));
+
+        for revision in 0..50 {
+            writer.save_content(note.id, format!("revision {}", revision), None);
+        }
+        assert!(writer.flush(Duration::from_secs(5)));
+        assert!(!writer.has_pending_writes());
+
+        let saved = db.notes.get_note(note.id).unwrap().unwrap();
+        assert_eq!(saved.content, "revision 49");
+    }
+
+    #[test]
+    fn saves_store_the_transformed_content() {
+        let dir = TempDir::new().unwrap();
+        let db = Arc::new(Database::new(dir.path().join("notes.db")).unwrap());
+        let note = Note::new("Draft".to_string());
+        db.notes.create_note(&note).unwrap();
+
+        let (outcomes_sender, _outcomes) = unbounded();
+        let writer = NoteWriter::spawn(Arc::clone(&db), outcomes_sender);
+        let stored = || db.notes.get_note(note.id).unwrap().unwrap().content;
+
+        writer.set_transform(Some(Box::new(|content| content.trim_end().to_string())));
+        writer.save_content(note.id, "typed   ".to_string(), None);
+        assert!(writer.flush(Duration::from_secs(5)));
+        assert_eq!(stored(), "typed");
+
+        writer.set_transform(None);
+        writer.save_content(note.id, "kept   ".to_string(), None);
+        assert!(writer.flush(Duration::from_secs(5)));
+        assert_eq!(stored(), "kept   ");
+    }
+
+    #[test]
+    fn updates_are_applied_and_reported() {
+        let dir = TempDir::new().unwrap();
+        let db = Arc::new(Database::new(dir.path().join("notes.db")).unwrap());
+        let note = Note::new("Draft".to_string());
+        db.notes.create_note(&note).unwrap();
+
+        let (outcomes_sender, mut outcomes) = unbounded();
+        let writer = NoteWriter::spawn(Arc::clone(&db), outcomes_sender);
+        writer.update(note.id, NoteChange::Title("Renamed".to_string()));
+        writer.update(note.id, NoteChange::Starred(true));
+        assert!(writer.flush(Duration::from_secs(5)));
+
+        let stored = db.notes.get_note(note.id).unwrap().unwrap();
+        assert_eq!(stored.title, "Renamed");
+        assert!(stored.starred);
+        assert!(matches!(
+            outcomes.try_recv().unwrap(),
+            WriteOutcome::Updated { change: NoteChange::Title(_), .. }
+        ));
+
+        writer.update(note.id, NoteChange::Trash);
+        assert!(writer.flush(Duration::from_secs(5)));
+        assert!(db.notes.list_notes(SortOrder::Manual).unwrap().is_empty());
+        assert_eq!(db.notes.count_trashed().unwrap(), 1);
+    }
+
+    #[test]
+    fn saving_an_externally_deleted_note_reports_it_missing() {
+        let dir = TempDir::new().unwrap();
+        let db = Arc::new(Database::new(dir.path().join("notes.db")).unwrap());
+        let note = Note::new("Ghost".to_string());
+        db.notes.create_note(&note).unwrap();
+
+        let (outcomes_sender, mut outcomes) = unbounded();
+        let writer = NoteWriter::spawn(Arc::clone(&db), outcomes_sender);
+
+        writer.save_content(note.id, "still here".to_string(), None);
+        assert!(writer.flush(Duration::from_secs(5)));
+        let outcome = outcomes.try_recv().unwrap();
+        assert!(matches!(outcome, WriteOutcome::Saved { note_id, .. } if note_id == note.id));
+
+        // Another process removes the row.
+        let external = Database::new(dir.path().join("notes.db")).unwrap();
+        external.notes.delete_note(note.id).unwrap();
+
+        writer.save_content(note.id, "edited after delete".to_string(), None);
+        assert!(writer.flush(Duration::from_secs(5)));
+        match outcomes.try_recv().unwrap() {
+            WriteOutcome::Missing { note_id, content } => {
+                assert_eq!(note_id, note.id);
+                assert_eq!(content, "edited after delete");
+            }
+            _ => panic!("expected the write to report the note missing"),
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::Path;
+use uuid::Uuid;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    #[serde(default)]
+    pub open_note_ids: Vec<Uuid>,
+    #[serde(default)]
+    pub active_note_id: Option<Uuid>,
+}
+
+impl Session {
+    pub fn load(path: &Path) -> Self {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Self::default(),
+            Err(e) => {
+                eprintln!("Failed to read session from {:?}: {}", path, e);
+                return Self::default();
+            }
+        };
+
+        serde_json::from_str(&contents).unwrap_or_else(|e| {
+            eprintln!("Ignoring unreadable session at {:?}: {}", path, e);
+            Self::default()
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), io::Error> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let json = serde_json::to_string_pretty(self)
+            .map_err(io::Error::other)?;
+        std::fs::write(path, json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn saves_and_loads_open_tabs() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("session.json");
+        let session = Session {
+            open_note_ids: vec![Uuid::new_v4(), Uuid::new_v4()],
+            active_note_id: Some(Uuid::new_v4()),
+        };
+
+        session.save(&path).unwrap();
+        assert_eq!(Session::load(&path), session);
+    }
+
+    #[test]
+    fn missing_or_corrupt_sessions_load_empty() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("session.json");
+        assert_eq!(Session::load(&path), Session::default());
+
+        std::fs::write(&path, "{ not json").unwrap();
+        assert_eq!(Session::load(&path), Session::default());
+    }
+}
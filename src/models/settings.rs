@@ -0,0 +1,462 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::io;
+use std::path::Path;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::models::normalize_newlines;
+
+/// Used when `date_format` is not a valid pattern.
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SidebarDensity {
+    Compact,
+    #[default]
+    Comfortable,
+}
+
+/// Which end of the manually ordered note list a new note joins.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NewNotePlacement {
+    #[default]
+    Top,
+    Bottom,
+}
+
+/// Text a new note can start from, offered by the "+" button.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NoteTemplate {
+    pub name: String,
+    pub content: String,
+}
+
+/// What the Tab key inserts and how new indentation is written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IndentStyle {
+    Spaces(usize),
+    Tab,
+    /// Follows whatever the current note already uses.
+    Auto,
+}
+
+impl Default for IndentStyle {
+    fn default() -> Self {
+        Self::Spaces(4)
+    }
+}
+
+/// Line endings written to exported text files.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+    /// `\r\n` on Windows, `\n` everywhere else.
+    Platform,
+}
+
+impl LineEnding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::CrLf => "\r\n",
+            Self::Platform if cfg!(windows) => "\r\n",
+            Self::Platform => "\n",
+        }
+    }
+
+    /// `text` with every line break, whatever its style, written as this one.
+    pub fn apply(self, text: &str) -> String {
+        let normalized = normalize_newlines(text);
+        match self.as_str() {
+            "\n" => normalized,
+            ending => normalized.replace('\n', ending),
+        }
+    }
+}
+
+/// Colors, as `0xRRGGBBAA`, for the editor's selection and for text an input
+/// method is still composing.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EditorHighlights {
+    pub selection: u32,
+    /// `None` underlines marked text in the text color, which stays legible
+    /// on any background the text itself is readable on.
+    pub marked_underline: Option<u32>,
+    /// Drawn twice as thick under the clause the input method has selected.
+    pub marked_underline_thickness: f32,
+}
+
+impl Default for EditorHighlights {
+    fn default() -> Self {
+        Self {
+            selection: 0x3311ff30,
+            marked_underline: None,
+            marked_underline_thickness: 1.0,
+        }
+    }
+}
+
+/// How much edit history the editor keeps per note. The oldest entries are
+/// dropped once either limit is passed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UndoLimits {
+    pub max_entries: usize,
+    /// Text removed and inserted by the kept entries, in bytes.
+    pub max_bytes: usize,
+}
+
+impl Default for UndoLimits {
+    fn default() -> Self {
+        Self {
+            max_entries: 500,
+            max_bytes: 4 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub auto_close_brackets: bool,
+    /// Pasting a URL over selected text inserts `[selection](url)`.
+    pub paste_url_as_link: bool,
+    pub indent_style: IndentStyle,
+    /// Columns between tab stops when displaying and measuring tabs.
+    pub tab_width: usize,
+    pub editor_font_size: f32,
+    /// Scale applied on top of `editor_font_size` by the zoom shortcuts.
+    pub editor_zoom: f32,
+    pub line_spacing: f32,
+    pub paragraph_spacing: f32,
+    pub vim_mode: bool,
+    pub trash_retention_days: u64,
+    pub auto_title_from_content: bool,
+    pub content_padding: f32,
+    pub focus_width: Option<f32>,
+    /// Title given to a note whose title is cleared and has no earlier one.
+    /// Set in settings.json; the settings panel doesn't edit it.
+    pub default_title: String,
+    /// Longest title, in characters as displayed, a note can be given.
+    pub max_title_length: usize,
+    pub sidebar_density: SidebarDensity,
+    pub new_note_placement: NewNotePlacement,
+    pub note_templates: Vec<NoteTemplate>,
+    /// Clicking "+" asks which template to start from whenever templates
+    /// exist. When off, only an Option-click asks.
+    pub choose_template_for_new_notes: bool,
+    /// Permanently delete a note on switching away from it while it is still
+    /// empty and carries a placeholder title. Off by default, since the note
+    /// skips the trash.
+    pub discard_empty_untitled_notes: bool,
+    /// Set once the first-launch tour is finished or skipped.
+    pub onboarding_completed: bool,
+    /// strftime-style pattern for displayed and inserted timestamps.
+    pub date_format: String,
+    pub editor_highlights: EditorHighlights,
+    pub export_line_ending: LineEnding,
+    /// Shows a rendered markdown preview beside the editor.
+    pub split_preview: bool,
+    pub undo_limits: UndoLimits,
+    /// Ids of the save transforms run over a note's text whenever it is saved.
+    pub save_transforms: Vec<String>,
+    /// Ask before closing the window while note writes are still queued.
+    /// When off, the window closes once the wait for them runs out.
+    pub confirm_close_with_unsaved_edits: bool,
+    /// Minutes between background checkpoints of the database; 0 turns
+    /// them off. Read at launch.
+    pub maintenance_interval_minutes: u64,
+    /// Reading speed behind the reading time in the note info panel. 0 is
+    /// read as 1 word a minute.
+    pub reading_words_per_minute: usize,
+}
+
+impl Settings {
+    pub fn load(path: &Path) -> Self {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Self::default(),
+            Err(e) => {
+                eprintln!("Failed to read settings from {:?}: {}", path, e);
+                return Self::default();
+            }
+        };
+
+        match serde_json::from_str(&contents) {
+            Ok(Value::Object(stored)) => Self::from_fields(stored),
+            Ok(_) => {
+                eprintln!("Ignoring settings at {:?}: not a JSON object", path);
+                Self::default()
+            }
+            Err(e) => {
+                eprintln!("Ignoring unreadable settings at {:?}: {}", path, e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Starts from the defaults and takes each stored field that still
+    /// parses, so one field with an outdated shape doesn't discard the rest.
+    /// Fields this build doesn't know are ignored.
+    fn from_fields(stored: Map<String, Value>) -> Self {
+        let Ok(Value::Object(mut fields)) = serde_json::to_value(Self::default()) else {
+            return Self::default();
+        };
+
+        for (key, value) in stored {
+            if !fields.contains_key(&key) {
+                continue;
+            }
+            let mut candidate = fields.clone();
+            candidate.insert(key.clone(), value);
+            if serde_json::from_value::<Self>(Value::Object(candidate.clone())).is_ok() {
+                fields = candidate;
+            } else {
+                eprintln!("Ignoring invalid setting {:?}", key);
+            }
+        }
+
+        serde_json::from_value(Value::Object(fields)).unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), io::Error> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let json = serde_json::to_string_pretty(self)
+            .map_err(io::Error::other)?;
+        std::fs::write(path, json)
+    }
+
+    pub fn editor_text_size(&self) -> f32 {
+        self.editor_font_size * self.editor_zoom
+    }
+
+    pub fn editor_line_height(&self) -> f32 {
+        self.editor_text_size() * self.line_spacing
+    }
+
+    /// Whether `title` is one the app generated rather than one the user
+    /// typed: the configured default or the "Untitled N" given to new notes.
+    pub fn is_placeholder_title(&self, title: &str) -> bool {
+        title == self.default_title
+            || title.strip_prefix("Untitled ").is_some_and(|number| {
+                !number.is_empty() && number.bytes().all(|byte| byte.is_ascii_digit())
+            })
+    }
+
+    /// Picks the title to keep when `candidate` is committed over `existing`:
+    /// the trimmed candidate if it has text, otherwise the existing title,
+    /// otherwise `default_title`. A new candidate is held to
+    /// `max_title_length`.
+    pub fn resolve_title(&self, candidate: &str, existing: &str) -> String {
+        if !candidate.trim().is_empty() {
+            self.limit_title(candidate.trim())
+        } else if !existing.trim().is_empty() {
+            existing.to_string()
+        } else {
+            self.default_title.clone()
+        }
+    }
+
+    /// `title` cut to `max_title_length` grapheme clusters, ending in an
+    /// ellipsis when anything was cut.
+    pub fn limit_title(&self, title: &str) -> String {
+        let max = self.max_title_length.max(1);
+        if title.graphemes(true).nth(max).is_none() {
+            return title.to_string();
+        }
+        let cut = title
+            .grapheme_indices(true)
+            .nth(max - 1)
+            .map_or(title.len(), |(index, _)| index);
+        format!("{}…", title[..cut].trim_end())
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            auto_close_brackets: true,
+            paste_url_as_link: true,
+            indent_style: IndentStyle::Spaces(4),
+            tab_width: 4,
+            editor_font_size: 16.0,
+            editor_zoom: 1.0,
+            line_spacing: 1.25,
+            paragraph_spacing: 0.0,
+            vim_mode: false,
+            trash_retention_days: 30,
+            auto_title_from_content: true,
+            content_padding: 16.0,
+            focus_width: Some(720.0),
+            default_title: "Untitled Note".to_string(),
+            max_title_length: 120,
+            sidebar_density: SidebarDensity::Comfortable,
+            new_note_placement: NewNotePlacement::default(),
+            note_templates: Vec::new(),
+            choose_template_for_new_notes: false,
+            discard_empty_untitled_notes: false,
+            onboarding_completed: false,
+            date_format: DEFAULT_DATE_FORMAT.to_string(),
+            editor_highlights: EditorHighlights::default(),
+            export_line_ending: LineEnding::Lf,
+            split_preview: false,
+            undo_limits: UndoLimits::default(),
+            save_transforms: Vec::new(),
+            confirm_close_with_unsaved_edits: true,
+            maintenance_interval_minutes: 5,
+            reading_words_per_minute: 200,
+        }
+    }
+}
+
+/// Whether chrono understands every specifier in `format`.
+pub fn is_valid_date_format(format: &str) -> bool {
+    !format.trim().is_empty() && !StrftimeItems::new(format).any(|item| item == Item::Error)
+}
+
+/// Formats a Unix timestamp in local time with `format`, falling back to
+/// `DEFAULT_DATE_FORMAT` when the pattern is invalid.
+pub fn format_timestamp(timestamp: u64, format: &str) -> String {
+    match Local.timestamp_opt(timestamp as i64, 0).single() {
+        Some(time) => format_date_time(&time, format),
+        None => String::new(),
+    }
+}
+
+fn format_date_time<Tz: TimeZone>(time: &DateTime<Tz>, format: &str) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    let format = if is_valid_date_format(format) {
+        format
+    } else {
+        DEFAULT_DATE_FORMAT
+    };
+    time.format(format).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholder_titles_are_recognized() {
+        let settings = Settings::default();
+        assert!(settings.is_placeholder_title("Untitled 3"));
+        assert!(settings.is_placeholder_title("Untitled Note"));
+        assert!(!settings.is_placeholder_title("Untitled"));
+        assert!(!settings.is_placeholder_title("Untitled plans"));
+        assert!(!settings.is_placeholder_title("Groceries"));
+    }
+
+    #[test]
+    fn timestamps_follow_the_date_format() {
+        let time = chrono::Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+        assert_eq!(format_date_time(&time, "%Y-%m-%d %H:%M"), "2023-11-14 22:13");
+        assert_eq!(format_date_time(&time, "%-I:%M %p"), "10:13 PM");
+        assert_eq!(format_date_time(&time, "%d/%m/%Y"), "14/11/2023");
+        assert_eq!(format_date_time(&time, "%b %-d, %Y"), "Nov 14, 2023");
+    }
+
+    #[test]
+    fn invalid_date_formats_fall_back_to_the_default() {
+        let time = chrono::Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+        assert!(!is_valid_date_format("%Q"));
+        assert!(!is_valid_date_format("%"));
+        assert!(!is_valid_date_format(""));
+        assert_eq!(format_date_time(&time, "%Q"), "2023-11-14 22:13");
+        assert_eq!(format_date_time(&time, ""), "2023-11-14 22:13");
+    }
+
+    #[test]
+    fn partial_highlight_settings_keep_the_other_defaults() {
+        let settings: Settings =
+            serde_json::from_str(r#"{ "editor_highlights": { "marked_underline": 4278190335 } }"#)
+                .unwrap();
+        assert_eq!(settings.editor_highlights.marked_underline, Some(0xff0000ff));
+        assert_eq!(settings.editor_highlights.selection, 0x3311ff30);
+        assert_eq!(settings.editor_highlights.marked_underline_thickness, 1.0);
+    }
+
+    #[test]
+    fn exports_rewrite_mixed_line_endings() {
+        assert_eq!(LineEnding::Lf.apply("a\r\nb\rc\nd"), "a\nb\nc\nd");
+        assert_eq!(LineEnding::CrLf.apply("a\r\nb\rc\nd"), "a\r\nb\r\nc\r\nd");
+        assert_eq!(LineEnding::CrLf.apply("no breaks"), "no breaks");
+    }
+
+    #[test]
+    fn missing_unknown_and_invalid_fields_fall_back_to_defaults() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("settings.json");
+        std::fs::write(
+            &path,
+            r#"{
+                "vim_mode": true,
+                "tab_width": "wide",
+                "theme": "solarized",
+                "date_format": "%d/%m/%Y"
+            }"#,
+        )
+        .unwrap();
+
+        let settings = Settings::load(&path);
+        assert!(settings.vim_mode);
+        assert_eq!(settings.date_format, "%d/%m/%Y");
+        assert_eq!(settings.tab_width, Settings::default().tab_width);
+        assert_eq!(settings.default_title, "Untitled Note");
+
+        let missing = Settings::load(&dir.path().join("missing.json"));
+        assert!(!missing.vim_mode);
+    }
+
+    #[test]
+    fn resolve_title_keeps_a_valid_candidate() {
+        let settings = Settings::default();
+        assert_eq!(settings.resolve_title("Groceries", "Old"), "Groceries");
+        assert_eq!(settings.resolve_title("  Groceries \n", "Old"), "Groceries");
+    }
+
+    #[test]
+    fn resolve_title_falls_back_to_existing_title() {
+        let settings = Settings::default();
+        assert_eq!(settings.resolve_title("", "Old"), "Old");
+        assert_eq!(settings.resolve_title(" \t ", "Old"), "Old");
+    }
+
+    #[test]
+    fn resolve_title_uses_configured_default_when_both_are_empty() {
+        let settings = Settings {
+            default_title: "Scratch".to_string(),
+            ..Settings::default()
+        };
+        assert_eq!(settings.resolve_title("", ""), "Scratch");
+        assert_eq!(settings.resolve_title("  ", " "), "Scratch");
+        assert_eq!(Settings::default().resolve_title("", ""), "Untitled Note");
+    }
+
+    #[test]
+    fn pasted_long_titles_are_cut_with_an_ellipsis() {
+        let settings = Settings {
+            max_title_length: 40,
+            ..Settings::default()
+        };
+        let pasted = "e\u{301}".repeat(500);
+        let title = settings.resolve_title(&pasted, "Old");
+        assert_eq!(title.graphemes(true).count(), 40);
+        assert!(title.ends_with("e\u{301}…"));
+
+        let exact = "x".repeat(40);
+        assert_eq!(settings.resolve_title(&exact, "Old"), exact);
+        assert_eq!(settings.limit_title("short"), "short");
+    }
+}
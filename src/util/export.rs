@@ -0,0 +1,163 @@
+use crate::models::{LineEnding, Note, NoteRepository, SortOrder, normalize_newlines};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use super::sanitize_filename;
+
+pub fn export_notes_to_markdown(
+    notes: &[Note],
+    dir: &Path,
+    line_ending: LineEnding,
+) -> Result<usize, io::Error> {
+    std::fs::create_dir_all(dir)?;
+
+    let mut used_names = HashSet::new();
+    for note in notes {
+        let base_name = sanitize_filename(&note.title);
+        let mut file_name = format!("{}.md", base_name);
+        let mut suffix = 2;
+        while !used_names.insert(file_name.to_lowercase()) {
+            file_name = format!("{} ({}).md", base_name, suffix);
+            suffix += 1;
+        }
+
+        let body = format!("# {}\n\n{}\n", note.title, note.content);
+        std::fs::write(dir.join(file_name), line_ending.apply(&body))?;
+    }
+
+    Ok(notes.len())
+}
+
+pub fn export_notes_to_json(notes: &[Note], path: &Path) -> Result<usize, io::Error> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let json = serde_json::to_string_pretty(notes)
+        .map_err(io::Error::other)?;
+    std::fs::write(path, json)?;
+
+    Ok(notes.len())
+}
+
+/// Writes every note as one JSON object per line, straight from the database
+/// cursor so large libraries are never held in memory at once. Notes are
+/// written as stored; encrypted ones stay encrypted.
+pub fn export_notes_to_jsonl(repository: &NoteRepository, path: &Path) -> Result<usize, io::Error> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    let mut write_error = None;
+    let count = repository
+        .for_each_note(SortOrder::Manual, |note| {
+            let line = serde_json::to_writer(&mut writer, &note)
+                .map_err(io::Error::from)
+                .and_then(|_| writer.write_all(b"\n"));
+            match line {
+                Ok(()) => true,
+                Err(e) => {
+                    write_error = Some(e);
+                    false
+                }
+            }
+        })
+        .map_err(io::Error::other)?;
+    if let Some(e) = write_error {
+        return Err(e);
+    }
+    writer.flush()?;
+
+    Ok(count)
+}
+
+/// Reads notes written by `export_notes_to_jsonl` one line at a time and
+/// inserts them in a single transaction, skipping ids that already exist.
+/// Line endings in titles and plain-text content are normalized to `\n`.
+/// A malformed line stops the import there; the notes before it are kept.
+pub fn import_notes_from_jsonl(
+    repository: &NoteRepository,
+    path: &Path,
+) -> Result<usize, io::Error> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut read_error = None;
+    let notes = reader
+        .lines()
+        .enumerate()
+        .map_while(|(index, line)| {
+            let note = line.and_then(|line| {
+                if line.trim().is_empty() {
+                    return Ok(None);
+                }
+                serde_json::from_str::<Note>(&line).map(Some).map_err(|e| {
+                    io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", index + 1, e))
+                })
+            });
+            match note {
+                Ok(Some(mut note)) => {
+                    note.title = normalize_newlines(&note.title);
+                    if !note.encrypted {
+                        note.content = normalize_newlines(&note.content);
+                    }
+                    Some(Some(note))
+                }
+                Ok(None) => Some(None),
+                Err(e) => {
+                    read_error = Some(e);
+                    None
+                }
+            }
+        })
+        .flatten();
+
+    let count = repository
+        .insert_notes(notes)
+        .map_err(io::Error::other)?;
+    match read_error {
+        Some(e) => Err(e),
+        None => Ok(count),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Database;
+    use tempfile::TempDir;
+
+    #[test]
+    fn jsonl_round_trips_notes_and_reports_bad_lines() {
+        let dir = TempDir::new().unwrap();
+        let source = Database::new(dir.path().join("source.db")).unwrap();
+        for title in ["first", "second"] {
+            let mut note = Note::new(title.to_string());
+            note.content = format!("{} body\nwith two lines", title);
+            source.notes.create_note(&note).unwrap();
+        }
+
+        let path = dir.path().join("notes.jsonl");
+        assert_eq!(export_notes_to_jsonl(&source.notes, &path).unwrap(), 2);
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 2);
+
+        let target = Database::new(dir.path().join("target.db")).unwrap();
+        assert_eq!(import_notes_from_jsonl(&target.notes, &path).unwrap(), 2);
+        assert_eq!(import_notes_from_jsonl(&target.notes, &path).unwrap(), 0);
+        let contents: Vec<String> = target
+            .notes
+            .list_notes(SortOrder::Manual)
+            .unwrap()
+            .into_iter()
+            .map(|note| note.content)
+            .collect();
+        assert!(contents.contains(&"first body\nwith two lines".to_string()));
+
+        let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+        writeln!(file, "{{not json").unwrap();
+        let error = import_notes_from_jsonl(&target.notes, &path).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().starts_with("line 3:"));
+    }
+}
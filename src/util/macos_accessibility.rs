@@ -0,0 +1,79 @@
+use crate::util::{AppRequest, send_app_request};
+use block::ConcreteBlock;
+use cocoa::base::{BOOL, NO, id, nil};
+use cocoa::foundation::NSString;
+use objc::{class, msg_send, sel, sel_impl};
+
+const DISPLAY_OPTIONS_CHANGED: &str =
+    "NSWorkspaceAccessibilityDisplayOptionsDidChangeNotification";
+const ANNOUNCEMENT_REQUESTED: &str = "AXAnnouncementRequested";
+const ANNOUNCEMENT_KEY: &str = "AXAnnouncementKey";
+const PRIORITY_KEY: &str = "AXPriorityKey";
+/// `NSAccessibilityPriorityHigh`: interrupts whatever VoiceOver is reading.
+const PRIORITY_HIGH: i64 = 90;
+
+#[link(name = "AppKit", kind = "framework")]
+unsafe extern "C" {
+    fn NSAccessibilityPostNotificationWithUserInfo(element: id, notification: id, user_info: id);
+}
+
+/// Whether "Reduce motion" is on in the system Accessibility display settings.
+pub fn reduce_motion_enabled() -> bool {
+    unsafe {
+        let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+        let enabled: BOOL = msg_send![workspace, accessibilityDisplayShouldReduceMotion];
+        enabled != NO
+    }
+}
+
+/// Sends `AppRequest::ReduceMotionChanged` whenever the accessibility display
+/// options change, so the app can re-read `reduce_motion_enabled`.
+pub fn observe_reduce_motion_changes() {
+    unsafe {
+        let handler = ConcreteBlock::new(|_notification: id| {
+            send_app_request(AppRequest::ReduceMotionChanged);
+        })
+        .copy();
+
+        let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+        let center: id = msg_send![workspace, notificationCenter];
+        let name = NSString::alloc(nil).init_str(DISPLAY_OPTIONS_CHANGED);
+        let observer: id = msg_send![
+            center,
+            addObserverForName: name
+            object: nil
+            queue: nil
+            usingBlock: &*handler
+        ];
+
+        if observer == nil {
+            eprintln!("Failed to observe accessibility display option changes");
+        }
+    }
+}
+
+/// Asks VoiceOver, or any other assistive app that is running, to read
+/// `message` out. gpui windows don't expose an accessibility tree, so this
+/// is how the app tells screen reader users what is on screen.
+pub fn announce(message: &str) {
+    if message.is_empty() {
+        return;
+    }
+
+    unsafe {
+        let app: id = msg_send![class!(NSApplication), sharedApplication];
+        let window: id = msg_send![app, mainWindow];
+        let element = if window == nil { app } else { window };
+
+        let text = NSString::alloc(nil).init_str(message);
+        let priority: id = msg_send![class!(NSNumber), numberWithLongLong: PRIORITY_HIGH];
+        let announcement_key = NSString::alloc(nil).init_str(ANNOUNCEMENT_KEY);
+        let priority_key = NSString::alloc(nil).init_str(PRIORITY_KEY);
+        let user_info: id = msg_send![class!(NSMutableDictionary), dictionary];
+        let _: () = msg_send![user_info, setObject: text forKey: announcement_key];
+        let _: () = msg_send![user_info, setObject: priority forKey: priority_key];
+
+        let notification = NSString::alloc(nil).init_str(ANNOUNCEMENT_REQUESTED);
+        NSAccessibilityPostNotificationWithUserInfo(element, notification, user_info);
+    }
+}
@@ -0,0 +1,69 @@
+use crate::util::{AppRequest, send_app_request};
+use block::ConcreteBlock;
+use cocoa::base::{YES, id, nil};
+use objc::{class, msg_send, sel, sel_impl};
+
+const KEY_DOWN_MASK: u64 = 1 << 10;
+const SPACE_KEY_CODE: u16 = 49;
+
+const SHIFT_FLAG: u64 = 1 << 17;
+const CONTROL_FLAG: u64 = 1 << 18;
+const OPTION_FLAG: u64 = 1 << 19;
+const COMMAND_FLAG: u64 = 1 << 20;
+
+/// Listens for Cmd+Shift+Space everywhere. The global monitor sees the key
+/// while another app is frontmost (macOS asks for Accessibility access the
+/// first time); the local one covers Tap itself being frontmost.
+pub fn register_quick_capture_hotkey() {
+    unsafe {
+        let global_handler = ConcreteBlock::new(|event: id| {
+            if is_quick_capture(event) {
+                request_quick_capture();
+            }
+        })
+        .copy();
+        let global_monitor: id = msg_send![
+            class!(NSEvent),
+            addGlobalMonitorForEventsMatchingMask: KEY_DOWN_MASK
+            handler: &*global_handler
+        ];
+
+        let local_handler = ConcreteBlock::new(|event: id| -> id {
+            if is_quick_capture(event) {
+                request_quick_capture();
+                return nil;
+            }
+            event
+        })
+        .copy();
+        let local_monitor: id = msg_send![
+            class!(NSEvent),
+            addLocalMonitorForEventsMatchingMask: KEY_DOWN_MASK
+            handler: &*local_handler
+        ];
+
+        if global_monitor == nil || local_monitor == nil {
+            eprintln!("Failed to register the quick capture hotkey");
+        } else {
+            println!("Registered Cmd+Shift+Space for quick capture");
+        }
+    }
+}
+
+fn is_quick_capture(event: id) -> bool {
+    unsafe {
+        let key_code: u16 = msg_send![event, keyCode];
+        let flags: u64 = msg_send![event, modifierFlags];
+        let modifiers = flags & (SHIFT_FLAG | CONTROL_FLAG | OPTION_FLAG | COMMAND_FLAG);
+        key_code == SPACE_KEY_CODE && modifiers == COMMAND_FLAG | SHIFT_FLAG
+    }
+}
+
+fn request_quick_capture() {
+    send_app_request(AppRequest::QuickCapture);
+
+    unsafe {
+        let app: id = msg_send![class!(NSApplication), sharedApplication];
+        let _: () = msg_send![app, activateIgnoringOtherApps: YES];
+    }
+}
@@ -1,4 +1,4 @@
-use crate::util::NOTE_TO_DELETE;
+use crate::util::{AppRequest, send_app_request};
 use block::ConcreteBlock;
 use cocoa::appkit::{NSEvent, NSEventType, NSMenu, NSMenuItem};
 use cocoa::base::{NO, YES, id, nil, selector};
@@ -8,6 +8,7 @@ use core_foundation::string::{CFString, CFStringRef};
 use objc::runtime::{Class, Object};
 use objc::{class, msg_send, sel, sel_impl};
 use std::os::raw::c_void;
+use std::panic::AssertUnwindSafe;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::thread;
@@ -15,16 +16,34 @@ use uuid::Uuid;
 
 pub struct ContextMenu {
     menu: id,
-    direct_delete_callback: Option<DirectDeleteCallback>,
 }
 
 pub enum MenuAction {
     Delete(Uuid),
+    MoveToTop(Uuid),
+    MoveToBottom(Uuid),
+    CopyNote(Uuid),
+    CopyContent(Uuid),
+    ToggleLocked(Uuid),
+    ToggleStarred(Uuid),
+    ToggleArchived(Uuid),
+    CopyLink(Uuid),
+    /// From the menu bar status item.
+    OpenNote(Uuid),
+    NewNote,
 }
 
-pub type MenuCallback = Box<dyn Fn(MenuAction) + Send + 'static>;
+const DELETE_TAG: i64 = 1;
+const MOVE_TO_TOP_TAG: i64 = 2;
+const MOVE_TO_BOTTOM_TAG: i64 = 3;
+const COPY_NOTE_TAG: i64 = 4;
+const COPY_CONTENT_TAG: i64 = 5;
+const TOGGLE_LOCKED_TAG: i64 = 6;
+const TOGGLE_STARRED_TAG: i64 = 7;
+const TOGGLE_ARCHIVED_TAG: i64 = 8;
+const COPY_LINK_TAG: i64 = 9;
 
-pub type DirectDeleteCallback = Box<dyn Fn(Uuid) -> bool + Send + 'static>;
+pub type MenuCallback = Box<dyn Fn(MenuAction) + Send + 'static>;
 
 impl ContextMenu {
     pub fn new() -> Self {
@@ -32,20 +51,53 @@ impl ContextMenu {
             let menu: id = msg_send![class!(NSMenu), new];
             let _: () = msg_send![menu, setAutoenablesItems:NO];
 
-            Self {
-                menu,
-                direct_delete_callback: None,
-            }
+            Self { menu }
         }
     }
 
     pub fn add_delete_item(&mut self, title: &str, note_id: Uuid) -> &mut Self {
+        self.add_note_item(title, note_id, DELETE_TAG)
+    }
+
+    pub fn add_move_to_top_item(&mut self, title: &str, note_id: Uuid) -> &mut Self {
+        self.add_note_item(title, note_id, MOVE_TO_TOP_TAG)
+    }
+
+    pub fn add_move_to_bottom_item(&mut self, title: &str, note_id: Uuid) -> &mut Self {
+        self.add_note_item(title, note_id, MOVE_TO_BOTTOM_TAG)
+    }
+
+    pub fn add_copy_note_item(&mut self, title: &str, note_id: Uuid) -> &mut Self {
+        self.add_note_item(title, note_id, COPY_NOTE_TAG)
+    }
+
+    pub fn add_copy_content_item(&mut self, title: &str, note_id: Uuid) -> &mut Self {
+        self.add_note_item(title, note_id, COPY_CONTENT_TAG)
+    }
+
+    pub fn add_toggle_locked_item(&mut self, title: &str, note_id: Uuid) -> &mut Self {
+        self.add_note_item(title, note_id, TOGGLE_LOCKED_TAG)
+    }
+
+    pub fn add_toggle_starred_item(&mut self, title: &str, note_id: Uuid) -> &mut Self {
+        self.add_note_item(title, note_id, TOGGLE_STARRED_TAG)
+    }
+
+    pub fn add_toggle_archived_item(&mut self, title: &str, note_id: Uuid) -> &mut Self {
+        self.add_note_item(title, note_id, TOGGLE_ARCHIVED_TAG)
+    }
+
+    pub fn add_copy_link_item(&mut self, title: &str, note_id: Uuid) -> &mut Self {
+        self.add_note_item(title, note_id, COPY_LINK_TAG)
+    }
+
+    fn add_note_item(&mut self, title: &str, note_id: Uuid, tag: i64) -> &mut Self {
         unsafe {
             let title_ns = NSString::alloc(nil).init_str(title);
             let menu_item: id = msg_send![class!(NSMenuItem), alloc];
             let menu_item: id = msg_send![menu_item, initWithTitle:title_ns action:selector("menuItemClicked:") keyEquivalent:NSString::alloc(nil).init_str("")];
 
-            let _: () = msg_send![menu_item, setTag:1];
+            let _: () = msg_send![menu_item, setTag:tag];
 
             let note_id_str = note_id.to_string();
             let note_id_ns = NSString::alloc(nil).init_str(&note_id_str);
@@ -59,7 +111,7 @@ impl ContextMenu {
 
     pub fn show_at_position(&self, x: f64, y: f64, callback: MenuCallback) {
         unsafe {
-            let cls = define_menu_handler_class(&callback, &self.direct_delete_callback);
+            let cls = define_menu_handler_class(&callback);
             let handler: id = msg_send![cls, alloc];
             let handler: id = msg_send![handler, init];
 
@@ -94,20 +146,27 @@ impl ContextMenu {
             let _: () = msg_send![main_queue, addOperationWithBlock:block];
         }
     }
+}
 
-    pub fn set_direct_delete_callback<F>(&mut self, callback: F) -> &mut Self
-    where
-        F: Fn(Uuid) -> bool + Send + 'static,
-    {
-        self.direct_delete_callback = Some(Box::new(callback));
-        self
+/// Runs the body of a method AppKit calls into. A panic must not unwind
+/// through the Objective-C runtime, so it is caught and logged here and
+/// `fallback` returned in its place.
+pub(crate) fn guard_ffi<R>(method: &str, fallback: R, body: impl FnOnce() -> R) -> R {
+    match std::panic::catch_unwind(AssertUnwindSafe(body)) {
+        Ok(result) => result,
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .copied()
+                .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+                .unwrap_or("non-string panic payload");
+            eprintln!("Panic in menu handler {}: {}", method, message);
+            fallback
+        }
     }
 }
 
-fn define_menu_handler_class(
-    callback: &MenuCallback,
-    direct_delete_callback: &Option<DirectDeleteCallback>,
-) -> *const Class {
+fn define_menu_handler_class(callback: &MenuCallback) -> *const Class {
     use std::sync::Once;
     static mut DELEGATE_CLASS: *const Class = 0 as *const Class;
     static INIT: Once = Once::new();
@@ -117,99 +176,43 @@ fn define_menu_handler_class(
         let mut decl = objc::declare::ClassDecl::new("RustMenuHandler", superclass).unwrap();
 
         decl.add_ivar::<*mut c_void>("callback");
-        decl.add_ivar::<*mut c_void>("directDeleteCallback");
 
-        extern "C" fn menu_item_clicked(this: &Object, _: objc::runtime::Sel, sender: id) {
-            unsafe {
-                println!("Menu item clicked!");
+        extern "C" fn menu_item_clicked(_this: &Object, _: objc::runtime::Sel, sender: id) {
+            guard_ffi("menuItemClicked:", (), || unsafe {
                 let tag: i64 = msg_send![sender, tag];
-                println!("Menu item tag: {}", tag);
-                if tag != 1 {
-                    println!("Not a delete action, tag is {}", tag);
-                    return;
-                }
+                let action: fn(Uuid) -> MenuAction = match tag {
+                    DELETE_TAG => MenuAction::Delete,
+                    MOVE_TO_TOP_TAG => MenuAction::MoveToTop,
+                    MOVE_TO_BOTTOM_TAG => MenuAction::MoveToBottom,
+                    COPY_NOTE_TAG => MenuAction::CopyNote,
+                    COPY_CONTENT_TAG => MenuAction::CopyContent,
+                    TOGGLE_LOCKED_TAG => MenuAction::ToggleLocked,
+                    TOGGLE_STARRED_TAG => MenuAction::ToggleStarred,
+                    TOGGLE_ARCHIVED_TAG => MenuAction::ToggleArchived,
+                    COPY_LINK_TAG => MenuAction::CopyLink,
+                    _ => {
+                        println!("Unknown menu item tag: {}", tag);
+                        return;
+                    }
+                };
 
                 let note_id_obj: id = msg_send![sender, representedObject];
                 if note_id_obj == nil {
-                    println!("note_id_obj is nil");
                     return;
                 }
 
-                let ns_string: id = note_id_obj;
-                let note_id_cstr: *const std::os::raw::c_char = msg_send![ns_string, UTF8String];
+                let note_id_cstr: *const std::os::raw::c_char = msg_send![note_id_obj, UTF8String];
                 let note_id_rust = std::ffi::CStr::from_ptr(note_id_cstr)
                     .to_str()
                     .unwrap_or("");
 
-                println!("Note ID from menu: {}", note_id_rust);
-
+                // Only send the action and return; the app's request
+                // listener handles it and re-renders from there.
                 match Uuid::parse_str(note_id_rust) {
-                    Ok(note_id) => {
-                        println!("Successfully parsed UUID: {}", note_id);
-
-                        let direct_callback_ptr: *mut c_void =
-                            *this.get_ivar("directDeleteCallback");
-                        if !direct_callback_ptr.is_null() {
-                            println!("Found direct delete callback, trying it first");
-                            let direct_callback =
-                                &*(direct_callback_ptr as *const DirectDeleteCallback);
-
-                            if direct_callback(note_id) {
-                                println!("Direct deletion succeeded!");
-                                return;
-                            } else {
-                                println!("Direct deletion failed, trying alternative methods");
-                            }
-                        } else {
-                            println!("No direct delete callback available");
-                        }
-
-                        println!("Setting note {} for direct deletion", note_id);
-                        if let Ok(mut guard) = NOTE_TO_DELETE.lock() {
-                            *guard = Some(note_id);
-                            println!("NOTE FOR DELETION SET DIRECTLY: {}", note_id);
-
-                            drop(guard);
-
-                            for i in 0..5 {
-                                if i > 0 {
-                                    std::thread::sleep(std::time::Duration::from_millis(
-                                        50 * i as u64,
-                                    ));
-                                }
-
-                                println!("Forcing UI refresh attempt {}", i + 1);
-                                let app: id = msg_send![class!(NSApplication), sharedApplication];
-                                let _: () = msg_send![app, updateWindows];
-
-                                if let Ok(check_guard) = NOTE_TO_DELETE.lock() {
-                                    if check_guard.is_none() {
-                                        println!("Deletion was processed on attempt {}", i + 1);
-                                        break;
-                                    }
-                                }
-                            }
-                        } else {
-                            println!("Failed to lock NOTE_TO_DELETE mutex for direct deletion");
-                        }
-
-                        let callback_ptr: *mut c_void = *this.get_ivar("callback");
-
-                        if callback_ptr.is_null() {
-                            println!("ERROR: callback_ptr is null!");
-                            return;
-                        }
-
-                        let callback = &*(callback_ptr as *const MenuCallback);
-                        println!("Calling callback for delete action");
-                        callback(MenuAction::Delete(note_id));
-                        println!("Callback completed");
-                    }
-                    Err(e) => {
-                        println!("Failed to parse UUID: {}", e);
-                    }
+                    Ok(note_id) => send_app_request(AppRequest::Menu(action(note_id))),
+                    Err(e) => println!("Failed to parse UUID: {}", e),
                 }
-            }
+            })
         }
 
         decl.add_method(
@@ -218,17 +221,15 @@ fn define_menu_handler_class(
         );
 
         extern "C" fn init_with_callback(this: &mut Object, _: objc::runtime::Sel) -> id {
-            unsafe {
+            guard_ffi("init", nil, || unsafe {
                 let this_ptr: id = msg_send![super(this, class!(NSObject)), init];
                 if this_ptr != nil {
                     let callback_box = Box::new(Box::new(|_: MenuAction| {}) as MenuCallback);
                     let callback_ptr = Box::into_raw(callback_box) as *mut c_void;
                     this.set_ivar("callback", callback_ptr);
-
-                    this.set_ivar("directDeleteCallback", std::ptr::null_mut() as *mut c_void);
                 }
                 this_ptr
-            }
+            })
         }
 
         decl.add_method(
@@ -237,17 +238,16 @@ fn define_menu_handler_class(
         );
 
         extern "C" fn dealloc(this: &mut Object, _: objc::runtime::Sel) {
-            unsafe {
+            // Dropping the callback runs its captured destructors; the
+            // superclass still has to be deallocated if one of those panics.
+            guard_ffi("dealloc", (), || unsafe {
                 let callback_ptr: *mut c_void = *this.get_ivar("callback");
                 if !callback_ptr.is_null() {
                     let _ = Box::from_raw(callback_ptr as *mut MenuCallback);
                 }
+            });
 
-                let direct_callback_ptr: *mut c_void = *this.get_ivar("directDeleteCallback");
-                if !direct_callback_ptr.is_null() {
-                    let _ = Box::from_raw(direct_callback_ptr as *mut DirectDeleteCallback);
-                }
-
+            unsafe {
                 let _: () = msg_send![super(this, class!(NSObject)), dealloc];
             }
         }
@@ -275,19 +275,6 @@ fn define_menu_handler_class(
         let new_callback_ptr = Box::into_raw(new_callback_box) as *mut c_void;
         handler_obj.set_ivar("callback", new_callback_ptr);
 
-        if let Some(direct_callback) = direct_delete_callback {
-            let direct_callback_ptr_ivar: *mut c_void =
-                *handler_obj.get_ivar("directDeleteCallback");
-            if !direct_callback_ptr_ivar.is_null() {
-                let _old_direct_callback =
-                    Box::from_raw(direct_callback_ptr_ivar as *mut DirectDeleteCallback);
-            }
-
-            let new_direct_callback_box = Box::new(direct_callback.clone());
-            let new_direct_callback_ptr = Box::into_raw(new_direct_callback_box) as *mut c_void;
-            handler_obj.set_ivar("directDeleteCallback", new_direct_callback_ptr);
-        }
-
         cls
     }
 }
@@ -0,0 +1,75 @@
+use cocoa::base::{NO, YES, id, nil};
+use cocoa::foundation::{NSPoint, NSRange, NSRect, NSSize, NSString};
+use objc::{class, msg_send, sel, sel_impl};
+use std::io;
+use std::path::Path;
+
+const PAGE_MARGIN: f64 = 54.0;
+const BODY_FONT_SIZE: f64 = 12.0;
+const TITLE_FONT_SIZE: f64 = 18.0;
+
+pub fn export_note_to_pdf(title: &str, content: &str, path: &Path) -> Result<(), io::Error> {
+    let path_str = path
+        .to_str()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid export path"))?;
+
+    unsafe {
+        let shared_info: id = msg_send![class!(NSPrintInfo), sharedPrintInfo];
+        let print_info: id = msg_send![shared_info, copy];
+
+        let url: id = msg_send![class!(NSURL), fileURLWithPath:NSString::alloc(nil).init_str(path_str)];
+        let dictionary: id = msg_send![print_info, dictionary];
+        let _: () = msg_send![dictionary, setObject:url forKey:NSString::alloc(nil).init_str("NSJobSavingURL")];
+        let _: () = msg_send![print_info, setJobDisposition:NSString::alloc(nil).init_str("NSPrintSaveJob")];
+
+        let _: () = msg_send![print_info, setTopMargin:PAGE_MARGIN];
+        let _: () = msg_send![print_info, setBottomMargin:PAGE_MARGIN];
+        let _: () = msg_send![print_info, setLeftMargin:PAGE_MARGIN];
+        let _: () = msg_send![print_info, setRightMargin:PAGE_MARGIN];
+        let _: () = msg_send![print_info, setHorizontallyCentered:NO];
+        let _: () = msg_send![print_info, setVerticallyCentered:NO];
+
+        let paper_size: NSSize = msg_send![print_info, paperSize];
+        let frame = NSRect::new(
+            NSPoint::new(0.0, 0.0),
+            NSSize::new(
+                paper_size.width - PAGE_MARGIN * 2.0,
+                paper_size.height - PAGE_MARGIN * 2.0,
+            ),
+        );
+
+        let text_view: id = msg_send![class!(NSTextView), alloc];
+        let text_view: id = msg_send![text_view, initWithFrame:frame];
+        let _: () = msg_send![text_view, setVerticallyResizable:YES];
+        let _: () = msg_send![text_view, setHorizontallyResizable:NO];
+
+        let document = format!("{}\n\n{}", title, content);
+        let _: () = msg_send![text_view, setString:NSString::alloc(nil).init_str(&document)];
+
+        let body_font: id = msg_send![class!(NSFont), systemFontOfSize:BODY_FONT_SIZE];
+        let title_font: id = msg_send![class!(NSFont), boldSystemFontOfSize:TITLE_FONT_SIZE];
+        let document_len = document.encode_utf16().count() as u64;
+        let title_len = title.encode_utf16().count() as u64;
+        let _: () = msg_send![text_view, setFont:body_font range:NSRange::new(0, document_len)];
+        let _: () = msg_send![text_view, setFont:title_font range:NSRange::new(0, title_len)];
+        let _: () = msg_send![text_view, sizeToFit];
+
+        let operation: id = msg_send![
+            class!(NSPrintOperation),
+            printOperationWithView:text_view
+            printInfo:print_info
+        ];
+        let _: () = msg_send![operation, setShowsPrintPanel:NO];
+        let _: () = msg_send![operation, setShowsProgressPanel:NO];
+
+        let succeeded: bool = msg_send![operation, runOperation];
+        if !succeeded {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Print operation did not complete",
+            ));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,164 @@
+use crate::util::{AppRequest, send_app_request};
+use crate::util::macos_menu::{MenuAction, guard_ffi};
+use cocoa::base::{NO, YES, id, nil, selector};
+use cocoa::foundation::NSString;
+use objc::runtime::{Class, Object, Sel};
+use objc::{class, msg_send, sel, sel_impl};
+use std::cell::Cell;
+use std::sync::Once;
+use uuid::Uuid;
+
+/// `NSVariableStatusItemLength`: the item sizes itself to its button.
+const VARIABLE_LENGTH: f64 = -1.0;
+
+/// Menu titles longer than this are cut so the menu stays a sensible width.
+const MAX_ITEM_TITLE_CHARS: usize = 48;
+
+thread_local! {
+    static STATUS_ITEM: Cell<id> = const { Cell::new(nil) };
+    static HANDLER: Cell<id> = const { Cell::new(nil) };
+}
+
+/// Shows Tap in the menu bar with a menu of `notes` (id and title, in
+/// order) followed by "New Note". The item is created on the first call and
+/// its menu rebuilt on every later one. Must run on the main thread.
+pub fn update_status_item(notes: &[(Uuid, String)]) {
+    unsafe {
+        let handler = status_menu_handler();
+        let menu: id = msg_send![class!(NSMenu), new];
+        let _: () = msg_send![menu, setAutoenablesItems: NO];
+
+        if notes.is_empty() {
+            let item = add_item(menu, "No starred notes", "openNote:", handler);
+            let _: () = msg_send![item, setEnabled: NO];
+        }
+        for (note_id, title) in notes {
+            let item = add_item(menu, &item_title(title), "openNote:", handler);
+            let note_id = NSString::alloc(nil).init_str(&note_id.to_string());
+            let _: () = msg_send![item, setRepresentedObject: note_id];
+        }
+
+        let separator: id = msg_send![class!(NSMenuItem), separatorItem];
+        let _: () = msg_send![menu, addItem: separator];
+        add_item(menu, "New Note", "newNote:", handler);
+
+        let status_item = status_item();
+        let _: () = msg_send![status_item, setMenu: menu];
+        let _: () = msg_send![menu, release];
+    }
+}
+
+fn item_title(title: &str) -> String {
+    if title.chars().count() <= MAX_ITEM_TITLE_CHARS {
+        return title.to_string();
+    }
+    let cut: String = title.chars().take(MAX_ITEM_TITLE_CHARS - 1).collect();
+    format!("{}…", cut.trim_end())
+}
+
+fn add_item(menu: id, title: &str, action: &str, handler: id) -> id {
+    unsafe {
+        let title = NSString::alloc(nil).init_str(title);
+        let key = NSString::alloc(nil).init_str("");
+        let item: id = msg_send![class!(NSMenuItem), alloc];
+        let item: id =
+            msg_send![item, initWithTitle: title action: selector(action) keyEquivalent: key];
+        let _: () = msg_send![item, setTarget: handler];
+        let _: () = msg_send![menu, addItem: item];
+        let _: () = msg_send![item, release];
+        item
+    }
+}
+
+/// The menu bar item, created and retained on first use.
+fn status_item() -> id {
+    STATUS_ITEM.with(|cell| {
+        if cell.get() != nil {
+            return cell.get();
+        }
+
+        unsafe {
+            let status_bar: id = msg_send![class!(NSStatusBar), systemStatusBar];
+            let item: id = msg_send![status_bar, statusItemWithLength: VARIABLE_LENGTH];
+            let item: id = msg_send![item, retain];
+
+            let button: id = msg_send![item, button];
+            let symbol = NSString::alloc(nil).init_str("note.text");
+            let description = NSString::alloc(nil).init_str("Tap");
+            let image: id = msg_send![
+                class!(NSImage),
+                imageWithSystemSymbolName: symbol
+                accessibilityDescription: description
+            ];
+            if image != nil {
+                let _: () = msg_send![image, setTemplate: YES];
+                let _: () = msg_send![button, setImage: image];
+            } else {
+                let _: () = msg_send![button, setTitle: description];
+            }
+
+            cell.set(item);
+            item
+        }
+    })
+}
+
+/// The target of every status menu item. It lives as long as the app.
+fn status_menu_handler() -> id {
+    HANDLER.with(|cell| {
+        if cell.get() == nil {
+            unsafe {
+                let handler: id = msg_send![status_menu_handler_class(), new];
+                cell.set(handler);
+            }
+        }
+        cell.get()
+    })
+}
+
+fn status_menu_handler_class() -> *const Class {
+    static mut HANDLER_CLASS: *const Class = 0 as *const Class;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| unsafe {
+        let mut decl =
+            objc::declare::ClassDecl::new("TapStatusMenuHandler", class!(NSObject)).unwrap();
+
+        extern "C" fn open_note(_this: &Object, _: Sel, sender: id) {
+            guard_ffi("openNote:", (), || unsafe {
+                let note_id: id = msg_send![sender, representedObject];
+                if note_id == nil {
+                    return;
+                }
+                let note_id: *const std::os::raw::c_char = msg_send![note_id, UTF8String];
+                let note_id = std::ffi::CStr::from_ptr(note_id).to_str().unwrap_or("");
+                match Uuid::parse_str(note_id) {
+                    Ok(note_id) => queue_action(MenuAction::OpenNote(note_id)),
+                    Err(e) => println!("Failed to parse UUID: {}", e),
+                }
+            })
+        }
+
+        extern "C" fn new_note(_this: &Object, _: Sel, _sender: id) {
+            guard_ffi("newNote:", (), || queue_action(MenuAction::NewNote))
+        }
+
+        decl.add_method(sel!(openNote:), open_note as extern "C" fn(&Object, Sel, id));
+        decl.add_method(sel!(newNote:), new_note as extern "C" fn(&Object, Sel, id));
+
+        HANDLER_CLASS = decl.register();
+    });
+
+    unsafe { HANDLER_CLASS }
+}
+
+/// Hands the action to the app's request listener and brings Tap forward,
+/// since the menu bar is usable while another app is frontmost.
+fn queue_action(action: MenuAction) {
+    send_app_request(AppRequest::Menu(action));
+
+    unsafe {
+        let app: id = msg_send![class!(NSApplication), sharedApplication];
+        let _: () = msg_send![app, activateIgnoringOtherApps: YES];
+    }
+}
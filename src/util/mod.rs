@@ -1,18 +1,210 @@
+pub mod export;
+pub mod macos_accessibility;
+pub mod macos_hotkey;
 pub mod macos_menu;
+pub mod macos_pdf;
+pub mod macos_status_item;
 
+use futures::channel::mpsc::{UnboundedReceiver, UnboundedSender, unbounded};
 use lazy_static::lazy_static;
+use macos_menu::MenuAction;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use std::io;
 use std::path::PathBuf;
-use std::sync::Mutex;
+use std::sync::{Mutex, OnceLock};
 use uuid::Uuid;
 
+/// Something asked of the app from outside gpui. These arrive in Cocoa
+/// callbacks with no app context to act in, so they're sent to a listener
+/// the app runs.
+pub enum AppRequest {
+    Menu(MenuAction),
+    /// The system-wide capture hotkey was pressed.
+    QuickCapture,
+    /// The accessibility display options changed; Reduce Motion may have.
+    ReduceMotionChanged,
+    /// A `tap://note/<uuid>` link was opened.
+    OpenUrl(String),
+}
+
 lazy_static! {
-    pub static ref NOTE_TO_DELETE: Mutex<Option<Uuid>> = Mutex::new(None);
+    static ref APP_REQUESTS: (
+        UnboundedSender<AppRequest>,
+        Mutex<Option<UnboundedReceiver<AppRequest>>>
+    ) = {
+        let (sender, receiver) = unbounded();
+        (sender, Mutex::new(Some(receiver)))
+    };
+}
+
+/// Hands `request` to the app. Requests sent before the app starts listening
+/// wait in the channel.
+pub fn send_app_request(request: AppRequest) {
+    if APP_REQUESTS.0.unbounded_send(request).is_err() {
+        eprintln!("The app has stopped listening for requests");
+    }
+}
+
+/// The receiving end for `send_app_request`. Handed out once.
+pub fn take_app_requests() -> Option<UnboundedReceiver<AppRequest>> {
+    APP_REQUESTS.1.lock().ok()?.take()
+}
+
+/// Where Tap keeps its database and side files. Prefers `~/.tap`, then the
+/// platform data directories, then the temp dir; the first one that can be
+/// created wins. Resolved once per process.
+pub fn get_data_dir() -> PathBuf {
+    static DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+    DATA_DIR.get_or_init(resolve_data_dir).clone()
+}
+
+fn resolve_data_dir() -> PathBuf {
+    let env_dir = |name: &str| {
+        std::env::var_os(name)
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+    };
+
+    let candidates = [
+        ("home directory", dirs::home_dir().map(|home| home.join(".tap"))),
+        ("$XDG_DATA_HOME", env_dir("XDG_DATA_HOME").map(|dir| dir.join("tap"))),
+        ("%APPDATA%", env_dir("APPDATA").map(|dir| dir.join("Tap"))),
+    ];
+
+    for (index, (source, dir)) in candidates.into_iter().enumerate() {
+        let Some(dir) = dir else {
+            continue;
+        };
+        match std::fs::create_dir_all(&dir) {
+            Ok(()) => {
+                if index == 0 {
+                    println!("Using data directory {:?}", dir);
+                } else {
+                    eprintln!(
+                        "Home directory unavailable, using data directory {:?} from the {}",
+                        dir, source
+                    );
+                }
+                return dir;
+            }
+            Err(e) => eprintln!("Cannot use data directory {:?} ({}): {}", dir, source, e),
+        }
+    }
+
+    // A fixed name, so notes written here are found again after a restart.
+    let dir = std::env::temp_dir().join("tap");
+    if let Err(e) = std::fs::create_dir_all(&dir) {
+        eprintln!("Cannot create data directory {:?}: {}", dir, e);
+    }
+    eprintln!(
+        "WARNING: no home or data directory is writable. Notes are stored in {:?} and may be \
+         lost when the system clears temporary files.",
+        dir
+    );
+    dir
 }
 
 pub fn get_db_path() -> PathBuf {
-    let home_dir = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
-    home_dir.join(".tap").join("notes.db")
+    get_data_dir().join("notes.db")
+}
+
+pub fn get_attachments_dir() -> PathBuf {
+    get_data_dir().join("attachments")
+}
+
+pub fn get_session_path() -> PathBuf {
+    get_data_dir().join("session.json")
+}
+
+pub fn get_settings_path() -> PathBuf {
+    get_data_dir().join("settings.json")
+}
+
+pub fn save_attachment_bytes(
+    id: Uuid,
+    extension: &str,
+    bytes: &[u8],
+) -> Result<PathBuf, io::Error> {
+    let dir = get_attachments_dir();
+    std::fs::create_dir_all(&dir)?;
+
+    let path = dir.join(format!("{}.{}", id, extension));
+    std::fs::write(&path, bytes)?;
+    Ok(path)
+}
+
+/// Signals `sender` whenever any of `paths` changes. The files share a
+/// directory, which is what gets watched, so they may not exist yet.
+pub fn watch_files(
+    paths: &[PathBuf],
+    sender: UnboundedSender<()>,
+) -> notify::Result<RecommendedWatcher> {
+    let watched_paths = paths.to_vec();
+    let mut watcher = notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+        match result {
+            Ok(event) => {
+                if !matches!(event.kind, EventKind::Access(_))
+                    && event.paths.iter().any(|p| watched_paths.contains(p))
+                {
+                    let _ = sender.unbounded_send(());
+                }
+            }
+            Err(e) => eprintln!("File watcher error: {}", e),
+        }
+    })?;
+
+    if let Some(path) = paths.first() {
+        let watch_dir = path.parent().unwrap_or(path);
+        watcher.watch(watch_dir, RecursiveMode::NonRecursive)?;
+    }
+
+    Ok(watcher)
+}
+
+/// The `tap://note/<uuid>` link that `note_id_from_url` reads back.
+pub fn note_url(id: Uuid) -> String {
+    format!("tap://note/{}", id)
+}
+
+pub fn note_id_from_url(url: &str) -> Result<Uuid, String> {
+    let path = url
+        .strip_prefix("tap://note/")
+        .ok_or_else(|| format!("Unsupported link: {}", url))?;
+    let id = path.split('?').next().unwrap_or(path).trim_end_matches('/');
+    Uuid::parse_str(id).map_err(|_| format!("Invalid note id in link: {}", id))
+}
+
+/// The one-based line asked for by a `?line=N` suffix on a note link.
+pub fn line_from_url(url: &str) -> Option<usize> {
+    let (_, query) = url.split_once('?')?;
+    query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("line="))
+        .and_then(|line| line.parse().ok())
+}
+
+pub fn sanitize_filename(title: &str) -> String {
+    let sanitized: String = title
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+
+    let sanitized = sanitized.trim().trim_start_matches('.').to_string();
+    if sanitized.is_empty() {
+        "Untitled".to_string()
+    } else {
+        sanitized
+    }
+}
+
+pub fn get_export_dir() -> PathBuf {
+    dirs::document_dir()
+        .or_else(dirs::home_dir)
+        .unwrap_or_else(|| PathBuf::from("."))
 }
 
 pub fn dump_db_contents() -> Result<(), io::Error> {
@@ -67,3 +259,16 @@ pub fn dump_db_contents() -> Result<(), io::Error> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn note_links_read_back_as_their_note() {
+        let id = Uuid::new_v4();
+        assert_eq!(note_id_from_url(&note_url(id)), Ok(id));
+        assert_eq!(note_id_from_url(&format!("{}?line=3", note_url(id))), Ok(id));
+        assert_eq!(line_from_url(&note_url(id)), None);
+    }
+}